@@ -0,0 +1,15 @@
+/// a + b, with instructions that are never executed
+
+{11111011};
+
+I = {q0};
+F = {q3};
+compose = { sum };
+
+// Only reached through the instructions of sum
+(q2, 0, 0, R, q3);
+
+// Never reached
+(r0, 1, 1, R, r1);
+(r1, 1, 0, R, r2);
+(r2, 0, 1, L, r0);
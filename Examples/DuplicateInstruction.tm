@@ -0,0 +1,12 @@
+/// a + b, with an instruction given twice
+
+{11111011};
+
+I = {p0};
+F = {p2};
+
+(p0, 1, 0, R, p1);
+
+(p1, 1, 1, R, p1);
+(p1, 0, 0, R, p2);
+(p1, 1, 1, R, p1);
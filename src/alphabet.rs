@@ -0,0 +1,819 @@
+use log::{debug, error, info, warn};
+use pest::{iterators::Pair, Parser};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{
+    turing::{list_items, parse_description, symbol_name, Rule},
+    warnings::ErrorPosition,
+    CompilerError, CompilerWarning, Library, Movement, TuringMachine, TuringOutput, TuringParser,
+};
+
+/// The maximum number of symbols of an alphabet, so that every symbol fits in a `Symbol`
+const MAX_SYMBOLS: usize = u8::MAX as usize + 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A symbol of an alphabet, interned as its index in the alphabet. Use `Alphabet::name` to get its name
+pub struct Symbol(u8);
+
+impl Symbol {
+    /// Returns the index of the symbol in its alphabet
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The symbols a machine can read and write. The first symbol is the blank one,
+/// which fills the tape around the cells given in the code
+pub struct Alphabet {
+    symbols: Vec<String>,
+    mark: Symbol,
+}
+
+impl Default for Alphabet {
+    /// The binary alphabet of `TuringMachine`, with `0` as the blank and `1` as the mark
+    fn default() -> Self {
+        Self {
+            symbols: vec![String::from("0"), String::from("1")],
+            mark: Symbol(1),
+        }
+    }
+}
+
+impl Alphabet {
+    /// Returns the symbol with the given name, or None if it is not in the alphabet
+    pub fn symbol(&self, name: &str) -> Option<Symbol> {
+        self.symbols
+            .iter()
+            .position(|s| s == name)
+            .map(|i| Symbol(i as u8))
+    }
+
+    /// Returns the name of a symbol of the alphabet
+    pub fn name(&self, symbol: Symbol) -> &str {
+        &self.symbols[symbol.index()]
+    }
+
+    /// Returns the blank symbol, the first one of the alphabet
+    pub fn blank(&self) -> Symbol {
+        Symbol(0)
+    }
+
+    /// Returns the symbol counted by `values` and `tape_value`
+    pub fn mark(&self) -> Symbol {
+        self.mark
+    }
+
+    /// Returns the number of symbols of the alphabet
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Returns true if the alphabet has no symbols, which never happens in a compiled machine
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Returns the names of the symbols, in the order they were declared
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    /// Returns the symbol of a record, giving an error if it is not in the alphabet
+    fn parse(&self, record: &Pair<Rule>) -> Result<Symbol, CompilerError> {
+        let name = symbol_name(record.as_str());
+
+        self.symbol(name).ok_or_else(|| {
+            error!("The symbol {} is not in the alphabet", name);
+
+            CompilerError::SyntaxError {
+                position: record.into(),
+                message: format!("The symbol {} is not in the alphabet", name),
+                code: String::from(record.as_str()),
+                expected: Rule::alphabet,
+                found: Some(Rule::symbol),
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// An instruction of a machine over an alphabet
+pub struct SymbolInstruction {
+    pub from_state: String,
+    pub from_symbol: Symbol,
+    pub to_symbol: Symbol,
+    pub movement: Movement,
+    pub to_state: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The result of executing a single step of a machine over an alphabet
+pub enum SymbolStep {
+    /// The step was executed and the machine is not in a final state
+    Continued,
+
+    /// The step was executed and the machine is in a final state
+    Halted,
+
+    /// There is no instruction for the current state and symbol, so no step was executed
+    Undefined {
+        /// The state the machine is stuck in
+        state: String,
+        /// The symbol read from the tape
+        symbol: Symbol,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The result of running a machine over an alphabet for a bounded number of steps
+pub enum SymbolResult {
+    /// The machine executed the halting step of a final state
+    Halted { steps: usize },
+
+    /// The machine executed the maximum number of steps without halting
+    StepLimitReached { steps: usize },
+
+    /// There is no instruction for the current state and symbol, and the state is not final
+    Stuck {
+        /// The state the machine got stuck in
+        state: String,
+        /// The symbol read from the tape
+        symbol: Symbol,
+        /// The number of steps executed before getting stuck
+        steps: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// A Turing machine whose tape holds the symbols of an alphabet instead of bits.
+///
+/// Its code declares the symbols with `alphabet = {B, 0, 1, X};`, and optionally the symbol counted as the output
+/// with `mark = X;` (`1` by default). Without an `alphabet` declaration the alphabet is `{0, 1}`,
+/// so the code of a `TuringMachine` compiles to the same machine. Libraries can be composed if the alphabet has a `0` and a `1`
+pub struct SymbolMachine {
+    /// The instructions of the machine, by state and symbol read
+    pub instructions: HashMap<(String, Symbol), SymbolInstruction>,
+
+    /// The final states of the machine
+    pub final_states: Vec<String>,
+
+    /// The current state of the machine
+    pub current_state: String,
+
+    /// The previous state of the machine
+    pub previous_state: Option<String>,
+
+    /// The number of steps executed since the machine was created or reset
+    pub steps: usize,
+
+    /// The symbols of the machine
+    pub alphabet: Alphabet,
+
+    /// The description of the machine. Found in the `///` comments at the top of the file
+    pub description: Option<String>,
+
+    /// The composed libraries that the machine uses
+    pub composed_libs: Vec<Library>,
+
+    /// The actual code of the machine
+    pub code: String,
+
+    /// The tape, padded with blank symbols around the head
+    tape: VecDeque<Symbol>,
+
+    /// The position of the head on the tape
+    position: usize,
+
+    /// The index in the tape of the first cell of the tape given in the code
+    origin: usize,
+
+    /// The tape, head position and origin right after compilation
+    initial_tape: (VecDeque<Symbol>, usize, usize),
+
+    /// The state the machine starts in
+    initial_state: String,
+}
+
+impl TuringMachine {
+    /// Create a new machine over the alphabet declared in the code, with `alphabet = {...};`.
+    /// See `SymbolMachine` for the syntax
+    pub fn new_with_alphabet(
+        code: &str,
+    ) -> Result<(SymbolMachine, Vec<CompilerWarning>), CompilerError> {
+        SymbolMachine::new(code)
+    }
+}
+
+impl SymbolMachine {
+    /// Create a new machine over an alphabet from a string of code
+    pub fn new(code: &str) -> Result<(Self, Vec<CompilerWarning>), CompilerError> {
+        let mut warnings: Vec<CompilerWarning> = Vec::new();
+
+        let file = match TuringParser::parse(Rule::symbol_file, code) {
+            Ok(mut f) => f.next().unwrap(),
+            Err(error) => {
+                return Err(CompilerError::FileRuleError {
+                    error: Box::new(error),
+                })
+            }
+        };
+
+        // The symbols are resolved once the alphabet is known, since it can be declared after them
+        let mut symbols: Option<Pair<Rule>> = None;
+        let mut mark: Option<Pair<Rule>> = None;
+        let mut tape: Option<Pair<Rule>> = None;
+        let mut instructions: Vec<Pair<Rule>> = Vec::new();
+        let mut initial_state: Option<String> = None;
+        let mut final_states: Vec<String> = Vec::new();
+        let mut description: Option<String> = None;
+        let mut composed: Vec<Library> = Vec::new();
+
+        for record in file.into_inner() {
+            match record.as_rule() {
+                Rule::description => {
+                    description = parse_description(record.as_str());
+                    debug!("Found description: \"{:?}\"", description);
+                }
+                Rule::alphabet => {
+                    if symbols.is_some() {
+                        return Err(TuringMachine::repeated_definition(&record, "alphabet"));
+                    }
+                    list_items(record.clone(), &mut warnings);
+                    symbols = Some(record);
+                }
+                Rule::mark => {
+                    if mark.is_some() {
+                        return Err(TuringMachine::repeated_definition(&record, "mark"));
+                    }
+                    mark = Some(record);
+                }
+                Rule::symbol_tape => {
+                    if tape.is_some() {
+                        return Err(TuringMachine::repeated_definition(&record, "tape"));
+                    }
+                    tape = Some(record);
+                }
+                Rule::initial_state => {
+                    if initial_state.is_some() {
+                        return Err(TuringMachine::repeated_definition(&record, "initial state"));
+                    }
+
+                    initial_state = Some(String::from(record.into_inner().as_str()));
+                    debug!("The initial tape state is \"{:?}\"", initial_state);
+                }
+                Rule::final_state => {
+                    if !final_states.is_empty() {
+                        return Err(TuringMachine::repeated_definition(&record, "final states"));
+                    }
+
+                    final_states = list_items(record, &mut warnings)
+                        .into_iter()
+                        .map(|v| String::from(v.as_str()))
+                        .collect();
+                    debug!("The final tape state is {:?}", final_states);
+                }
+                Rule::composition => {
+                    for r in list_items(record, &mut warnings) {
+                        if r.as_rule() == Rule::alias {
+                            error!("The libraries of a symbol machine cannot have an alias");
+
+                            return Err(CompilerError::SyntaxError {
+                                position: (&r).into(),
+                                message: String::from(
+                                    "The libraries of a symbol machine cannot have an alias",
+                                ),
+                                code: String::from(r.as_str()),
+                                expected: Rule::function_name,
+                                found: Some(Rule::alias),
+                            });
+                        }
+
+                        let Some(library) = crate::LIBRARIES.iter().find(|l| l.name == r.as_str())
+                        else {
+                            error!("Could not find the library \"{}\"", r.as_str());
+
+                            return Err(CompilerError::SyntaxError {
+                                position: (&r).into(),
+                                message: format!("Could not find the library \"{}\"", r.as_str()),
+                                code: String::from(r.as_str()),
+                                expected: r.as_rule(),
+                                found: None,
+                            });
+                        };
+
+                        composed.push(library.clone());
+                    }
+                }
+                Rule::symbol_instruction => instructions.push(record),
+                Rule::doc => debug!("Ignoring the doc comment {}", record.as_str()),
+                Rule::EOI => {
+                    debug!("End of file");
+                }
+                _ => {
+                    warn!("Unhandled: {}", record.into_inner().as_str());
+                }
+            }
+        }
+
+        let alphabet = match symbols {
+            Some(record) => Self::parse_alphabet(record, mark)?,
+            None => match mark {
+                Some(record) => Self::parse_alphabet_mark(Alphabet::default(), record)?,
+                None => Alphabet::default(),
+            },
+        };
+        debug!("The alphabet is {:?}", alphabet.symbols);
+
+        let Some(tape) = tape else {
+            error!("No tape given");
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: String::from("No tape given"),
+                code: String::from(code),
+                expected: Rule::symbol_tape,
+                found: None,
+            });
+        };
+        let tape_position: ErrorPosition = (&tape).into();
+        let tape_code = String::from(tape.as_str());
+
+        let mut cells: Vec<Symbol> = Vec::new();
+        let mut head: Option<usize> = None;
+        for r in tape.into_inner() {
+            match r.as_rule() {
+                Rule::symbol => cells.push(alphabet.parse(&r)?),
+                Rule::symbol_head => {
+                    if head.is_some() {
+                        error!("The head was marked more than once");
+
+                        return Err(CompilerError::SyntaxError {
+                            position: (&r).into(),
+                            message: String::from(
+                                "Only one cell of the tape can be marked as the head",
+                            ),
+                            code: String::from(r.as_str()),
+                            expected: Rule::symbol,
+                            found: Some(Rule::symbol_head),
+                        });
+                    }
+
+                    head = Some(cells.len());
+                    let symbol = r.into_inner().next().unwrap();
+                    cells.push(alphabet.parse(&symbol)?);
+                }
+                _ => warn!("Unhandled: ({:?}, {})", r.as_rule(), r.as_str()),
+            }
+        }
+
+        // Leading blanks are skipped like the leading 0s of a `TuringMachine`, except the ones from the head onwards
+        let leading = cells.iter().take_while(|s| **s == alphabet.blank()).count();
+        let skipped = leading.min(head.unwrap_or(leading));
+        if skipped > 0 {
+            info!("The tape started with {} blanks, skipping them", skipped);
+        }
+        let cells = cells.split_off(skipped);
+
+        if cells.iter().all(|s| *s == alphabet.blank()) {
+            error!("The tape did not contain at least a non-blank symbol");
+
+            return Err(CompilerError::SyntaxError {
+                position: tape_position,
+                message: String::from("Expected at least a non-blank symbol in the tape"),
+                code: tape_code,
+                expected: Rule::symbol_tape,
+                found: None,
+            });
+        }
+
+        if final_states.is_empty() {
+            error!("No final state given");
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: String::from("No final state given"),
+                code: String::from(code),
+                expected: Rule::final_state,
+                found: None,
+            });
+        }
+
+        let Some(initial_state) = initial_state else {
+            error!("No initial state given");
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: String::from("No initial state given"),
+                code: String::from(code),
+                expected: Rule::initial_state,
+                found: None,
+            });
+        };
+
+        let mut table: HashMap<(String, Symbol), SymbolInstruction> = HashMap::new();
+
+        for library in composed.iter() {
+            let (Some(zero), Some(one)) = (alphabet.symbol("0"), alphabet.symbol("1")) else {
+                error!("The library {} needs the symbols 0 and 1", library.name);
+
+                return Err(CompilerError::SyntaxError {
+                    position: ErrorPosition::new((0, 0), None),
+                    message: format!(
+                        "The library {} needs the symbols 0 and 1 in the alphabet",
+                        library.name
+                    ),
+                    code: String::from(code),
+                    expected: Rule::alphabet,
+                    found: Some(Rule::composition),
+                });
+            };
+            let symbol = |v: bool| if v { one } else { zero };
+
+            for instruction in library.get_instructions()?.into_values() {
+                let instruction = SymbolInstruction {
+                    from_symbol: symbol(instruction.from_value),
+                    to_symbol: symbol(instruction.to_value),
+                    from_state: instruction.from_state,
+                    movement: instruction.movement,
+                    to_state: instruction.to_state,
+                };
+
+                table.insert(
+                    (instruction.from_state.clone(), instruction.from_symbol),
+                    instruction,
+                );
+            }
+        }
+
+        for record in instructions {
+            let position: ErrorPosition = (&record).into();
+            let instruction = Self::parse_instruction(&alphabet, record)?;
+            let key = (instruction.from_state.clone(), instruction.from_symbol);
+
+            if table.contains_key(&key) {
+                warn!(
+                    "Instruction ({}, {}) already exists, overwriting it",
+                    instruction.from_state,
+                    alphabet.name(instruction.from_symbol)
+                );
+
+                // The warning only tells whether the symbol is the mark, since it holds a bit
+                warnings.push(CompilerWarning::StateOverwrite {
+                    position,
+                    state: instruction.from_state.clone(),
+                    value_from: instruction.from_symbol == alphabet.mark(),
+                })
+            }
+            table.insert(key, instruction);
+        }
+
+        if !table.keys().any(|(state, _)| *state == initial_state)
+            && !final_states.contains(&initial_state)
+        {
+            error!("The initial state {} is not known", initial_state);
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: format!(
+                    "The initial state {} has no instructions and is not a final state",
+                    initial_state
+                ),
+                code: String::from(code),
+                expected: Rule::initial_state,
+                found: None,
+            });
+        }
+
+        let mut tm = Self {
+            instructions: table,
+            final_states,
+            current_state: initial_state.clone(),
+            previous_state: None,
+            steps: 0,
+            description,
+            composed_libs: composed,
+            code: String::from(code),
+            tape: VecDeque::from(cells),
+            position: head.unwrap_or(skipped) - skipped,
+            origin: 0,
+            initial_tape: (VecDeque::new(), 0, 0),
+            initial_state,
+            alphabet,
+        };
+        tm.pad();
+        tm.initial_tape = (tm.tape.clone(), tm.position, tm.origin);
+
+        Ok((tm, warnings))
+    }
+
+    /// Builds the alphabet of an `alphabet` record, with the mark of the `mark` record if given
+    fn parse_alphabet(
+        record: Pair<Rule>,
+        mark: Option<Pair<Rule>>,
+    ) -> Result<Alphabet, CompilerError> {
+        let mut symbols: Vec<String> = Vec::new();
+
+        for r in record.clone().into_inner() {
+            if r.as_rule() == Rule::trailing_comma {
+                continue;
+            }
+            let name = symbol_name(r.as_str());
+
+            if symbols.iter().any(|s| s == name) {
+                error!("The symbol {} was declared more than once", name);
+
+                return Err(CompilerError::SyntaxError {
+                    position: (&r).into(),
+                    message: format!("The symbol {} can only be declared once", name),
+                    code: String::from(record.as_str()),
+                    expected: Rule::symbol,
+                    found: None,
+                });
+            }
+
+            symbols.push(String::from(name));
+        }
+
+        if symbols.len() > MAX_SYMBOLS {
+            error!("The alphabet has {} symbols", symbols.len());
+
+            return Err(CompilerError::SyntaxError {
+                position: (&record).into(),
+                message: format!("An alphabet can have at most {} symbols", MAX_SYMBOLS),
+                code: String::from(record.as_str()),
+                expected: Rule::alphabet,
+                found: None,
+            });
+        }
+
+        let alphabet = Alphabet {
+            symbols,
+            mark: Symbol(0),
+        };
+
+        match mark {
+            Some(mark) => Self::parse_alphabet_mark(alphabet, mark),
+            None => match alphabet.symbol("1") {
+                Some(one) => Ok(Alphabet {
+                    mark: one,
+                    ..alphabet
+                }),
+                None => {
+                    error!("No mark given and there is no 1 in the alphabet");
+
+                    Err(CompilerError::SyntaxError {
+                        position: (&record).into(),
+                        message: String::from(
+                            "Expected a mark, since there is no 1 in the alphabet",
+                        ),
+                        code: String::from(record.as_str()),
+                        expected: Rule::mark,
+                        found: None,
+                    })
+                }
+            },
+        }
+    }
+
+    /// Sets the mark of the alphabet from a `mark` record
+    fn parse_alphabet_mark(
+        alphabet: Alphabet,
+        record: Pair<Rule>,
+    ) -> Result<Alphabet, CompilerError> {
+        let symbol = record.into_inner().next().unwrap();
+        let mark = alphabet.parse(&symbol)?;
+
+        Ok(Alphabet { mark, ..alphabet })
+    }
+
+    /// Create an instruction from a `symbol_instruction` record
+    fn parse_instruction(
+        alphabet: &Alphabet,
+        record: Pair<Rule>,
+    ) -> Result<SymbolInstruction, CompilerError> {
+        let mut code = record.into_inner();
+
+        let from_state = match code.next() {
+            Some(s) => String::from(s.as_str()),
+            None => panic!("The instruction lacks an initial state"),
+        };
+        let from_symbol = match code.next() {
+            Some(s) => alphabet.parse(&s)?,
+            None => panic!("The instruction lacks an initial tape symbol"),
+        };
+        let to_symbol = match code.next() {
+            Some(s) => alphabet.parse(&s)?,
+            None => panic!("The instruction lacks a target tape symbol"),
+        };
+        let movement = match code.next() {
+            Some(s) => match Movement::from_str(s.as_str()) {
+                Ok(m) => m,
+                Err(message) => {
+                    return Err(CompilerError::SyntaxError {
+                        position: ErrorPosition::from(&s),
+                        message,
+                        code: String::from(s.as_str()),
+                        expected: Rule::movement,
+                        found: None,
+                    })
+                }
+            },
+            None => panic!("The instruction lacks a movement"),
+        };
+        let to_state = match code.next() {
+            Some(s) => String::from(s.as_str()),
+            None => panic!("The instruction lacks a target state"),
+        };
+
+        Ok(SymbolInstruction {
+            from_state,
+            from_symbol,
+            to_symbol,
+            movement,
+            to_state,
+        })
+    }
+
+    /// Keeps at least 3 blank cells at each side of the head, like the tape of a `TuringMachine`
+    fn pad(&mut self) {
+        let blank = self.alphabet.blank();
+
+        while self.position < 3 {
+            self.tape.push_front(blank);
+            self.position += 1;
+            self.origin += 1;
+        }
+
+        while self.tape.len() < self.position + 4 {
+            self.tape.push_back(blank);
+        }
+    }
+
+    /// Restores the tape, the head, the current state and the step counter
+    /// to the values they had right after the machine was created
+    pub fn reset(&mut self) {
+        (self.tape, self.position, self.origin) = self.initial_tape.clone();
+        self.current_state = self.initial_state.clone();
+        self.previous_state = None;
+        self.steps = 0;
+    }
+
+    /// Returns the symbol under the head
+    pub fn read(&self) -> Symbol {
+        self.tape[self.position]
+    }
+
+    /// Returns true if the current state is a final state
+    pub fn finished(&self) -> bool {
+        self.final_states.contains(&self.current_state)
+    }
+
+    /// Returns true if there is no instruction for the current state and symbol, except if the state is final
+    pub fn is_undefined(&self) -> bool {
+        !self
+            .instructions
+            .contains_key(&(self.current_state.clone(), self.read()))
+            && !self.finished()
+    }
+
+    /// Calculates the next step of the machine
+    pub fn advance(&mut self) -> SymbolStep {
+        let symbol = self.read();
+        let key = (self.current_state.clone(), symbol);
+
+        let (to_symbol, movement, to_state) = match self.instructions.get(&key) {
+            Some(i) => (i.to_symbol, i.movement, i.to_state.clone()),
+            // The implicit halt instruction of a final state leaves the tape untouched
+            None if self.finished() => (symbol, Movement::HALT, key.0.clone()),
+            None => {
+                error!(
+                    "No instruction given for state ({}, {})",
+                    self.current_state,
+                    self.alphabet.name(symbol)
+                );
+
+                return SymbolStep::Undefined {
+                    state: key.0,
+                    symbol,
+                };
+            }
+        };
+
+        self.tape[self.position] = to_symbol;
+        match movement {
+            Movement::LEFT => {
+                if self.position == 0 {
+                    self.tape.push_front(self.alphabet.blank());
+                    self.origin += 1;
+                } else {
+                    self.position -= 1;
+                }
+            }
+            Movement::RIGHT => self.position += 1,
+            Movement::HALT | Movement::STAY => {}
+        }
+        self.pad();
+
+        self.previous_state = Some(key.0);
+        self.current_state = to_state;
+        self.steps += 1;
+
+        if self.finished() {
+            SymbolStep::Halted
+        } else {
+            SymbolStep::Continued
+        }
+    }
+
+    /// Runs the machine until it halts, gets stuck or executes `max_steps` steps.
+    /// Like `TuringMachine::run`, the step executed from a final state is the halting one
+    pub fn run(&mut self, max_steps: usize) -> SymbolResult {
+        let mut steps = 0;
+
+        while steps < max_steps {
+            let halting = self.finished();
+
+            if let SymbolStep::Undefined { state, symbol } = self.advance() {
+                return SymbolResult::Stuck {
+                    state,
+                    symbol,
+                    steps,
+                };
+            }
+            steps += 1;
+
+            if halting {
+                return SymbolResult::Halted { steps };
+            }
+        }
+
+        SymbolResult::StepLimitReached { steps }
+    }
+
+    /// Returns an iterator over the symbols of the tape, including the blank padding around the head
+    pub fn tape(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.tape.iter().copied()
+    }
+
+    /// Returns the position of the head relative to the first cell of the tape given in the code,
+    /// like `TuringMachine::head_offset`
+    pub fn head_offset(&self) -> isize {
+        self.position as isize - self.origin as isize
+    }
+
+    /// Returns the values of the tape, like `TuringMachine::values`,
+    /// with the runs of the mark separated by any other symbol
+    pub fn values(&self) -> Vec<u32> {
+        let mark = self.alphabet.mark();
+        let tape: Vec<Symbol> = self.tape().collect();
+
+        tape.split(|s| *s != mark)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.len() as u32 - 1)
+            .collect()
+    }
+
+    /// Returns the current output of the machine, like `TuringMachine::tape_value`,
+    /// counting the cells with the mark.
+    ///
+    /// A stuck machine gives `TuringOutput::Undefined` instead of `Stuck`, whose value read is a bit
+    /// and cannot hold a symbol of the alphabet: `run` gives the state and the symbol as `SymbolResult::Stuck`
+    pub fn tape_value(&self) -> TuringOutput {
+        if self.is_undefined() {
+            return TuringOutput::Undefined(self.steps);
+        }
+
+        let mark = self.alphabet.mark();
+        TuringOutput::Defined((
+            self.steps,
+            self.tape().filter(|s| *s == mark).count() as u32,
+        ))
+    }
+
+    /// Returns the final output of the machine, like `TuringMachine::final_result`,
+    /// giving up after `max_steps` steps. Getting stuck gives `TuringOutput::Undefined`, like `tape_value`
+    pub fn final_result(&mut self, max_steps: usize) -> TuringOutput {
+        match self.run(max_steps) {
+            SymbolResult::Halted { .. } => self.tape_value(),
+            _ => TuringOutput::Undefined(self.steps),
+        }
+    }
+}
+
+impl Display for SymbolMachine {
+    /// Displays the tape with its head, like a `TuringMachine`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut head = String::new();
+        for (i, symbol) in self.tape().enumerate() {
+            write!(f, "{} ", self.alphabet.name(symbol))?;
+            head += if i == self.position { "^ " } else { "  " };
+        }
+
+        write!(f, "\n{}", head)
+    }
+}
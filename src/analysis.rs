@@ -0,0 +1,87 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{InstructionKey, TuringInstruction, TuringMachine};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A state and value with no instruction, found by `missing_transitions`
+pub struct MissingTransition {
+    /// The state without an instruction
+    pub state: String,
+
+    /// The value without an instruction
+    pub value: bool,
+
+    /// False if the state can never be reached from the current state, so the transition is not really needed
+    pub reachable: bool,
+}
+
+impl TuringMachine {
+    /// Returns the states that can be reached from the current state (the initial state right after compiling),
+    /// following the instructions for both values regardless of the tape
+    pub fn reachable_states(&self) -> HashSet<String> {
+        let mut reachable: HashSet<String> = HashSet::from([String::from(self.current_state())]);
+        let mut pending: VecDeque<String> = VecDeque::from([String::from(self.current_state())]);
+
+        while let Some(state) = pending.pop_front() {
+            for value in [false, true] {
+                let Some(instruction) =
+                    self.instructions().get(&InstructionKey::new(&state, value))
+                else {
+                    continue;
+                };
+
+                if reachable.insert(instruction.to_state.clone()) {
+                    pending.push_back(instruction.to_state.clone());
+                }
+            }
+        }
+
+        reachable
+    }
+
+    /// Returns the instructions that can never be executed because their state is not reachable from the current state,
+    /// sorted by state and value
+    pub fn unreachable_instructions(&self) -> Vec<&TuringInstruction> {
+        let reachable = self.reachable_states();
+
+        let mut instructions: Vec<&TuringInstruction> = self
+            .instructions()
+            .values()
+            .filter(|i| !reachable.contains(&i.from_state))
+            .collect();
+        instructions.sort_by(|a, b| a.cmp_by_state(b));
+
+        instructions
+    }
+
+    /// Returns every state and value without an instruction, for the states that are not final
+    /// and appear in some instruction, sorted by state and value.
+    /// Each of them tells whether the state is reachable, since only reachable ones can get the machine stuck
+    pub fn missing_transitions(&self) -> Vec<MissingTransition> {
+        let reachable = self.reachable_states();
+
+        let mut states: Vec<&str> = self
+            .instructions()
+            .values()
+            .flat_map(|i| [i.from_state.as_str(), i.to_state.as_str()])
+            .filter(|s| !self.final_states().iter().any(|f| f == s))
+            .collect();
+        states.sort();
+        states.dedup();
+
+        states
+            .into_iter()
+            .flat_map(|state| [(state, false), (state, true)])
+            .filter(|(state, value)| {
+                !self
+                    .instructions()
+                    .contains_key(&InstructionKey::new(state, *value))
+            })
+            .map(|(state, value)| MissingTransition {
+                state: String::from(state),
+                value,
+                reachable: reachable.contains(state),
+            })
+            .collect()
+    }
+}
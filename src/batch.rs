@@ -0,0 +1,34 @@
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{ExecutionResult, TuringMachine};
+
+impl TuringMachine {
+    /// Runs a copy of the machine on each input tape for up to `max_steps` steps, like `set_input_tape` followed by `run`.
+    /// The machine itself is left untouched, and the copies share its instructions.
+    ///
+    /// The results are in the same order as the inputs, and an input that `set_input_tape` rejects gives
+    /// `ExecutionResult::InvalidInput`. With the `rayon` feature the inputs are run in parallel
+    pub fn run_batch(&self, inputs: &[Vec<bool>], max_steps: usize) -> Vec<ExecutionResult> {
+        #[cfg(feature = "rayon")]
+        let inputs = inputs.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let inputs = inputs.iter();
+
+        inputs
+            .map(|input| self.run_input(input, max_steps))
+            .collect()
+    }
+
+    /// Runs a copy of the machine on the given input tape
+    fn run_input(&self, input: &[bool], max_steps: usize) -> ExecutionResult {
+        let mut tm = self.clone();
+
+        match tm.set_input_tape(input) {
+            Ok(()) => tm.run(max_steps),
+            Err(error) => ExecutionResult::InvalidInput {
+                message: error.message(),
+            },
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Saving and loading whole machines as bytes, enabled with the `binary` feature.
+//!
+//! The machine is written with its serde implementations in the compact postcard format, after a header
+//! with the version of the format, so data written by another version is rejected with a clear error
+//! instead of being read as garbage. Postcard reads the fields by their order, so the fields that JSON
+//! leaves out when they are empty (the spans, docs and choices) are always written
+
+use std::fmt::Display;
+
+use crate::{serialization::with_every_field, TuringMachine};
+
+/// The bytes every serialized machine starts with
+const MAGIC: &[u8; 4] = b"TMBN";
+
+/// The version of the format, to be increased whenever the way a machine is written changes
+pub const BINARY_VERSION: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An error when loading a machine from the binary format
+pub enum BinaryError {
+    /// The data does not start with the header of the format
+    InvalidHeader,
+
+    /// The data was written with another version of the format
+    UnsupportedVersion {
+        /// The version of the data
+        found: u8,
+        /// The version this library reads
+        supported: u8,
+    },
+
+    /// The data is not a valid machine, with the reason
+    Invalid(String),
+}
+
+impl Display for BinaryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "The data is not a machine in the binary format"),
+            Self::UnsupportedVersion { found, supported } => write!(
+                f,
+                "The data was written with the version {} of the binary format, but only the version {} is supported",
+                found, supported
+            ),
+            Self::Invalid(reason) => write!(f, "The data is not a valid machine: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for BinaryError {}
+
+impl TuringMachine {
+    /// Writes the whole machine in the binary format, with the runtime state that is serialized to JSON
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(BINARY_VERSION);
+
+        with_every_field(|| postcard::to_extend(self, bytes))
+            .expect("A machine can always be serialized")
+    }
+
+    /// Loads a machine written with `to_bytes`.
+    /// Returns an error if the header is missing, the version of the format is not this one or the data is not a machine,
+    /// including a machine whose head or states are out of range
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BinaryError> {
+        let data = bytes
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or(BinaryError::InvalidHeader)?;
+
+        let (&version, input) = data.split_first().ok_or(BinaryError::InvalidHeader)?;
+        if version != BINARY_VERSION {
+            return Err(BinaryError::UnsupportedVersion {
+                found: version,
+                supported: BINARY_VERSION,
+            });
+        }
+
+        match postcard::take_from_bytes(input) {
+            Ok((machine, [])) => Ok(machine),
+            Ok((_, rest)) => Err(BinaryError::Invalid(format!(
+                "{} bytes were left after the machine",
+                rest.len()
+            ))),
+            Err(e) => Err(BinaryError::Invalid(e.to_string())),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ExecutionResult, TuringMachine};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// A condition on the writes to the tape that stops `run_to_breakpoint`
+pub enum Watchpoint {
+    /// Any write to the cell at the given position, relative to the first cell of the tape given in the code
+    Cell(isize),
+
+    /// A write of the given value over a cell that had a different value
+    Write(bool),
+}
+
+impl TuringMachine {
+    /// Adds a breakpoint on the given state, so that `run_to_breakpoint` stops when the machine enters it
+    pub fn add_breakpoint(&mut self, state: &str) {
+        self.breakpoints.insert(String::from(state));
+    }
+
+    /// Removes the breakpoint on the given state. Returns false if there was no breakpoint on it
+    pub fn remove_breakpoint(&mut self, state: &str) -> bool {
+        self.breakpoints.remove(state)
+    }
+
+    /// Removes all the breakpoints
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Returns true if there is a breakpoint on the given state
+    pub fn has_breakpoint(&self, state: &str) -> bool {
+        self.breakpoints.contains(state)
+    }
+
+    /// Adds a watchpoint on the cell at the given position, relative to the first cell of the tape given in the code.
+    /// Negative positions are to the left of it
+    pub fn add_cell_watch(&mut self, index: isize) {
+        self.cell_watches.insert(index);
+    }
+
+    /// Removes the watchpoint on the given cell. Returns false if there was no watchpoint on it
+    pub fn remove_cell_watch(&mut self, index: isize) -> bool {
+        self.cell_watches.remove(&index)
+    }
+
+    /// Adds a watchpoint that triggers when the given value is written over a cell with a different value
+    /// (e.g. `add_write_watch(false)` triggers when any cell changes from 1 to 0)
+    pub fn add_write_watch(&mut self, value: bool) {
+        self.write_watches.insert(value);
+    }
+
+    /// Removes the watchpoint on writes of the given value. Returns false if there was no such watchpoint
+    pub fn remove_write_watch(&mut self, value: bool) -> bool {
+        self.write_watches.remove(&value)
+    }
+
+    /// Removes all the watchpoints
+    pub fn clear_watches(&mut self) {
+        self.cell_watches.clear();
+        self.write_watches.clear();
+    }
+
+    /// Returns the watchpoint triggered by the last step, if any
+    pub fn watch_hit(&self) -> Option<Watchpoint> {
+        self.watch_hit
+    }
+
+    /// Checks the watchpoints against the write of the current step
+    pub(crate) fn check_watches(&mut self, previous: bool, written: bool) {
+        let cell = self.head_offset();
+
+        if self.cell_watches.contains(&cell) {
+            self.watch_hit = Some(Watchpoint::Cell(cell));
+        } else if previous != written && self.write_watches.contains(&written) {
+            self.watch_hit = Some(Watchpoint::Write(written));
+        }
+    }
+
+    /// Runs the machine until it enters a state with a breakpoint, triggers a watchpoint,
+    /// halts, gets stuck or executes `max_steps` steps.
+    ///
+    /// A breakpoint on the initial state triggers before any step is executed,
+    /// otherwise at least one step is executed so that the machine can continue after a breakpoint.
+    pub fn run_to_breakpoint(&mut self, max_steps: usize) -> ExecutionResult {
+        if let Some(result) = self.initial_breakpoint() {
+            return result;
+        }
+
+        self.run_checked(max_steps, |_, _, _| {}, Self::breakpoint_hit)
+    }
+
+    /// Returns the breakpoint of the initial state the first time it is checked, before any step is executed
+    pub(crate) fn initial_breakpoint(&mut self) -> Option<ExecutionResult> {
+        if self.previous_state.is_none()
+            && !self.initial_breakpoint_hit
+            && self.breakpoints.contains(self.current_state())
+        {
+            self.initial_breakpoint_hit = true;
+
+            return Some(ExecutionResult::Breakpoint {
+                state: String::from(self.current_state()),
+                steps: 0,
+            });
+        }
+
+        None
+    }
+
+    /// Returns the watchpoint or breakpoint triggered by the last step, after `steps` steps of a run
+    pub(crate) fn breakpoint_hit(&self, steps: usize) -> Option<ExecutionResult> {
+        if let Some(watchpoint) = self.watch_hit {
+            return Some(ExecutionResult::Watchpoint { watchpoint, steps });
+        }
+
+        if self.breakpoints.contains(self.current_state()) {
+            return Some(ExecutionResult::Breakpoint {
+                state: String::from(self.current_state()),
+                steps,
+            });
+        }
+
+        None
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    parts::MachineParts, warnings::ErrorPosition, CompilerError, CompilerWarning, Movement, Rule,
+    TapeMode, TuringInstruction, TuringMachine, LIBRARIES,
+};
+
+#[derive(Debug, Clone, Default)]
+/// Builds a Turing machine step by step instead of parsing its code:
+///
+/// ```
+/// use turing_lib::{Movement, TuringMachineBuilder};
+///
+/// let (tm, warnings) = TuringMachineBuilder::new()
+///     .initial("q0")
+///     .finals(["q2"])
+///     .tape("111011")
+///     .instr("q0", 1, 0, Movement::RIGHT, "q1")
+///     .instr("q1", 1, 1, Movement::RIGHT, "q1")
+///     .instr("q1", 0, 0, Movement::RIGHT, "q2")
+///     .build()
+///     .unwrap();
+///
+/// assert!(warnings.is_empty());
+/// assert_eq!(tm.current_state(), "q0");
+/// ```
+///
+/// The machine is checked when calling `build`, exactly like the parsed code.
+/// The warnings of the instructions refer to their index as the line, since there is no code to point at
+pub struct TuringMachineBuilder {
+    description: Option<String>,
+    composed: Vec<String>,
+    tape_mode: TapeMode,
+    tape: Option<String>,
+    initial_state: Option<String>,
+    final_states: Vec<String>,
+    accepting_states: Vec<String>,
+    rejecting_states: Vec<String>,
+    instructions: Vec<(String, u8, u8, Movement, String)>,
+}
+
+impl TuringMachineBuilder {
+    /// Creates an empty builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the description of the machine
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(String::from(description));
+        self
+    }
+
+    /// Composes the library with the given name, like `compose = {name};`
+    pub fn compose(mut self, name: &str) -> Self {
+        self.composed.push(String::from(name));
+        self
+    }
+
+    /// Sets whether the tape is left-bounded, like `tape = bounded;`
+    pub fn tape_mode(mut self, mode: TapeMode) -> Self {
+        self.tape_mode = mode;
+        self
+    }
+
+    /// Sets the tape, written with `0`s and `1`s like in the code (e.g. `"111011"`)
+    pub fn tape(mut self, tape: &str) -> Self {
+        self.tape = Some(String::from(tape));
+        self
+    }
+
+    /// Sets the initial state
+    pub fn initial(mut self, state: &str) -> Self {
+        self.initial_state = Some(String::from(state));
+        self
+    }
+
+    /// Adds final states
+    pub fn finals<I, S>(mut self, states: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.final_states
+            .extend(states.into_iter().map(|s| String::from(s.as_ref())));
+        self
+    }
+
+    /// Adds final states that accept the input, like `A = {...};`
+    pub fn accepting<I, S>(mut self, states: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.accepting_states
+            .extend(states.into_iter().map(|s| String::from(s.as_ref())));
+        self
+    }
+
+    /// Adds final states that reject the input, like `R = {...};`
+    pub fn rejecting<I, S>(mut self, states: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.rejecting_states
+            .extend(states.into_iter().map(|s| String::from(s.as_ref())));
+        self
+    }
+
+    /// Adds an instruction, with the values written as `0` or `1`
+    pub fn instr(
+        mut self,
+        from_state: &str,
+        from_value: u8,
+        to_value: u8,
+        movement: Movement,
+        to_state: &str,
+    ) -> Self {
+        self.instructions.push((
+            String::from(from_state),
+            from_value,
+            to_value,
+            movement,
+            String::from(to_state),
+        ));
+        self
+    }
+
+    /// Checks the machine and builds it, just like `TuringMachine::new` does with the code
+    pub fn build(self) -> Result<(TuringMachine, Vec<CompilerWarning>), CompilerError> {
+        let mut parts = MachineParts::new(String::new());
+        parts.description = self.description;
+        parts.tape_mode = self.tape_mode;
+        parts.initial_state = self.initial_state;
+        parts.final_states = self.final_states;
+        parts.accepting_states = self.accepting_states;
+        parts.rejecting_states = self.rejecting_states;
+
+        for name in self.composed {
+            let Some(library) = LIBRARIES.iter().find(|l| l.name == name) else {
+                return Err(CompilerError::SyntaxError {
+                    position: ErrorPosition::new((0, 0), None),
+                    message: format!("Could not find the library \"{}\"", name),
+                    code: name,
+                    expected: Rule::function_name,
+                    found: None,
+                });
+            };
+
+            parts
+                .composed
+                .push((library.clone(), ErrorPosition::new((0, 0), None)));
+        }
+
+        if let Some(tape) = self.tape {
+            let mut cells = Vec::with_capacity(tape.len());
+
+            for c in tape.chars() {
+                match c {
+                    '0' => cells.push(false),
+                    '1' => cells.push(true),
+                    _ => {
+                        return Err(CompilerError::SyntaxError {
+                            position: ErrorPosition::new((0, 0), None),
+                            message: format!("Expected a 0 or a 1 in the tape, found {:?}", c),
+                            code: tape,
+                            expected: Rule::value,
+                            found: None,
+                        })
+                    }
+                }
+            }
+
+            parts.tape = Some((cells, ErrorPosition::new((0, 0), None)));
+        }
+
+        for (i, (from_state, from_value, to_value, movement, to_state)) in
+            self.instructions.into_iter().enumerate()
+        {
+            let position = ErrorPosition::new((i, 0), None);
+
+            let (Some(from_value), Some(to_value)) = (value(from_value), value(to_value)) else {
+                return Err(CompilerError::SyntaxError {
+                    position,
+                    message: String::from("Expected the values of the instruction to be 0 or 1"),
+                    code: format!(
+                        "({}, {}, {}, {}, {})",
+                        from_state, from_value, to_value, movement, to_state
+                    ),
+                    expected: Rule::value,
+                    found: None,
+                });
+            };
+
+            parts.instructions.push((
+                TuringInstruction {
+                    from_state,
+                    from_value,
+                    to_value,
+                    movement,
+                    to_state,
+                    span: None,
+                    doc: None,
+                },
+                position,
+            ));
+        }
+
+        parts.code = parts.write_code();
+
+        TuringMachine::from_machine_parts(parts)
+    }
+}
+
+/// Converts a value written as `0` or `1`
+fn value(v: u8) -> Option<bool> {
+    match v {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt::Display;
+
+use crate::{MachineSnapshot, StepResult, TuringMachine};
+
+#[derive(Debug, Clone)]
+/// A snapshot of the machine taken by `enable_checkpoints`, with the length of the trace at that moment
+struct Checkpoint {
+    snapshot: MachineSnapshot,
+    trace_len: usize,
+}
+
+impl Checkpoint {
+    fn new(tm: &TuringMachine) -> Self {
+        Self {
+            snapshot: tm.snapshot(),
+            trace_len: tm.trace().len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The snapshots taken by `enable_checkpoints` while the machine runs
+pub(crate) struct Checkpoints {
+    /// The number of steps between two snapshots
+    pub(crate) every: usize,
+
+    /// The snapshots taken so far, in the order of their steps. The first one is the machine when they were enabled
+    snapshots: Vec<Checkpoint>,
+
+    /// The last step reached since the checkpoints were enabled
+    recorded: usize,
+}
+
+impl Checkpoints {
+    /// Returns the step the checkpoints were enabled at, the first one that can be gone to
+    pub(crate) fn start(&self) -> usize {
+        self.snapshots[0].snapshot.steps
+    }
+
+    /// Returns the last step reached since the checkpoints were enabled
+    pub(crate) fn recorded(&self) -> usize {
+        self.recorded
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An error when going to a step with `goto_step`
+pub enum TimeTravelError {
+    /// The checkpoints are not enabled, see `enable_checkpoints`
+    NotEnabled,
+
+    /// The step was not reached since the checkpoints were enabled, or it is before the first checkpoint
+    NotRecorded {
+        /// The step requested
+        step: usize,
+
+        /// The first and the last steps that can be gone to
+        recorded: (usize, usize),
+    },
+
+    /// The machine could not execute a step again while going to the requested one, because it was changed
+    /// after the checkpoints were taken (e.g. its tape limit, its tape mode or a cell written by hand)
+    Diverged {
+        /// The step that could not be executed again
+        step: usize,
+
+        /// What happened instead of executing it
+        result: StepResult,
+    },
+}
+
+impl Display for TimeTravelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotEnabled => write!(f, "The checkpoints are not enabled"),
+            Self::NotRecorded {
+                step,
+                recorded: (first, last),
+            } => write!(
+                f,
+                "The step {} was not recorded, only the steps {} to {} were",
+                step, first, last
+            ),
+            Self::Diverged { step, result } => write!(
+                f,
+                "The step {} could not be executed again ({:?}), the machine changed since the checkpoints were taken",
+                step, result
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TimeTravelError {}
+
+impl TuringMachine {
+    /// Takes a snapshot of the machine now and every `every` steps after it (at least 1) while it runs,
+    /// so that `goto_step` can go to any step reached since then. Only `steps / every` snapshots are kept.
+    ///
+    /// The scanning loops are executed step by step while the checkpoints are enabled. Changing the tape or the state
+    /// by hand makes the snapshots taken later out of date, so enable the checkpoints again after doing it
+    pub fn enable_checkpoints(&mut self, every: usize) {
+        self.checkpoints = Some(Checkpoints {
+            every: every.max(1),
+            snapshots: vec![Checkpoint::new(self)],
+            recorded: self.steps,
+        });
+    }
+
+    /// Forgets the checkpoints and stops taking them
+    pub fn disable_checkpoints(&mut self) {
+        self.checkpoints = None;
+    }
+
+    /// Returns the number of snapshots taken since the checkpoints were enabled, or 0 if they are not enabled
+    pub fn checkpoint_count(&self) -> usize {
+        self.checkpoints.as_ref().map_or(0, |c| c.snapshots.len())
+    }
+
+    /// Goes to a step reached since the checkpoints were enabled, restoring the nearest snapshot before it
+    /// and executing the steps after it again. The machine ends up exactly like when it reached that step,
+    /// except for its history. The trace (if enabled) is cut back to the step, and the statistics and the coverage
+    /// count the steps executed again.
+    ///
+    /// Returns an error (leaving the machine untouched) if the checkpoints are not enabled
+    /// or the step was not reached since they were. If a step cannot be executed again, because the machine
+    /// was changed after the snapshots were taken, the error is `Diverged` and the machine stays at that step
+    pub fn goto_step(&mut self, step: usize) -> Result<(), TimeTravelError> {
+        let Some(checkpoints) = &self.checkpoints else {
+            return Err(TimeTravelError::NotEnabled);
+        };
+
+        let first = checkpoints.start();
+        if step < first || step > checkpoints.recorded {
+            return Err(TimeTravelError::NotRecorded {
+                step,
+                recorded: (first, checkpoints.recorded),
+            });
+        }
+
+        let nearest = checkpoints
+            .snapshots
+            .partition_point(|c| c.snapshot.steps <= step)
+            - 1;
+        let checkpoint = &checkpoints.snapshots[nearest];
+
+        // Running forward from the current step is enough if no snapshot is closer.
+        // The trace cut back by a previous jump cannot be restored, so it is recorded again instead
+        let ahead =
+            checkpoint.snapshot.steps > self.steps && checkpoint.trace_len <= self.trace().len();
+
+        if step < self.steps || ahead {
+            let Checkpoint {
+                snapshot,
+                trace_len,
+            } = checkpoint.clone();
+
+            self.restore(snapshot)
+                .expect("The snapshots are taken from the same machine");
+            if let Some(trace) = &mut self.trace {
+                trace.truncate(trace_len);
+            }
+        }
+
+        // The steps were already executed, so anything but executing them means the machine changed
+        while self.steps < step {
+            match self.advance() {
+                StepResult::Continued | StepResult::Halted => {}
+                result => {
+                    return Err(TimeTravelError::Diverged {
+                        step: self.steps + 1,
+                        result,
+                    })
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Takes a snapshot if the current step is a multiple of the interval of the checkpoints after the first one
+    /// and was not reached before
+    pub(crate) fn record_checkpoint(&mut self) {
+        let Some(checkpoints) = &self.checkpoints else {
+            return;
+        };
+
+        let last = checkpoints.snapshots.last().map_or(0, |c| c.snapshot.steps);
+        let take = self.steps > last
+            && (self.steps - checkpoints.start()).is_multiple_of(checkpoints.every);
+        let checkpoint = take.then(|| Checkpoint::new(self));
+
+        if let Some(checkpoints) = &mut self.checkpoints {
+            checkpoints.recorded = checkpoints.recorded.max(self.steps);
+            checkpoints.snapshots.extend(checkpoint);
+        }
+    }
+}
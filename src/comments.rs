@@ -0,0 +1,63 @@
+use pest::{Parser, Span};
+use serde::{Deserialize, Serialize};
+
+use crate::{Rule, SourceSpan, TuringMachine, TuringParser};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// A `//` or `#` comment of the code, which the compiler skips like a space
+pub struct Comment {
+    /// The text of the comment, with its `//` or `#`
+    pub text: String,
+
+    /// Where the comment is written in the code
+    pub span: SourceSpan,
+}
+
+impl TuringMachine {
+    /// Returns the `//` and `#` comments of the code in the order they are written,
+    /// so the code can be written again keeping them. The description and the `///` docs are not comments
+    pub fn comments(&self) -> Vec<Comment> {
+        comments(self.code())
+    }
+}
+
+/// Finds the comments of the code of a machine. They are the only text between the tokens of the grammar
+/// that starts with `//` or `#`, since no other rule has those characters outside of a token
+pub(crate) fn comments(code: &str) -> Vec<Comment> {
+    let Ok(file) = TuringParser::parse(Rule::file, code) else {
+        return Vec::new();
+    };
+
+    let mut comments: Vec<Comment> = Vec::new();
+    let mut gap_start = 0;
+
+    let tokens = file
+        .flatten()
+        .filter(|pair| pair.clone().into_inner().next().is_none())
+        .map(|pair| (pair.as_span().start(), pair.as_span().end()));
+
+    for (start, end) in tokens.chain([(code.len(), code.len())]) {
+        let mut i = gap_start;
+
+        while i < start {
+            let rest = &code[i..start];
+
+            if rest.starts_with("//") || rest.starts_with('#') {
+                let len = rest.find(['\n', '\r']).unwrap_or(rest.len());
+                let span = Span::new(code, i, i + len).unwrap();
+
+                comments.push(Comment {
+                    text: String::from(span.as_str()),
+                    span: SourceSpan::from(&span),
+                });
+                i += len;
+            } else {
+                i += rest.chars().next().map_or(1, char::len_utf8);
+            }
+        }
+
+        gap_start = gap_start.max(end);
+    }
+
+    comments
+}
@@ -0,0 +1,55 @@
+use crate::{CompilerError, CompilerWarning, TuringMachine};
+
+#[derive(Debug, Clone)]
+/// A machine compiled with `TuringMachine::compile`, with everything the compiler found in its code
+pub struct CompilationResult {
+    /// The compiled machine
+    pub machine: TuringMachine,
+
+    /// The warnings given while compiling, in the order of the code
+    pub warnings: Vec<CompilerWarning>,
+
+    /// The names of the composed libraries, in the order of the code
+    pub composed: Vec<String>,
+}
+
+impl CompilationResult {
+    /// Returns the machine, ignoring the warnings
+    pub fn into_machine(self) -> TuringMachine {
+        self.machine
+    }
+
+    /// Returns true if the compiler gave no warnings
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    /// Returns the machine only if the compiler gave no warnings, or the warnings otherwise.
+    /// Useful to be strict about the code, like when grading it
+    pub fn warnings_as_errors(self) -> Result<TuringMachine, Vec<CompilerWarning>> {
+        if self.warnings.is_empty() {
+            Ok(self.machine)
+        } else {
+            Err(self.warnings)
+        }
+    }
+}
+
+impl TuringMachine {
+    /// Compiles a new Turing machine from a string of code, like `new`,
+    /// but keeps the warnings and the composed libraries together with the machine
+    pub fn compile(code: &str) -> Result<CompilationResult, CompilerError> {
+        let (machine, warnings) = Self::from_machine_parts(Self::parse_parts(code)?)?;
+        let composed = machine
+            .composed_libs()
+            .iter()
+            .map(|l| l.name.to_string())
+            .collect();
+
+        Ok(CompilationResult {
+            machine,
+            warnings,
+            composed,
+        })
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use crate::{program::StateKey, TuringInstruction, TuringMachine};
+
+impl TuringMachine {
+    /// Starts counting how many times each instruction is executed, forgetting any previous counts.
+    /// Unlike the frequencies, the implicit halt of final states is not counted, and `reset_frequencies` keeps the counts
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(HashMap::new());
+    }
+
+    /// Stops counting the executed instructions and forgets the counts
+    pub fn disable_coverage(&mut self) {
+        self.coverage = None;
+    }
+
+    /// Returns the number of times each instruction was executed since the coverage was enabled,
+    /// by state and value, or None if it is not enabled
+    pub fn coverage(&self) -> Option<&HashMap<(String, bool), usize>> {
+        self.coverage.as_ref()
+    }
+
+    /// Returns the instructions that were not executed since the coverage was enabled, sorted by state and value.
+    /// Every instruction is returned if the coverage is not enabled
+    pub fn uncovered_instructions(&self) -> Vec<&TuringInstruction> {
+        let mut instructions: Vec<&TuringInstruction> = self
+            .instructions()
+            .iter()
+            .filter(|(key, _)| {
+                self.coverage
+                    .as_ref()
+                    .is_none_or(|coverage| !coverage.contains_key(*key as &dyn StateKey))
+            })
+            .map(|(_, i)| i)
+            .collect();
+        instructions.sort_by(|a, b| a.cmp_by_state(b));
+
+        instructions
+    }
+}
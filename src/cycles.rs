@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use crate::TuringMachine;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+/// A configuration of a Turing machine: its state, the position of its head and its tape.
+///
+/// The key does not depend on how the tape is stored: positions are relative to the first cell given in the code
+/// and the blank cells around the 1s are left out. So a configuration always has the same key,
+/// even after `reset`, `snapshot` and `restore` or when the tape has grown
+pub struct ConfigKey {
+    /// The current state
+    pub state: String,
+
+    /// The position of the head relative to the first cell given in the code, like `head_offset`
+    pub head: isize,
+
+    /// The position of the first cell of `cells` relative to the first cell given in the code
+    pub start: isize,
+
+    /// The cells between the first and the last 1 of the tape (both included), empty if the tape has no 1s
+    pub cells: Vec<bool>,
+}
+
+impl TuringMachine {
+    /// Enables or disables the exact detection of infinite loops.
+    ///
+    /// When enabled, a hash of every configuration (state, head position and tape contents) is stored,
+    /// and if a configuration repeats the machine is provably in an infinite loop.
+    /// This costs memory proportional to the number of steps executed
+    pub fn detect_cycles(&mut self, enabled: bool) {
+        self.cycle_detected = false;
+
+        if enabled {
+            self.configurations = Some(HashSet::from([self.configuration_hash()]));
+        } else {
+            self.configurations = None;
+        }
+    }
+
+    /// Returns the key of the current configuration
+    pub fn configuration_key(&self) -> ConfigKey {
+        let (cells, head) = self.tape_trimmed();
+
+        ConfigKey {
+            state: String::from(self.current_state()),
+            head: self.head_offset(),
+            start: if cells.is_empty() {
+                0
+            } else {
+                self.head_offset() - head
+            },
+            cells: cells.to_vec(),
+        }
+    }
+
+    /// Runs the machine like `run`, returning the key of every configuration it goes through:
+    /// the current one, and the one after every step executed.
+    /// The halting step of a final state leaves the configuration unchanged, so its key is repeated at the end
+    pub fn run_collect_configs(&mut self, max_steps: usize) -> Vec<ConfigKey> {
+        let mut keys = vec![self.configuration_key()];
+
+        let result = self.run_checked(
+            max_steps,
+            |_, _, _| {},
+            |tm, _| {
+                keys.push(tm.configuration_key());
+                None
+            },
+        );
+
+        // The configuration is not checked after the halting step, nor after one that repeated
+        if keys.len() == result.steps() {
+            keys.push(self.configuration_key());
+        }
+
+        keys
+    }
+
+    /// Returns true if cycle detection is enabled and a configuration has repeated
+    pub fn is_cycle_detected(&self) -> bool {
+        self.cycle_detected
+    }
+
+    /// Records the current configuration, flagging a cycle if it was already seen
+    pub(crate) fn record_configuration(&mut self) {
+        let hash = self.configuration_hash();
+
+        if let Some(configurations) = &mut self.configurations {
+            if !configurations.insert(hash) {
+                self.cycle_detected = true;
+            }
+        }
+    }
+
+    /// Hashes the current configuration. Blank cells at both ends of the tape are ignored
+    /// and positions are relative to the origin, so that the padding of the tape does not matter
+    fn configuration_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        self.current_state().hash(&mut hasher);
+        self.head_offset().hash(&mut hasher);
+
+        let (tape, head) = self.tape_trimmed();
+        head.hash(&mut hasher);
+        tape.hash(&mut hasher);
+
+        hasher.finish()
+    }
+}
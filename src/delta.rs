@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{StepResult, TuringMachine};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// The changes made by a single step of a Turing machine, so the tape can be updated without copying it.
+/// The cells are given relative to the first cell of the tape given in the code, like `head_offset`,
+/// so they stay the same when the tape grows at the front
+pub struct StepDelta {
+    /// The number of steps executed so far, including this one
+    pub step: usize,
+
+    /// The cell written and its new value, or None if the value did not change
+    pub written_cell: Option<(isize, bool)>,
+
+    /// The movement of the head: -1 for left, 1 for right and 0 for halt
+    pub head_move: i8,
+
+    /// The state of the machine after the step
+    pub new_state: String,
+
+    /// The number of blank cells inserted at the front of the tape
+    pub grew_left: usize,
+
+    /// The number of blank cells pushed at the back of the tape
+    pub grew_right: usize,
+
+    /// True if the machine is in a final state after the step
+    pub halted: bool,
+}
+
+impl TuringMachine {
+    /// Calculates the next step of the Turing machine like `advance`, and returns the changes it made.
+    /// Returns None if no step was executed, i.e. the machine is stuck, the head would leave a left-bounded tape
+    /// or the tape would exceed its limit
+    pub fn step_delta(&mut self) -> Option<StepDelta> {
+        let head = self.head_offset();
+        let value = self.tape[self.tape_position];
+        let origin = self.origin;
+        let len = self.tape.len();
+
+        let halted = match self.advance() {
+            StepResult::Continued => false,
+            StepResult::Halted => true,
+            StepResult::Undefined { .. }
+            | StepResult::OutOfTape { .. }
+            | StepResult::TapeLimitExceeded { .. } => return None,
+        };
+
+        let grew_left = self.origin - origin;
+        let written = self.tape[(self.origin as isize + head) as usize];
+
+        Some(StepDelta {
+            step: self.steps,
+            written_cell: (written != value).then_some((head, written)),
+            head_move: (self.head_offset() - head) as i8,
+            new_state: String::from(self.current_state()),
+            grew_left,
+            grew_right: self.tape.len() - len - grew_left,
+            halted,
+        })
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{natural_cmp, TuringInstruction, TuringMachine};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// An instruction of a machine that does something else in the other machine of a `MachineDiff`
+pub struct InstructionChange {
+    /// The instruction of the first machine
+    pub before: TuringInstruction,
+
+    /// The instruction of the second machine, with the same state and value
+    pub after: TuringInstruction,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// The differences between two machines found by `diff`. The instructions are sorted like `instructions_sorted`.
+///
+/// It is displayed like a unified diff, with the lines of the first machine starting with `-`
+/// and the ones of the second machine starting with `+`
+pub struct MachineDiff {
+    /// The instructions only the second machine has
+    pub added: Vec<TuringInstruction>,
+
+    /// The instructions only the first machine has
+    pub removed: Vec<TuringInstruction>,
+
+    /// The instructions of both machines for the same state and value that write, move or go to a different state
+    pub modified: Vec<InstructionChange>,
+
+    /// The initial states of both machines, if they are different
+    pub initial_state: Option<(String, String)>,
+
+    /// The final states of both machines in natural order, if they are different
+    pub final_states: Option<(Vec<String>, Vec<String>)>,
+
+    /// The code of the tapes of both machines (e.g. `{11[0]1};`), if they are different
+    pub tape: Option<(String, String)>,
+}
+
+impl MachineDiff {
+    /// Returns true if both machines have the same instructions, initial state, final states and tape
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Display for MachineDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((before, after)) = &self.initial_state {
+            writeln!(f, "- I = {{{}}};", before)?;
+            writeln!(f, "+ I = {{{}}};", after)?;
+        }
+        if let Some((before, after)) = &self.final_states {
+            writeln!(f, "- F = {{{}}};", before.join(", "))?;
+            writeln!(f, "+ F = {{{}}};", after.join(", "))?;
+        }
+        if let Some((before, after)) = &self.tape {
+            writeln!(f, "- {}", before)?;
+            writeln!(f, "+ {}", after)?;
+        }
+
+        // Every change of an instruction is shown where the instruction is in the sorted code
+        let mut lines: Vec<(
+            &TuringInstruction,
+            Option<&TuringInstruction>,
+            Option<&TuringInstruction>,
+        )> = self
+            .removed
+            .iter()
+            .map(|i| (i, Some(i), None))
+            .chain(self.added.iter().map(|i| (i, None, Some(i))))
+            .chain(
+                self.modified
+                    .iter()
+                    .map(|c| (&c.before, Some(&c.before), Some(&c.after))),
+            )
+            .collect();
+        lines.sort_by(|a, b| a.0.cmp_by_state(b.0));
+
+        for (_, before, after) in lines {
+            if let Some(before) = before {
+                writeln!(f, "- {};", before)?;
+            }
+            if let Some(after) = after {
+                writeln!(f, "+ {};", after)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TuringMachine {
+    /// Compares the instructions, the initial state, the final states and the tape given in the code of two machines.
+    /// The runtime state of the machines (the current tape, state and steps) is not compared
+    pub fn diff(&self, other: &TuringMachine) -> MachineDiff {
+        let mut diff = MachineDiff::default();
+
+        for instruction in self.instructions_sorted() {
+            match other.instructions().get(&instruction.key()) {
+                None => diff.removed.push(instruction.clone()),
+                Some(after) if after != instruction => diff.modified.push(InstructionChange {
+                    before: instruction.clone(),
+                    after: after.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        diff.added = other
+            .instructions_sorted()
+            .into_iter()
+            .filter(|i| !self.instructions().contains_key(&i.key()))
+            .cloned()
+            .collect();
+
+        if self.initial_state() != other.initial_state() {
+            diff.initial_state = Some((
+                String::from(self.initial_state()),
+                String::from(other.initial_state()),
+            ));
+        }
+
+        let sorted = |tm: &TuringMachine| {
+            let mut states = tm.final_states().to_vec();
+            states.sort_by(|a, b| natural_cmp(a, b));
+            states
+        };
+        let (before, after) = (sorted(self), sorted(other));
+        if before != after {
+            diff.final_states = Some((before, after));
+        }
+
+        let (before, after) = (
+            self.machine_parts().tape_code().unwrap_or_default(),
+            other.machine_parts().tape_code().unwrap_or_default(),
+        );
+        if before != after {
+            diff.tape = Some((before, after));
+        }
+
+        diff
+    }
+}
@@ -0,0 +1,423 @@
+use std::{collections::HashMap, fmt::Display, ops::Range, sync::Arc};
+
+use pest::{error::InputLocation, Parser, Position, Span};
+
+use crate::{
+    CompilerError, CompilerWarning, ErrorPosition, InstructionKey, Rule, SourceSpan,
+    TuringInstruction, TuringMachine, TuringParser,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An error when renaming a state with `rename_state`
+pub enum RenameError {
+    /// The machine has no state with the given name
+    UnknownState(String),
+
+    /// The machine already has a state with the new name
+    ExistingState(String),
+
+    /// The new name is not a valid state name, which is made of letters followed by digits (e.g. `q10`)
+    InvalidName(String),
+}
+
+impl Display for RenameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownState(state) => write!(f, "The state {} does not exist", state),
+            Self::ExistingState(state) => write!(f, "The state {} already exists", state),
+            Self::InvalidName(name) => write!(f, "\"{}\" is not a valid state name", name),
+        }
+    }
+}
+
+impl std::error::Error for RenameError {}
+
+impl TuringMachine {
+    /// Renames a state everywhere in the machine: its instructions, final states, current state, frequencies,
+    /// coverage, breakpoints and trace. The code is written again from the renamed machine.
+    ///
+    /// Returns an error (leaving the machine untouched) if the state does not exist,
+    /// or if the new name is already used or is not a valid state name
+    pub fn rename_state(&mut self, old: &str, new: &str) -> Result<(), RenameError> {
+        let valid = TuringParser::parse(Rule::state, new).is_ok_and(|p| p.as_str() == new);
+        if !valid {
+            return Err(RenameError::InvalidName(String::from(new)));
+        }
+
+        let states = self.states();
+        if !states.contains(&old) {
+            return Err(RenameError::UnknownState(String::from(old)));
+        }
+        if states.contains(&new) {
+            return Err(RenameError::ExistingState(String::from(new)));
+        }
+
+        let rename = |state: &mut String| {
+            if state == old {
+                *state = String::from(new);
+            }
+        };
+        let rename_keys = |map: &mut HashMap<(String, bool), usize>| {
+            *map = std::mem::take(map)
+                .into_iter()
+                .map(|((mut state, value), n)| {
+                    rename(&mut state);
+                    ((state, value), n)
+                })
+                .collect();
+        };
+
+        let program = Arc::make_mut(&mut self.program);
+
+        program.instructions = std::mem::take(&mut program.instructions)
+            .into_values()
+            .map(|mut instruction| {
+                rename(&mut instruction.from_state);
+                rename(&mut instruction.to_state);
+
+                (instruction.key(), instruction)
+            })
+            .collect();
+
+        if let Some(choices) = &mut program.choices {
+            *choices = std::mem::take(choices)
+                .into_iter()
+                .map(|(mut key, mut instructions)| {
+                    rename(&mut key.state);
+                    for instruction in instructions.iter_mut() {
+                        rename(&mut instruction.from_state);
+                        rename(&mut instruction.to_state);
+                    }
+
+                    (key, instructions)
+                })
+                .collect();
+        }
+
+        program.libraries = std::mem::take(&mut program.libraries)
+            .into_iter()
+            .map(|(mut key, library)| {
+                rename(&mut key.state);
+                (key, library)
+            })
+            .collect();
+
+        program
+            .final_states
+            .iter_mut()
+            .chain(program.accepting_states.iter_mut())
+            .chain(program.rejecting_states.iter_mut())
+            .for_each(rename);
+
+        // The ids of the states are kept, so the current, previous and initial states and the history stay valid
+        program.states.rename(old, new);
+        program.compile();
+
+        if let Some(coverage) = &mut self.coverage {
+            rename_keys(coverage);
+        }
+
+        if self.breakpoints.remove(old) {
+            self.breakpoints.insert(String::from(new));
+        }
+
+        if let Some(trace) = &mut self.trace {
+            for entry in trace.iter_mut() {
+                rename(&mut entry.from_state);
+                rename(&mut entry.to_state);
+                rename(&mut entry.instruction.from_state);
+                rename(&mut entry.instruction.to_state);
+            }
+        }
+
+        // The configurations are hashed with the name of the state
+        if self.configurations.is_some() {
+            self.detect_cycles(true);
+        }
+
+        self.regenerate_code();
+
+        Ok(())
+    }
+
+    /// Adds an instruction to the machine, overwriting the one with the same state and value (if any),
+    /// and writes the code again. Returns the `StateOverwrite` warning if an instruction was overwritten.
+    ///
+    /// In a non-deterministic machine the instruction is added as another choice, like in `new_nondeterministic`
+    pub fn add_instruction(&mut self, instruction: TuringInstruction) -> Option<CompilerWarning> {
+        let key = instruction.key();
+        let program = Arc::make_mut(&mut self.program);
+
+        if let Some(choices) = &mut program.choices {
+            let list = choices.entry(key.clone()).or_default();
+            if !list.contains(&instruction) {
+                list.push(instruction.clone());
+            }
+        }
+
+        program.libraries.remove(&key);
+        let warning = program
+            .instructions
+            .insert(key, instruction)
+            .map(|previous| CompilerWarning::StateOverwrite {
+                position: ErrorPosition::new((0, 0), None),
+                state: previous.from_state,
+                value_from: previous.from_value,
+            });
+
+        self.instructions_changed();
+
+        warning
+    }
+
+    /// Removes the instruction of the given state and value (every choice in a non-deterministic machine),
+    /// and writes the code again. Returns the removed instruction, if there was one.
+    ///
+    /// If the machine reaches that state and value afterwards, it gets stuck like with any undefined instruction
+    pub fn remove_instruction(&mut self, state: &str, value: bool) -> Option<TuringInstruction> {
+        let key = InstructionKey::new(state, value);
+        if !self.program.instructions.contains_key(&key) {
+            return None;
+        }
+
+        let program = Arc::make_mut(&mut self.program);
+        if let Some(choices) = &mut program.choices {
+            choices.remove(&key);
+        }
+        program.libraries.remove(&key);
+        let removed = program.instructions.remove(&key);
+
+        self.instructions_changed();
+
+        removed
+    }
+
+    /// Replaces the instruction with the same state and value as the given one (every choice in a
+    /// non-deterministic machine), and writes the code again. Returns the replaced instruction.
+    ///
+    /// Nothing is changed if the machine has no instruction for that state and value, use `add_instruction` instead
+    pub fn replace_instruction(
+        &mut self,
+        instruction: TuringInstruction,
+    ) -> Option<TuringInstruction> {
+        let key = instruction.key();
+        if !self.program.instructions.contains_key(&key) {
+            return None;
+        }
+
+        let program = Arc::make_mut(&mut self.program);
+        if let Some(choices) = &mut program.choices {
+            choices.insert(key.clone(), vec![instruction.clone()]);
+        }
+        program.libraries.remove(&key);
+        let replaced = program.instructions.insert(key, instruction);
+
+        self.instructions_changed();
+
+        replaced
+    }
+
+    /// Replaces the given lines of the code (starting at 0, like in `ErrorPosition`, and without the end one)
+    /// with a single instruction, without parsing the rest of the code again.
+    /// The instructions written in those lines are replaced by the new one, and `code` is patched with the new text.
+    ///
+    /// This is intentionally "edit and continue": the tape, the head, the current state and the step counter
+    /// are left untouched, so a paused execution continues with the edited program.
+    /// Returns the warnings of the new instruction, or an error (leaving the machine untouched)
+    /// if the text is not exactly one instruction, the lines are not in the code
+    /// or they hold something other than instructions, docs and comments, like the tape
+    pub fn patch_instruction_source(
+        &mut self,
+        lines: Range<usize>,
+        new_text: &str,
+    ) -> Result<Vec<CompilerWarning>, CompilerError> {
+        let code = self.code();
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(code.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        if lines.start > lines.end || lines.end > line_starts.len() {
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((lines.start, 0), Some((lines.end, 0))),
+                message: format!(
+                    "The lines {}..{} are not in the code, which has {} lines",
+                    lines.start,
+                    lines.end,
+                    line_starts.len()
+                ),
+                code: String::from(new_text),
+                expected: Rule::instruction,
+                found: None,
+            });
+        }
+
+        let start_byte = line_starts[lines.start];
+        let end_byte = line_starts.get(lines.end).copied().unwrap_or(code.len());
+        let replaced = &code[start_byte..end_byte];
+
+        // Only instructions can be replaced, or the tape and the states would be left out of the code
+        let replaced_lines = match TuringParser::parse(Rule::instruction_lines, replaced) {
+            Ok(mut pairs) => pairs.next().unwrap(),
+            Err(_) => {
+                return Err(CompilerError::SyntaxError {
+                    position: ErrorPosition::new((lines.start, 0), Some((lines.end, 0))),
+                    message: format!(
+                        "The lines {}..{} hold code that is not an instruction",
+                        lines.start, lines.end
+                    ),
+                    code: String::from(replaced),
+                    expected: Rule::instruction_lines,
+                    found: None,
+                });
+            }
+        };
+
+        let mut text = String::from(new_text.trim_end_matches('\n'));
+        if replaced.ends_with('\n') {
+            text.push('\n');
+        }
+        let patched = format!("{}{}{}", &code[..start_byte], text, &code[end_byte..]);
+
+        // The instruction is parsed alone, but the positions are given in the patched code
+        let trimmed = new_text.trim();
+        let text_start = start_byte + (new_text.len() - new_text.trim_start().len());
+        let position = |byte: usize| {
+            let (line, col) = Position::new(&patched, text_start + byte)
+                .unwrap()
+                .line_col();
+            (line - 1, col)
+        };
+        // The errors of the instruction are given in the text, like the ones of pest
+        let relocate = |mut error: CompilerError| {
+            let in_patched = |(line, col): (usize, usize)| {
+                let line_start: usize =
+                    trimmed.split_inclusive('\n').take(line).map(str::len).sum();
+                let column: usize = trimmed[line_start..]
+                    .chars()
+                    .take(col.saturating_sub(1))
+                    .map(char::len_utf8)
+                    .sum();
+                position(line_start + column)
+            };
+
+            if let CompilerError::SyntaxError { position, .. } = &mut error {
+                position.start = in_patched(position.start);
+                position.end = position.end.map(in_patched);
+            }
+            error
+        };
+
+        let pair = match TuringParser::parse(Rule::instruction, trimmed) {
+            Ok(mut pairs) => pairs.next().unwrap(),
+            Err(error) => {
+                let (start, end) = match error.location {
+                    InputLocation::Pos(p) => (position(p), None),
+                    InputLocation::Span((s, e)) => (position(s), Some(position(e))),
+                };
+
+                return Err(CompilerError::SyntaxError {
+                    position: ErrorPosition::new(start, end),
+                    message: error.variant.message().into_owned(),
+                    code: String::from(trimmed),
+                    expected: Rule::instruction,
+                    found: None,
+                });
+            }
+        };
+        if pair.as_str() != trimmed {
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new(position(pair.as_str().len()), None),
+                message: String::from("Expected a single instruction"),
+                code: String::from(trimmed),
+                expected: Rule::instruction,
+                found: None,
+            });
+        }
+
+        let mut instruction = TuringInstruction::from(pair.into_inner()).map_err(relocate)?;
+        let span_end = text_start + trimmed.len();
+        instruction.span = Span::new(&patched, text_start, span_end).map(|s| SourceSpan::from(&s));
+
+        // The instructions that were written in the replaced lines, if any
+        let previous: Vec<InstructionKey> = replaced_lines
+            .into_inner()
+            .filter(|record| record.as_rule() == Rule::instruction)
+            .filter_map(|record| TuringInstruction::expand(record.into_inner()).ok())
+            .flatten()
+            .map(|i| i.key())
+            .collect();
+
+        let key = instruction.key();
+        let mut warnings: Vec<CompilerWarning> = Vec::new();
+        if !previous.contains(&key) && self.program.instructions.contains_key(&key) {
+            let position = ErrorPosition::new(position(0), Some(position(trimmed.len())));
+
+            warnings.push(match self.program.libraries.get(&key) {
+                Some(library) => CompilerWarning::LibraryOverride {
+                    position,
+                    state: key.state.clone(),
+                    value_from: key.read,
+                    library: library.clone(),
+                },
+                None => CompilerWarning::StateOverwrite {
+                    position,
+                    state: key.state.clone(),
+                    value_from: key.read,
+                },
+            });
+        }
+
+        // The instructions written after the patch moved with the lines around them
+        let removed = replaced.len();
+        let shift = |byte: usize| byte + text.len() - removed;
+        let program = Arc::make_mut(&mut self.program);
+
+        for old in previous {
+            program.instructions.remove(&old);
+            program.libraries.remove(&old);
+            if let Some(choices) = &mut program.choices {
+                choices.remove(&old);
+            }
+        }
+
+        for (key, other) in program.instructions.iter_mut() {
+            let moved = other
+                .span
+                .filter(|s| !program.libraries.contains_key(key) && s.start_byte >= end_byte);
+
+            if let Some(span) = moved {
+                other.span = Span::new(&patched, shift(span.start_byte), shift(span.end_byte))
+                    .map(|s| SourceSpan::from(&s));
+            }
+        }
+
+        if let Some(choices) = &mut program.choices {
+            let list = choices.entry(key.clone()).or_default();
+            if !list.contains(&instruction) {
+                list.push(instruction.clone());
+            }
+        }
+        program.libraries.remove(&key);
+        program.instructions.insert(key, instruction);
+        program.code = patched;
+        program.compile();
+
+        if self.configurations.is_some() {
+            self.detect_cycles(true);
+        }
+
+        Ok(warnings)
+    }
+
+    /// Compiles the program again after its instructions changed, and writes the code again
+    fn instructions_changed(&mut self) {
+        Arc::make_mut(&mut self.program).compile();
+
+        // A configuration already seen may continue differently with the new instructions
+        if self.configurations.is_some() {
+            self.detect_cycles(true);
+        }
+
+        self.regenerate_code();
+    }
+}
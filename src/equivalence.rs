@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ExecutionResult, TuringMachine, TuringOutput};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// An input on which two machines compared with `equivalent_on` computed different numbers
+pub struct Counterexample {
+    /// The numbers given as input
+    pub input: Vec<u32>,
+
+    /// The number computed by the first machine, or None if it did not halt
+    pub expected: Option<u32>,
+
+    /// The number computed by the second machine, or None if it did not halt
+    pub got: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// The result of comparing two machines with `equivalent_on`
+pub struct EquivalenceReport {
+    /// The maximum number of steps of each run
+    pub max_steps: usize,
+
+    /// The number of inputs both machines were run on
+    pub inputs: usize,
+
+    /// The first input, in the order given, on which the machines computed different numbers
+    pub counterexample: Option<Counterexample>,
+}
+
+impl EquivalenceReport {
+    /// Returns true if both machines computed the same numbers on every input
+    pub fn is_equivalent(&self) -> bool {
+        self.counterexample.is_none()
+    }
+}
+
+impl TuringMachine {
+    /// Runs copies of this machine and `other` on each input, written with `set_input_values`, for up to `max_steps` steps,
+    /// and compares the numbers they compute (see `numeric_result`). Not halting counts as a different result
+    /// from any number, but two machines that do not halt on an input agree on it.
+    /// Neither machine is changed
+    pub fn equivalent_on<I>(
+        &self,
+        other: &TuringMachine,
+        inputs: I,
+        max_steps: usize,
+    ) -> EquivalenceReport
+    where
+        I: IntoIterator<Item = Vec<u32>>,
+    {
+        let inputs: Vec<Vec<u32>> = inputs.into_iter().collect();
+        // The inputs that cannot be encoded give an empty tape, which fails to run on both machines
+        let tapes: Vec<Vec<bool>> = inputs
+            .iter()
+            .map(|i| Self::encode_input(i).unwrap_or_default())
+            .collect();
+
+        let expected = self.run_batch(&tapes, max_steps);
+        let got = other.run_batch(&tapes, max_steps);
+
+        let counterexample = inputs
+            .iter()
+            .zip(expected.into_iter().zip(got))
+            .map(|(input, (expected, got))| Counterexample {
+                input: input.clone(),
+                expected: computed(&expected),
+                got: computed(&got),
+            })
+            .find(|c| c.expected != c.got);
+
+        EquivalenceReport {
+            max_steps,
+            inputs: inputs.len(),
+            counterexample,
+        }
+    }
+}
+
+/// Returns the number computed by a run, if it halted
+fn computed(result: &ExecutionResult) -> Option<u32> {
+    match result {
+        ExecutionResult::Halted {
+            output: TuringOutput::Defined((_, ones)),
+            ..
+        } => Some(*ones),
+        _ => None,
+    }
+}
@@ -0,0 +1,300 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{TuringInstruction, TuringMachine, TuringOutput, Verdict, Watchpoint};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The result of executing a single step of a Turing machine
+pub enum StepResult {
+    /// The step was executed and the machine is not in a final state
+    Continued,
+
+    /// The step was executed and the machine is in a final state
+    Halted,
+
+    /// There is no instruction for the current state and value, so no step was executed
+    Undefined {
+        /// The state the machine is stuck in
+        state: String,
+        /// The value read from the tape
+        value: bool,
+    },
+
+    /// The instruction moves the head left of the first cell of a left-bounded tape, so no step was executed
+    OutOfTape {
+        /// The state of the instruction
+        state: String,
+        /// The value read from the tape
+        value: bool,
+    },
+
+    /// The instruction moves the head to a new cell, which would make the tape use more cells than its limit,
+    /// so no step was executed
+    TapeLimitExceeded {
+        /// The limit of the tape
+        cells: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The result of running a Turing machine for a bounded number of steps
+pub enum ExecutionResult {
+    /// The machine executed the halting step of a final state
+    Halted {
+        /// The number of steps executed
+        steps: usize,
+        /// The output of the machine, with the total number of steps executed by the machine
+        output: TuringOutput,
+        /// Whether the machine accepted or rejected its input, if it halted in an accepting or rejecting state
+        verdict: Option<Verdict>,
+    },
+
+    /// The machine executed the maximum number of steps without halting
+    StepLimitReached { steps: usize },
+
+    /// There is no instruction for the current state and value, and the state is not final
+    Stuck {
+        /// The state the machine got stuck in
+        state: String,
+        /// The value read from the tape
+        value: bool,
+        /// The number of steps executed before getting stuck
+        steps: usize,
+    },
+
+    /// An instruction tried to move the head left of the first cell of a left-bounded tape
+    OutOfTape {
+        /// The state of the instruction
+        state: String,
+        /// The value read from the tape
+        value: bool,
+        /// The number of steps executed before the error
+        steps: usize,
+    },
+
+    /// An instruction tried to move the head to a new cell, which would make the tape use more cells
+    /// than the limit given with `set_tape_limit`
+    TapeLimitExceeded {
+        /// The limit of the tape
+        cells: usize,
+        /// The number of steps executed before the error
+        steps: usize,
+    },
+
+    /// The machine entered a state with a breakpoint
+    Breakpoint {
+        /// The state with the breakpoint
+        state: String,
+        /// The number of steps executed
+        steps: usize,
+    },
+
+    /// A (state, value) pair was executed more times than the loop threshold, so the machine is probably in an infinite loop
+    ProbableLoop { steps: usize },
+
+    /// A configuration repeated, so the machine is in an infinite loop (only detected if `detect_cycles` is enabled)
+    InfiniteLoop { steps: usize },
+
+    /// The predicate given to `run_until` returned true
+    PredicateMet { steps: usize },
+
+    /// The last step triggered a watchpoint
+    Watchpoint {
+        /// The watchpoint that was triggered
+        watchpoint: Watchpoint,
+        /// The number of steps executed
+        steps: usize,
+    },
+
+    /// The flag given to `run_with_cancel` was set
+    Cancelled { steps: usize },
+
+    /// The input given to `run_batch` was rejected like `set_input_tape` rejects it, so no step was executed
+    InvalidInput {
+        /// Why the input was rejected
+        message: String,
+    },
+}
+
+impl ExecutionResult {
+    /// Returns the number of steps executed
+    pub fn steps(&self) -> usize {
+        match self {
+            Self::Halted { steps, .. }
+            | Self::StepLimitReached { steps }
+            | Self::ProbableLoop { steps }
+            | Self::InfiniteLoop { steps }
+            | Self::PredicateMet { steps }
+            | Self::Cancelled { steps }
+            | Self::Stuck { steps, .. }
+            | Self::OutOfTape { steps, .. }
+            | Self::TapeLimitExceeded { steps, .. }
+            | Self::Breakpoint { steps, .. }
+            | Self::Watchpoint { steps, .. } => *steps,
+            Self::InvalidInput { .. } => 0,
+        }
+    }
+
+    /// Returns whether the machine accepted or rejected its input, which is only given if the machine halted
+    /// in an accepting or rejecting state
+    pub fn verdict(&self) -> Option<Verdict> {
+        match self {
+            Self::Halted { verdict, .. } => *verdict,
+            _ => None,
+        }
+    }
+
+    /// Returns the output of the machine, which is only defined if the machine halted
+    pub fn output(&self) -> TuringOutput {
+        match self {
+            Self::Halted { output, .. } => output.clone(),
+            Self::Stuck {
+                state,
+                value,
+                steps,
+            } => TuringOutput::Stuck {
+                steps: *steps,
+                state: state.clone(),
+                value: *value,
+            },
+            _ => TuringOutput::Undefined(self.steps()),
+        }
+    }
+}
+
+impl TuringMachine {
+    /// Runs the machine until it halts, gets stuck or executes `max_steps` steps.
+    /// The scanning loops are executed at once if `enable_fast_forward` was called
+    pub fn run(&mut self, max_steps: usize) -> ExecutionResult {
+        let fast_forward = self.fast_forward;
+        self.run_loop(max_steps, |_, _, _| {}, |_, _| None, fast_forward)
+    }
+
+    /// Executes up to `n` steps, stopping early if the machine halts or gets stuck,
+    /// and returns the number of steps actually executed
+    pub fn step_n(&mut self, n: usize) -> usize {
+        self.run(n).steps()
+    }
+
+    /// Runs the machine until `predicate` returns true, the machine halts, gets stuck or executes `max_steps` steps.
+    /// The predicate is evaluated after every step, except the halting one
+    pub fn run_until<F>(&mut self, mut predicate: F, max_steps: usize) -> ExecutionResult
+    where
+        F: FnMut(&TuringMachine) -> bool,
+    {
+        self.run_checked(
+            max_steps,
+            |_, _, _| {},
+            |tm, steps| predicate(tm).then_some(ExecutionResult::PredicateMet { steps }),
+        )
+    }
+
+    /// Runs the machine like `run`, but stops before the next step once `cancel` is set, e.g. from another thread.
+    /// The flag is checked before every step, so the machine can keep running or stepping afterwards
+    pub fn run_with_cancel(&mut self, max_steps: usize, cancel: &AtomicBool) -> ExecutionResult {
+        if cancel.load(Ordering::Relaxed) {
+            return ExecutionResult::Cancelled { steps: 0 };
+        }
+
+        self.run_checked(
+            max_steps,
+            |_, _, _| {},
+            |_, steps| {
+                cancel
+                    .load(Ordering::Relaxed)
+                    .then_some(ExecutionResult::Cancelled { steps })
+            },
+        )
+    }
+
+    /// Runs the machine like `run`, calling `observer` after every step like `step_with`
+    pub fn run_with<O>(&mut self, max_steps: usize, observer: O) -> ExecutionResult
+    where
+        O: FnMut(&TuringInstruction, usize, &str),
+    {
+        self.run_checked(max_steps, observer, |_, _| None)
+    }
+
+    /// Runs the machine like `run_with`, but calls `check` after every step that did not halt the machine
+    /// with the number of steps executed so far, stopping with its result if it returns one
+    pub(crate) fn run_checked<O, F>(
+        &mut self,
+        max_steps: usize,
+        observer: O,
+        check: F,
+    ) -> ExecutionResult
+    where
+        O: FnMut(&TuringInstruction, usize, &str),
+        F: FnMut(&Self, usize) -> Option<ExecutionResult>,
+    {
+        self.run_loop(max_steps, observer, check, false)
+    }
+
+    /// Runs the machine like `run_checked`, executing the scanning loops at once if `fast_forward` is true.
+    /// The observer and `check` are not called for the steps skipped
+    fn run_loop<O, F>(
+        &mut self,
+        max_steps: usize,
+        mut observer: O,
+        mut check: F,
+        fast_forward: bool,
+    ) -> ExecutionResult
+    where
+        O: FnMut(&TuringInstruction, usize, &str),
+        F: FnMut(&Self, usize) -> Option<ExecutionResult>,
+    {
+        let mut steps = 0;
+
+        while steps < max_steps {
+            if fast_forward {
+                steps += self.skip_scan(max_steps - steps);
+
+                if steps == max_steps {
+                    break;
+                }
+            }
+
+            // The step executed from a final state is the halting one
+            let halting = self.finished();
+
+            match self.step_with(&mut observer) {
+                StepResult::Undefined { state, value } => {
+                    return ExecutionResult::Stuck {
+                        state,
+                        value,
+                        steps,
+                    }
+                }
+                StepResult::OutOfTape { state, value } => {
+                    return ExecutionResult::OutOfTape {
+                        state,
+                        value,
+                        steps,
+                    }
+                }
+                StepResult::TapeLimitExceeded { cells } => {
+                    return ExecutionResult::TapeLimitExceeded { cells, steps }
+                }
+                StepResult::Continued | StepResult::Halted => {}
+            }
+            steps += 1;
+
+            if halting {
+                return ExecutionResult::Halted {
+                    steps,
+                    output: self.tape_value(),
+                    verdict: self.verdict(),
+                };
+            }
+
+            if self.cycle_detected {
+                return ExecutionResult::InfiniteLoop { steps };
+            }
+
+            if let Some(result) = check(self, steps) {
+                return result;
+            }
+        }
+
+        ExecutionResult::StepLimitReached { steps }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{program::StateKey, Movement, TuringInstruction, TuringMachine};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+/// The language of the explanations given by `explain_next_step_in`
+pub enum Language {
+    /// English, with the names of the `L` and `R` movements
+    #[default]
+    English,
+
+    /// Spanish, with the names of the `I` and `D` movements
+    Spanish,
+}
+
+impl TuringMachine {
+    /// Explains in English what the next step does, like "In state q1 reading 1: write 0, move Right, go to q2".
+    /// Returns None if the machine is stuck
+    pub fn explain_next_step(&self) -> Option<String> {
+        self.explain_next_step_in(Language::English)
+    }
+
+    /// Explains what the next step does in the given language, like `explain_next_step`
+    pub fn explain_next_step_in(&self, language: Language) -> Option<String> {
+        self.next_instruction().map(|resolved| {
+            self.explain(
+                resolved.instruction(),
+                resolved.is_implicit_halt(),
+                language,
+            )
+        })
+    }
+
+    /// Explains what an instruction of the machine does, naming the library it comes from if it was composed
+    pub(crate) fn explain(
+        &self,
+        instruction: &TuringInstruction,
+        implicit_halt: bool,
+        language: Language,
+    ) -> String {
+        let read = u8::from(instruction.from_value);
+
+        let mut explanation = match (language, implicit_halt) {
+            (Language::English, true) => format!(
+                "In state {} reading {}: halt, since {} is a final state",
+                instruction.from_state, read, instruction.from_state
+            ),
+            (Language::Spanish, true) => format!(
+                "En el estado {} leyendo {}: parar, ya que {} es un estado final",
+                instruction.from_state, read, instruction.from_state
+            ),
+            (Language::English, false) => format!(
+                "In state {} reading {}: write {}, {}, go to {}",
+                instruction.from_state,
+                read,
+                u8::from(instruction.to_value),
+                match instruction.movement {
+                    Movement::RIGHT => "move Right",
+                    Movement::LEFT => "move Left",
+                    Movement::HALT => "do not move",
+                    Movement::STAY => "stay in place",
+                },
+                instruction.to_state
+            ),
+            (Language::Spanish, false) => format!(
+                "En el estado {} leyendo {}: escribir {}, {}, ir a {}",
+                instruction.from_state,
+                read,
+                u8::from(instruction.to_value),
+                match instruction.movement {
+                    Movement::RIGHT => "mover a la Derecha",
+                    Movement::LEFT => "mover a la Izquierda",
+                    Movement::HALT => "no moverse",
+                    Movement::STAY => "quedarse quieto",
+                },
+                instruction.to_state
+            ),
+        };
+
+        if let Some(library) = self.library_of(instruction) {
+            explanation += &match language {
+                Language::English => format!(" (from the library {})", library),
+                Language::Spanish => format!(" (de la librería {})", library),
+            };
+        }
+
+        explanation
+    }
+
+    /// Returns the composed library an instruction of the machine comes from, as it is written in `compose`,
+    /// or None if it was given in the code or is not an instruction of the machine
+    pub fn library_of(&self, instruction: &TuringInstruction) -> Option<&str> {
+        let key: &dyn StateKey = &(instruction.from_state.as_str(), instruction.from_value);
+
+        if self.program.instructions.get(key) != Some(instruction) {
+            return None;
+        }
+
+        self.program.libraries.get(key).map(String::as_str)
+    }
+}
@@ -0,0 +1,120 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use log::{debug, warn};
+use pest::iterators::Pair;
+
+use crate::{
+    turing::list_items, CompilerError, CompilerWarning, Library, Rule, TuringInstruction,
+    TuringMachine, LIBRARIES,
+};
+
+impl TuringMachine {
+    /// Builds the libraries of the functions defined in the code with `fn name { ... }`, by their names.
+    /// The `///` comments right before a function are its description.
+    ///
+    /// A function with the name of a built-in library gives a `LibraryShadowed` warning, since it replaces it,
+    /// and defining a function twice or with several final states is an error
+    pub(crate) fn parse_functions(
+        file: &Pair<Rule>,
+        warnings: &mut Vec<CompilerWarning>,
+    ) -> Result<HashMap<String, Library>, CompilerError> {
+        let mut functions: HashMap<String, Library> = HashMap::new();
+        let mut docs: Vec<&str> = Vec::new();
+
+        for record in file.clone().into_inner() {
+            if record.as_rule() == Rule::doc {
+                docs.push(record.as_str());
+                continue;
+            }
+            if record.as_rule() != Rule::function {
+                docs.clear();
+                continue;
+            }
+
+            let description = TuringInstruction::doc_from(&docs).unwrap_or_default();
+            docs.clear();
+
+            let mut inner = record.clone().into_inner();
+            let name = inner.next().unwrap();
+            let initial_state = String::from(inner.next().unwrap().into_inner().as_str());
+
+            if functions.contains_key(name.as_str()) {
+                return Err(Self::repeated_definition(
+                    &record,
+                    &format!("function {}", name.as_str()),
+                ));
+            }
+
+            let final_states = inner.next().unwrap();
+            let final_states_code = String::from(final_states.as_str());
+            let final_states = list_items(final_states, warnings);
+            if final_states.len() != 1 {
+                return Err(CompilerError::SyntaxError {
+                    position: record.as_span().into(),
+                    message: format!(
+                        "The function {} needs exactly one final state, like a library",
+                        name.as_str()
+                    ),
+                    code: final_states_code,
+                    expected: Rule::function,
+                    found: Some(Rule::final_state),
+                });
+            }
+            let final_state = String::from(final_states[0].as_str());
+
+            let mut used_states = vec![initial_state.clone(), final_state.clone()];
+            let mut code = String::new();
+            let mut instruction_docs: Vec<&str> = Vec::new();
+
+            for r in inner {
+                if r.as_rule() == Rule::doc {
+                    instruction_docs.push(r.as_str());
+                    continue;
+                }
+
+                for instruction in TuringInstruction::expand(r.into_inner())? {
+                    for line in instruction_docs.iter() {
+                        code += &format!("{}\n", line.trim());
+                    }
+                    code += &format!("{};\n", instruction);
+
+                    used_states.push(instruction.from_state);
+                    used_states.push(instruction.to_state);
+                }
+                instruction_docs.clear();
+            }
+
+            used_states.sort_by(|a, b| crate::natural_cmp(a, b));
+            used_states.dedup();
+
+            if LIBRARIES.iter().any(|l| l.name == name.as_str()) {
+                warn!(
+                    "The function {} replaces the library with the same name",
+                    name.as_str()
+                );
+
+                warnings.push(CompilerWarning::LibraryShadowed {
+                    position: name.as_span().into(),
+                    library: String::from(name.as_str()),
+                });
+            }
+
+            debug!("Found the function {}", name.as_str());
+
+            functions.insert(
+                String::from(name.as_str()),
+                Library {
+                    name: Cow::Owned(String::from(name.as_str())),
+                    description: Cow::Owned(description),
+                    initial_state: Cow::Owned(initial_state),
+                    final_state: Cow::Owned(final_state),
+                    used_states: Cow::Owned(used_states.into_iter().map(Cow::Owned).collect()),
+                    code: Cow::Owned(code),
+                    alias: None,
+                },
+            );
+        }
+
+        Ok(functions)
+    }
+}
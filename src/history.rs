@@ -0,0 +1,91 @@
+use log::debug;
+
+use crate::{program::StateId, TuringMachine};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The information needed to undo a single step of a Turing machine
+pub(crate) struct HistoryEntry {
+    /// The state the machine was in before the step
+    pub state: StateId,
+
+    /// The previous state the machine had before the step
+    pub previous_state: Option<StateId>,
+
+    /// The position of the head before the step (before any cell was inserted at the front)
+    pub tape_position: usize,
+
+    /// The value of the cell that was overwritten
+    pub value: bool,
+
+    /// The number of cells inserted at the front of the tape during the step
+    pub grew_left: usize,
+
+    /// The number of cells pushed at the back of the tape during the step
+    pub grew_right: usize,
+}
+
+impl TuringMachine {
+    /// Starts recording the last `capacity` steps so they can be undone with `step_back`.
+    /// A capacity of 0 disables the history
+    pub fn enable_history(&mut self, capacity: usize) {
+        self.history_capacity = capacity;
+
+        while self.history.len() > capacity {
+            self.history.pop_front();
+        }
+    }
+
+    /// Stops recording steps and forgets the recorded history
+    pub fn disable_history(&mut self) {
+        self.enable_history(0);
+    }
+
+    /// Returns the number of steps that can currently be undone
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    pub(crate) fn record_history(&mut self, entry: HistoryEntry) {
+        if self.history.len() == self.history_capacity {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(entry);
+    }
+
+    /// Undoes the last recorded step. Returns false if there is no step to undo
+    pub fn step_back(&mut self) -> bool {
+        let Some(entry) = self.history.pop_back() else {
+            return false;
+        };
+
+        debug!(
+            "Undoing step from state {}",
+            self.program.states.name(entry.state)
+        );
+
+        self.tape
+            .resize(self.tape.len() - entry.grew_right, self.blank);
+        self.tape.pop_front(entry.grew_left);
+        self.origin -= entry.grew_left;
+        self.tape.set(entry.tape_position, entry.value);
+        self.tape_position = entry.tape_position;
+
+        if let Some(counts) = self.frequencies.get_mut(entry.state.index()) {
+            counts[entry.value as usize] = counts[entry.value as usize].saturating_sub(1);
+        }
+
+        if self
+            .max_frequency
+            .is_some_and(|(state, value, _)| state == entry.state && value == entry.value)
+        {
+            self.update_max_frequency();
+        }
+
+        self.steps -= 1;
+        self.current_state = entry.state;
+        self.previous_state = entry.previous_state;
+
+        true
+    }
+}
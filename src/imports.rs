@@ -0,0 +1,144 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+
+use log::{debug, error};
+
+use crate::{parts::MachineParts, CompilerError, CompilerWarning, TuringMachine};
+
+/// Reads the files imported with `import "sum.tm";`, so the compiler never touches the filesystem by itself
+pub trait Resolver {
+    /// Returns the code of the file with the given path, as it is written in the `import`
+    fn resolve(&self, path: &str) -> Result<String, io::Error>;
+}
+
+/// The files kept in memory, by their path
+impl Resolver for HashMap<String, String> {
+    fn resolve(&self, path: &str) -> Result<String, io::Error> {
+        self.get(path).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("The file {} does not exist", path),
+            )
+        })
+    }
+}
+
+#[cfg(any(test, feature = "fs"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Reads the imported files from the filesystem, relative to a directory
+pub struct FileResolver {
+    root: std::path::PathBuf,
+}
+
+#[cfg(any(test, feature = "fs"))]
+impl FileResolver {
+    /// Creates a resolver that reads the paths relative to `root`, usually the directory of the main file
+    pub fn new<P: Into<std::path::PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[cfg(any(test, feature = "fs"))]
+impl Resolver for FileResolver {
+    fn resolve(&self, path: &str) -> Result<String, io::Error> {
+        std::fs::read_to_string(self.root.join(path))
+    }
+}
+
+impl TuringMachine {
+    /// Create a new Turing machine from a string of code, like `new`, reading the files of its `import "sum.tm";`
+    /// with `resolver`. The instructions of the imported files (and of the files they import) come before the ones
+    /// of the code, so the code can overwrite them, and the libraries they compose are composed too.
+    /// Their tapes, initial and final states are left out.
+    ///
+    /// A file imported twice is only read once. Importing a file that imports itself through other files is an
+    /// `ImportError`, like a file that cannot be read or compiled
+    pub fn new_with_resolver(
+        code: &str,
+        resolver: &dyn Resolver,
+    ) -> Result<(Self, Vec<CompilerWarning>), CompilerError> {
+        let mut parts = Self::parse_parts(code)?;
+        Self::resolve_imports(&mut parts, resolver, &mut Vec::new(), &mut HashSet::new())?;
+
+        Self::from_machine_parts(parts)
+    }
+
+    /// Adds the instructions and the libraries of the files imported by `parts`, importing their files first.
+    /// `stack` has the files being imported, to find the cycles, and `imported` every file already read
+    fn resolve_imports(
+        parts: &mut MachineParts,
+        resolver: &dyn Resolver,
+        stack: &mut Vec<String>,
+        imported: &mut HashSet<String>,
+    ) -> Result<(), CompilerError> {
+        let mut instructions = Vec::new();
+
+        for (path, position) in std::mem::take(&mut parts.imports) {
+            let import_error = |source: String| {
+                error!("Could not import the file {}: {}", path, source);
+
+                CompilerError::ImportError {
+                    path: path.clone(),
+                    position,
+                    source,
+                }
+            };
+
+            if stack.contains(&path) {
+                let cycle: Vec<&str> = stack
+                    .iter()
+                    .chain(std::iter::once(&path))
+                    .map(|p| p.as_str())
+                    .collect();
+
+                return Err(import_error(format!(
+                    "The file imports itself through {}",
+                    cycle.join(" -> ")
+                )));
+            }
+            if !imported.insert(path.clone()) {
+                debug!("The file {} was already imported", path);
+                continue;
+            }
+
+            let code = resolver
+                .resolve(&path)
+                .map_err(|e| import_error(e.to_string()))?;
+            let mut file = Self::parse_parts(&code).map_err(|e| import_error(e.message()))?;
+
+            stack.push(path.clone());
+            Self::resolve_imports(&mut file, resolver, stack, imported)
+                .map_err(|e| import_error(e.message()))?;
+            stack.pop();
+
+            debug!(
+                "Imported {} instructions from {}",
+                file.instructions.len(),
+                path
+            );
+
+            // The spans of the instructions point at the code of the other file
+            for (mut instruction, _) in file.instructions {
+                instruction.span = None;
+                instructions.push((instruction, position));
+            }
+
+            for (library, _) in file.composed {
+                if !parts
+                    .composed
+                    .iter()
+                    .any(|(l, _)| l.composed_name() == library.composed_name())
+                {
+                    parts.composed.push((library, position));
+                }
+            }
+        }
+
+        instructions.append(&mut parts.instructions);
+        parts.instructions = instructions;
+
+        Ok(())
+    }
+}
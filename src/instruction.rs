@@ -1,15 +1,27 @@
-use std::{fmt::Display, str::FromStr};
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
 
-use crate::{turing::Rule, CompilerError, ErrorPosition};
-use pest::iterators::Pairs;
-use serde::{Deserialize, Serialize};
+use crate::{
+    turing::{binary_value, Rule},
+    CompilerError, ErrorPosition,
+};
+use pest::{iterators::Pairs, Span};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
-/// The possible movements of the tape head
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// The possible movements of the tape head.
+/// They are serialized with the letters of the code, `R`, `L`, `H` and `S`.
+///
+/// Neither `HALT` nor `STAY` stop the machine: it keeps stepping while the target state has an instruction
+/// for the value under the head. A machine halts by going to a final state
 pub enum Movement {
+    /// Move the head one cell to the right, `R` (or `D`, derecha)
     RIGHT,
+    /// Move the head one cell to the left, `L` (or `I`, izquierda)
     LEFT,
+    /// Do not move the head, `H` (or `N`, ninguno). Usually written in the instructions that go to a final state
     HALT,
+    /// Do not move the head and continue with the target state, `S` (or `Q`, quieto)
+    STAY,
 }
 
 impl std::str::FromStr for Movement {
@@ -21,11 +33,41 @@ impl std::str::FromStr for Movement {
             "R" | "D" => Ok(Self::RIGHT),
             "L" | "I" => Ok(Self::LEFT),
             "H" | "N" => Ok(Self::HALT),
+            "S" | "Q" => Ok(Self::STAY),
             _ => Err(format!("\"{input}\" is an unknown movement")),
         }
     }
 }
 
+impl Serialize for Movement {
+    /// Serialize a movement as its letter, like `Display`
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Movement {
+    /// Deserialize a movement from any of the letters accepted by `FromStr`,
+    /// or from the names of the variants it was serialized with before
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+
+        match name.as_str() {
+            "RIGHT" => Ok(Self::RIGHT),
+            "LEFT" => Ok(Self::LEFT),
+            "HALT" => Ok(Self::HALT),
+            "STAY" => Ok(Self::STAY),
+            _ => Self::from_str(&name).map_err(de::Error::custom),
+        }
+    }
+}
+
 impl Display for Movement {
     /// Display a movement as a string
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -33,6 +75,64 @@ impl Display for Movement {
             Movement::RIGHT => write!(f, "R"),
             Movement::LEFT => write!(f, "L"),
             Movement::HALT => write!(f, "H"),
+            Movement::STAY => write!(f, "S"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The instruction the next step of a machine executes
+pub enum ResolvedInstruction {
+    /// An instruction given in the code or by a composed library
+    Explicit(TuringInstruction),
+
+    /// The halt instruction executed from a final state without an instruction for the read value,
+    /// which leaves the tape and the state untouched
+    ImplicitHalt(TuringInstruction),
+}
+
+impl ResolvedInstruction {
+    /// Returns the instruction, whether it is explicit or not
+    pub fn instruction(&self) -> &TuringInstruction {
+        match self {
+            Self::Explicit(i) | Self::ImplicitHalt(i) => i,
+        }
+    }
+
+    /// Returns true if the instruction is the implicit halt of a final state
+    pub fn is_implicit_halt(&self) -> bool {
+        matches!(self, Self::ImplicitHalt(_))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// The part of the code an instruction was written in.
+/// Like in `ErrorPosition`, the lines start at 0 and the columns start at 1
+pub struct SourceSpan {
+    /// The line and column where the instruction starts
+    pub start: (usize, usize),
+
+    /// The line and column where the instruction ends, right after its last character
+    pub end: (usize, usize),
+
+    /// The offset in bytes of the start of the instruction in the code
+    pub start_byte: usize,
+
+    /// The offset in bytes of the end of the instruction in the code
+    pub end_byte: usize,
+}
+
+impl From<&Span<'_>> for SourceSpan {
+    /// Convert a `&pest::Span` to a `SourceSpan`
+    fn from(span: &Span) -> Self {
+        let (start_line, start_col) = span.start_pos().line_col();
+        let (end_line, end_col) = span.end_pos().line_col();
+
+        Self {
+            start: (start_line - 1, start_col),
+            end: (end_line - 1, end_col),
+            start_byte: span.start(),
+            end_byte: span.end(),
         }
     }
 }
@@ -45,6 +145,38 @@ pub struct TuringInstruction {
     pub to_value: bool,
     pub movement: Movement,
     pub to_state: String,
+
+    /// Where the instruction was written in the code, or None if it was not parsed (e.g. the halt instructions)
+    #[serde(default, skip_serializing_if = "crate::serialization::skip_none")]
+    pub span: Option<SourceSpan>,
+
+    /// The text of the `///` comments written right before the instruction, one line each
+    #[serde(default, skip_serializing_if = "crate::serialization::skip_none")]
+    pub doc: Option<String>,
+}
+
+impl PartialEq for TuringInstruction {
+    /// Two instructions are equal if they do the same, wherever they were written and whatever their docs say
+    fn eq(&self, other: &Self) -> bool {
+        self.from_state == other.from_state
+            && self.from_value == other.from_value
+            && self.to_value == other.to_value
+            && self.movement == other.movement
+            && self.to_state == other.to_state
+    }
+}
+
+impl Eq for TuringInstruction {}
+
+impl std::hash::Hash for TuringInstruction {
+    /// Hash an instruction without its span and its doc, like `PartialEq`
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.from_state.hash(state);
+        self.from_value.hash(state);
+        self.to_value.hash(state);
+        self.movement.hash(state);
+        self.to_state.hash(state);
+    }
 }
 
 impl Display for TuringInstruction {
@@ -62,19 +194,173 @@ impl Display for TuringInstruction {
     }
 }
 
+/// Compares two state names in natural order, comparing their runs of digits as numbers,
+/// so that `q2` comes before `q10`. Names that only differ in leading 0s (`q01` and `q1`) are compared as strings
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    /// Splits a name into runs of digits and runs of other characters
+    fn chunks(s: &str) -> impl Iterator<Item = &str> {
+        let mut rest = s;
+
+        std::iter::from_fn(move || {
+            let first = rest.chars().next()?;
+            let end = rest
+                .find(|c: char| c.is_ascii_digit() != first.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let (chunk, tail) = rest.split_at(end);
+            rest = tail;
+
+            Some(chunk)
+        })
+    }
+
+    let compare = |x: &str, y: &str| {
+        let digits = x.starts_with(|c: char| c.is_ascii_digit())
+            && y.starts_with(|c: char| c.is_ascii_digit());
+
+        if digits {
+            let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+            x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+        } else {
+            x.cmp(y)
+        }
+    };
+
+    let mut left = chunks(a);
+    let mut right = chunks(b);
+    loop {
+        match (left.next(), right.next()) {
+            (Some(x), Some(y)) => match compare(x, y) {
+                Ordering::Equal => continue,
+                other => return other,
+            },
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// The state and the read value an instruction is found with in the instructions of a machine
+pub struct InstructionKey {
+    /// The state the instruction is executed in
+    pub state: String,
+
+    /// The value the instruction reads
+    pub read: bool,
+}
+
+impl InstructionKey {
+    /// Creates the key of the instruction for the given state and read value
+    pub fn new(state: &str, read: bool) -> Self {
+        Self {
+            state: String::from(state),
+            read,
+        }
+    }
+}
+
+impl From<(String, bool)> for InstructionKey {
+    fn from((state, read): (String, bool)) -> Self {
+        Self { state, read }
+    }
+}
+
+impl From<InstructionKey> for (String, bool) {
+    fn from(key: InstructionKey) -> Self {
+        (key.state, key.read)
+    }
+}
+
+impl PartialOrd for InstructionKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InstructionKey {
+    /// Sorts the keys by their state in natural order (see `natural_cmp`) and then by their value
+    fn cmp(&self, other: &Self) -> Ordering {
+        natural_cmp(&self.state, &other.state).then_with(|| self.read.cmp(&other.read))
+    }
+}
+
+impl Display for InstructionKey {
+    /// Display a key like the start of an instruction, `(state, value)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.state, u8::from(self.read))
+    }
+}
+
+impl PartialOrd for TuringInstruction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TuringInstruction {
+    /// Sorts the instructions like `cmp_by_state`, and then by what they do, so only equal instructions are equal
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_by_state(other)
+            .then_with(|| self.to_value.cmp(&other.to_value))
+            .then_with(|| self.movement.cmp(&other.movement))
+            .then_with(|| natural_cmp(&self.to_state, &other.to_state))
+    }
+}
+
 impl TuringInstruction {
-    /// Create an instruction from a `Pairs<Rule>` object
-    pub fn from(mut code: Pairs<Rule>) -> Result<Self, CompilerError> {
+    /// Returns the key the instruction is found with in the instructions of a machine
+    pub fn key(&self) -> InstructionKey {
+        InstructionKey::new(&self.from_state, self.from_value)
+    }
+
+    /// Compares two instructions by their state in natural order (see `natural_cmp`) and then by their value
+    pub fn cmp_by_state(&self, other: &Self) -> Ordering {
+        natural_cmp(&self.from_state, &other.from_state)
+            .then_with(|| self.from_value.cmp(&other.from_value))
+    }
+
+    /// Create an instruction from a `Pairs<Rule>` object.
+    /// Gives an error if it reads the wildcard `*`, which stands for several instructions (see `expand`)
+    pub fn from(code: Pairs<Rule>) -> Result<Self, CompilerError> {
+        match code.clone().nth(1) {
+            Some(read) if read.as_rule() == Rule::wildcard => Err(CompilerError::SyntaxError {
+                position: ErrorPosition::from(&read),
+                message: String::from("Expected a single instruction, but * reads every value"),
+                code: String::from(read.as_str()),
+                expected: Rule::value,
+                found: Some(Rule::wildcard),
+            }),
+            _ => Self::parse(code, None),
+        }
+    }
+
+    /// Create the instructions of a `Pairs<Rule>` object: one for each value if it reads the wildcard `*`,
+    /// or only the one written otherwise. Writing `*` writes back the value read
+    pub fn expand(code: Pairs<Rule>) -> Result<Vec<Self>, CompilerError> {
+        match code.clone().nth(1) {
+            Some(read) if read.as_rule() == Rule::wildcard => [false, true]
+                .into_iter()
+                .map(|value| Self::parse(code.clone(), Some(value)))
+                .collect(),
+            _ => Ok(vec![Self::parse(code, None)?]),
+        }
+    }
+
+    /// Parses an instruction, reading `read` instead of the value written if it is given
+    fn parse(mut code: Pairs<Rule>, read: Option<bool>) -> Result<Self, CompilerError> {
         let from_state = match code.next() {
             Some(s) => String::from(s.as_span().as_str()),
             None => panic!("The instruction lacks an initial state"),
         };
-        let from_value = match code.next() {
-            Some(s) => s.as_span().as_str() == "1",
-            None => panic!("The instruction lacks an initial tape value"),
+        let from_value = match (code.next(), read) {
+            (Some(_), Some(value)) => value,
+            (Some(s), None) => binary_value(&s)?,
+            (None, _) => panic!("The instruction lacks an initial tape value"),
         };
         let to_value = match code.next() {
-            Some(s) => s.as_span().as_str() == "1",
+            Some(s) if s.as_rule() == Rule::wildcard => from_value,
+            Some(s) => binary_value(&s)?,
             None => panic!("The instruction lacks a target tape value"),
         };
 
@@ -105,17 +391,36 @@ impl TuringInstruction {
             to_value,
             movement,
             to_state,
+            span: None,
+            doc: None,
         })
     }
 
+    /// Builds the doc of an instruction from the `///` comments written right before it,
+    /// or None if there are none or they are empty
+    pub(crate) fn doc_from(comments: &[&str]) -> Option<String> {
+        let doc = comments
+            .iter()
+            .map(|c| c.trim_start_matches('/').trim())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let doc = doc.trim();
+
+        (!doc.is_empty()).then(|| String::from(doc))
+    }
+
     /// Create a halt instruction when there is missing information
-    pub fn halt(index: (String, bool)) -> Self {
+    pub fn halt(key: impl Into<InstructionKey>) -> Self {
+        let key = key.into();
+
         Self {
-            from_state: index.0.clone(),
-            from_value: index.1,
-            to_value: index.1,
+            from_state: key.state.clone(),
+            from_value: key.read,
+            to_value: key.read,
             movement: Movement::HALT,
-            to_state: index.0,
+            to_state: key.state,
+            span: None,
+            doc: None,
         }
     }
 }
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{StepResult, TuringMachine};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// A copy of the configuration of a Turing machine after executing a step
+pub struct Configuration {
+    /// The number of steps executed so far (starting at 1 for the first yielded configuration)
+    pub step: usize,
+
+    /// The state of the machine after the step
+    pub state: String,
+
+    /// The position of the head on the tape after the step
+    pub tape_position: usize,
+
+    /// The tape after the step
+    pub tape: Vec<bool>,
+
+    /// True if the machine got stuck, i.e. there is no instruction for the current state and value.
+    /// This is always the last configuration yielded, and no step was executed to produce it.
+    pub undefined: bool,
+}
+
+/// An iterator that executes a Turing machine one step at a time,
+/// yielding the configuration of the machine after each step.
+///
+/// It stops after the halting step of a final state, or after yielding an undefined configuration.
+pub struct Steps<'a> {
+    tm: &'a mut TuringMachine,
+    step: usize,
+    done: bool,
+}
+
+impl<'a> Steps<'a> {
+    pub(crate) fn new(tm: &'a mut TuringMachine) -> Self {
+        Self {
+            tm,
+            step: 0,
+            done: false,
+        }
+    }
+
+    fn configuration(&self, undefined: bool) -> Configuration {
+        Configuration {
+            step: self.step,
+            state: String::from(self.tm.current_state()),
+            tape_position: self.tm.tape_position,
+            tape: self.tm.tape.to_vec(),
+            undefined,
+        }
+    }
+}
+
+impl Iterator for Steps<'_> {
+    type Item = Configuration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        if self.tm.is_undefined() {
+            self.done = true;
+            return Some(self.configuration(true));
+        }
+
+        // The step executed from a final state is the last one, just like in `final_result`
+        self.done = self.tm.finished();
+        if let StepResult::OutOfTape { .. } | StepResult::TapeLimitExceeded { .. } =
+            self.tm.advance()
+        {
+            self.done = true;
+            return Some(self.configuration(true));
+        }
+        self.step += 1;
+
+        Some(self.configuration(false))
+    }
+}
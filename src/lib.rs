@@ -1,16 +1,92 @@
+mod alphabet;
+mod analysis;
+mod batch;
+#[cfg(any(test, feature = "binary"))]
+mod binary;
+mod breakpoints;
+mod builder;
+mod checkpoints;
+mod comments;
+mod compilation;
+mod coverage;
+mod cycles;
+mod delta;
+mod diff;
+mod edit;
+mod equivalence;
+mod execution;
+mod explain;
+mod functions;
+mod history;
+mod imports;
 mod instruction;
+mod iter;
+mod machines;
+mod minimize;
+mod multitape;
+mod nondeterministic;
+mod options;
 mod output;
+mod parts;
+mod program;
+mod render;
+mod scan;
+mod serialization;
+mod session;
+mod snapshot;
+mod stats;
+mod tape;
+#[cfg(any(test, feature = "testing"))]
+mod testing;
+mod trace;
 mod turing;
+mod verdict;
+mod verification;
 mod warnings;
 
 use std::{borrow::Cow, collections::HashMap};
 
-pub use instruction::{Movement, TuringInstruction};
+pub use alphabet::{Alphabet, Symbol, SymbolInstruction, SymbolMachine, SymbolResult, SymbolStep};
+pub use analysis::MissingTransition;
+#[cfg(any(test, feature = "binary"))]
+pub use binary::{BinaryError, BINARY_VERSION};
+pub use breakpoints::Watchpoint;
+pub use builder::TuringMachineBuilder;
+pub use checkpoints::TimeTravelError;
+pub use comments::Comment;
+pub use compilation::CompilationResult;
+pub use cycles::ConfigKey;
+pub use delta::StepDelta;
+pub use diff::{InstructionChange, MachineDiff};
+pub use edit::RenameError;
+pub use equivalence::{Counterexample, EquivalenceReport};
+pub use execution::{ExecutionResult, StepResult};
+pub use explain::Language;
+#[cfg(any(test, feature = "fs"))]
+pub use imports::FileResolver;
+pub use imports::Resolver;
+pub use instruction::{
+    natural_cmp, InstructionKey, Movement, ResolvedInstruction, SourceSpan, TuringInstruction,
+};
+pub use iter::{Configuration, Steps};
+pub use multitape::{MultiTapeInstruction, MultiTapeMachine, MultiTapeResult, MultiTapeStep};
+pub use nondeterministic::Exploration;
+pub use options::{ParserOptions, WarningLevel, DEFAULT_MAX_TAPE_CELLS};
 pub use output::TuringOutput;
 use pest::Parser;
+pub use render::Compressed;
 use serde::{Deserialize, Serialize};
-pub use turing::{Rule, TuringMachine, TuringParser};
-pub use warnings::{CompilerError, CompilerWarning, ErrorPosition};
+pub use session::{DebugSession, DEFAULT_CHECKPOINT_INTERVAL};
+pub use snapshot::{MachineSnapshot, SnapshotError};
+pub use stats::Stats;
+pub use tape::{TapeError, TapeMode};
+#[cfg(any(test, feature = "testing"))]
+pub use testing::{generate_instruction, generate_machine, generate_tape};
+pub use trace::{replay, TraceEntry};
+pub use turing::{Rule, TuringMachine, TuringParser, DEFAULT_LOOP_THRESHOLD};
+pub use verdict::Verdict;
+pub use verification::{InputVerification, Outcome, VerificationReport};
+pub use warnings::{rule_name, CompilerError, CompilerWarning, ErrorPosition};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Library {
@@ -20,29 +96,68 @@ pub struct Library {
     pub final_state: Cow<'static, str>,
     pub used_states: Cow<'static, [Cow<'static, str>]>,
     pub code: Cow<'static, str>,
+    /// The alias given with `compose = {sum as s};`, which prefixes the states of the library (e.g. `s_q0`).
+    /// The other fields keep the states of its code, use `prefixed` to get the states of the composed machine
+    #[serde(default)]
+    pub alias: Option<Cow<'static, str>>,
 }
 
 impl Library {
     pub fn get_instructions(
         &self,
-    ) -> Result<HashMap<(String, bool), TuringInstruction>, CompilerError> {
-        let mut instructions: HashMap<(String, bool), TuringInstruction> = HashMap::new();
+    ) -> Result<HashMap<InstructionKey, TuringInstruction>, CompilerError> {
+        let mut instructions: HashMap<InstructionKey, TuringInstruction> = HashMap::new();
 
         let file = match TuringParser::parse(Rule::instructions, self.code.as_ref()) {
             Ok(mut f) => f.next().unwrap(),
             Err(e) => panic!("{}", e),
         };
 
-        for record in file.into_inner() {
-            let tmp = match TuringInstruction::from(record.into_inner()) {
-                Ok(i) => i,
-                Err(e) => return Err(e),
-            };
-            instructions.insert((tmp.from_state.clone(), tmp.from_value), tmp.clone());
+        for record in file
+            .into_inner()
+            .filter(|r| r.as_rule() == Rule::instruction)
+        {
+            let span = SourceSpan::from(&record.as_span());
+            let mut tmp = TuringInstruction::from(record.into_inner())?;
+            tmp.span = Some(span);
+            tmp.from_state = self.prefixed(&tmp.from_state);
+            tmp.to_state = self.prefixed(&tmp.to_state);
+            instructions.insert(tmp.key(), tmp);
         }
 
         Ok(instructions)
     }
+
+    /// Returns a copy of the library composed with an alias, like `compose = {sum as s};`
+    pub fn with_alias(&self, alias: &str) -> Self {
+        Self {
+            alias: Some(Cow::Owned(String::from(alias))),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the name of a state of the library in the composed machine, with the prefix of the alias if it has one
+    pub fn prefixed(&self, state: &str) -> String {
+        match &self.alias {
+            Some(alias) => format!("{}_{}", alias, state),
+            None => String::from(state),
+        }
+    }
+
+    /// Returns the library as it is written in `compose`, with its alias if it has one (e.g. `sum as s`)
+    pub fn composed_name(&self) -> String {
+        match &self.alias {
+            Some(alias) => format!("{} as {}", self.name, alias),
+            None => String::from(self.name.as_ref()),
+        }
+    }
+
+    /// Returns true if the library is one of `LIBRARIES`, and false if it is a function defined in the code
+    pub fn is_builtin(&self) -> bool {
+        LIBRARIES
+            .iter()
+            .any(|l| l.name == self.name && l.code == self.code)
+    }
 }
 
 /// Array of all the libraries that are included in the compiler.
@@ -74,6 +189,7 @@ pub const LIBRARIES: [Library; 5] = [
             Cow::Borrowed("q2"),
         ]),
         code: Cow::Borrowed(include_str!("./composition/sum.tm")),
+        alias: None,
     },
     Library {
         name: Cow::Borrowed("x2"),
@@ -90,6 +206,7 @@ pub const LIBRARIES: [Library; 5] = [
             Cow::Borrowed("qf"),
         ]),
         code: Cow::Borrowed(include_str!("./composition/duplicate.tm")),
+        alias: None,
     },
     Library {
         name: Cow::Borrowed("mod"),
@@ -113,6 +230,7 @@ pub const LIBRARIES: [Library; 5] = [
             Cow::Borrowed("qf"),
         ]),
         code: Cow::Borrowed(include_str!("./composition/mod.tm")),
+        alias: None,
     },
     Library {
         name: Cow::Borrowed("div2"),
@@ -126,6 +244,7 @@ pub const LIBRARIES: [Library; 5] = [
             Cow::Borrowed("qf"),
         ]),
         code: Cow::Borrowed(include_str!("./composition/div2.tm")),
+        alias: None,
     },
     Library {
         name: Cow::Borrowed("bound_diff"),
@@ -143,19 +262,38 @@ pub const LIBRARIES: [Library; 5] = [
             Cow::Borrowed("qf"),
         ]),
         code: Cow::Borrowed(include_str!("./composition/bound_diff.tm")),
+        alias: None,
     },
 ];
 
 #[cfg(test)]
 mod test_parsing {
-    use std::fs;
+    use std::{collections::HashSet, fs};
 
+    use crate::rule_name;
     use crate::warnings::ErrorPosition;
     use crate::CompilerError;
+    use crate::CompilerWarning;
+    use crate::ExecutionResult;
+    use crate::InstructionKey;
+    use crate::MissingTransition;
+    use crate::Movement;
+    use crate::MultiTapeMachine;
+    use crate::ParserOptions;
+    use crate::RenameError;
     use crate::Rule;
+    use crate::SourceSpan;
+    use crate::SymbolMachine;
+    use crate::TuringInstruction;
     use crate::TuringMachine;
+    use crate::TuringMachineBuilder;
+    use crate::TuringOutput;
     use crate::TuringParser;
-    use pest::{consumes_to, parses_to};
+    use crate::WarningLevel;
+    use crate::DEFAULT_MAX_TAPE_CELLS;
+    use crate::LIBRARIES;
+    use crate::{generate_instruction, generate_machine, generate_tape};
+    use pest::{consumes_to, parses_to, Parser};
 
     #[test]
     fn parse_description() {
@@ -254,6 +392,38 @@ mod test_parsing {
         }
     }
 
+    #[test]
+    fn parse_final_states() {
+        let test = "F = {q2, q5};";
+
+        parses_to! {
+            parser: TuringParser,
+            input: test,
+            rule: Rule::final_state,
+            tokens: [
+                final_state(0, 13, [
+                    state(5, 7),
+                    state(9, 11)
+                ])
+            ]
+        }
+    }
+
+    #[test]
+    /// Test that the states listed twice in the final states are kept once, with a warning
+    fn repeated_final_states() {
+        let (tm, warnings) =
+            TuringMachine::new("{1};\nI = {q0};\nF = {q2, q5, q2};\n(q0, 1, 1, H, q5);").unwrap();
+        assert_eq!(tm.final_states(), vec!["q2", "q5"]);
+        assert_eq!(
+            warnings,
+            vec![CompilerWarning::RepeatedFinalState {
+                position: ErrorPosition::new((2, 14), Some((2, 16))),
+                state: String::from("q2"),
+            }]
+        );
+    }
+
     #[test]
     fn parse_instruction() {
         let test = "(q0, 1, 0, R, q1);";
@@ -290,98 +460,4717 @@ mod test_parsing {
             "0 0 0 1 1 1 1 1 0 1 1 \n      ^               "
         )
     }
-}
 
-#[cfg(test)]
-mod test_composition {
-    use crate::Rule;
-    use crate::TuringMachine;
-    use crate::TuringOutput;
-    use crate::TuringParser;
-    use crate::LIBRARIES;
-    use pest::{consumes_to, parses_to};
+    #[test]
+    /// Test that the machine and its output can be used directly in `format!`
+    fn display() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        assert_eq!(
+            format!("{tm}"),
+            "0 0 0 1 1 1 1 1 0 1 1 \n      ^               "
+        );
+
+        let output = tm.final_result();
+        assert_eq!(format!("{output}"), "Defined(7, 6)");
+        assert_eq!(
+            format!("{}", crate::TuringOutput::Undefined(3)),
+            "Undefined"
+        );
+    }
 
     #[test]
-    fn parse_composition_function_name_valid() {
-        let test = "sum_test";
+    /// Test parsing a tape with the head marked on one of its cells
+    fn parse_tape_head() {
+        let test = "{10[1]};";
 
         parses_to! {
             parser: TuringParser,
             input: test,
-            rule: Rule::function_name,
+            rule: Rule::tape,
             tokens: [
-                function_name(0, 8)
+                tape(0, 8, [
+                    value(1, 2),
+                    value(2, 3),
+                    head(3, 6, [
+                        value(4, 5)
+                    ]),
+                ]),
             ]
         }
     }
 
     #[test]
-    fn parse_composition_valid() {
-        let test = "compose = { sum_test };";
+    /// Test that the head can only be marked once
+    fn parse_tape_two_heads() {
+        let test = "
+        {[1]0[1]};
+        I = {q0};
+        F = {q1};
+        ";
 
-        parses_to! {
-            parser: TuringParser,
-            input: test,
-            rule: Rule::composition,
-            tokens: [
-                composition(0, 23, [
-                    function_name(12, 20)
-                ])
-            ]
-        }
+        let error = TuringMachine::new(test).unwrap_err();
+
+        assert_eq!(
+            error.message(),
+            "Only one cell of the tape can be marked as the head"
+        );
     }
 
     #[test]
-    fn parse_multiple_compositions() {
-        let test = "compose = {sum, diff};";
+    /// Test parsing the tape mode directive
+    fn parse_tape_mode() {
+        let test = "tape = bounded;";
 
         parses_to! {
             parser: TuringParser,
             input: test,
-            rule: Rule::composition,
+            rule: Rule::tape_mode,
             tokens: [
-                composition(0, 22, [
-                    function_name(11, 14),
-                    function_name(16, 20)
-                ])
+                tape_mode(0, 15, [
+                    mode(7, 14)
+                ]),
             ]
         }
     }
 
     #[test]
-    /// Test that all the libraries are correctly parsed
-    /// (should not panic)
-    fn libraries() {
-        for lib in LIBRARIES {
-            let _ = lib.get_instructions();
+    /// Test that the tape, the initial state and the final states must be given exactly once
+    fn parse_repeated_definitions() {
+        let error = TuringMachine::new("{1}; {11}; I = {q0}; F = {q1};").unwrap_err();
+        assert_eq!(error.message(), "The tape can only be given once");
+
+        let error = TuringMachine::new("{1}; I = {q0}; F = {q1}; I = {q1};").unwrap_err();
+        assert_eq!(error.message(), "The initial state can only be given once");
+
+        let error = TuringMachine::new("I = {q0}; F = {q1};").unwrap_err();
+        assert_eq!(error.message(), "No tape given");
+    }
+
+    /// Builds an instruction for the tests
+    fn instruction(
+        from_state: &str,
+        from_value: bool,
+        to_value: bool,
+        movement: Movement,
+        to_state: &str,
+    ) -> TuringInstruction {
+        TuringInstruction {
+            from_state: String::from(from_state),
+            from_value,
+            to_value,
+            movement,
+            to_state: String::from(to_state),
+            span: None,
+            doc: None,
         }
     }
 
     #[test]
-    /// Test compiling a program that uses composition and nothing else (no extra code)
-    /// Also tests that you can write the `compose`, tape (`{111011}`), initial state (`I = {q0}`) and final state (`F = {q2}`) in any order
-    fn composition() {
-        let test = "
-        compose = {sum};
-        
-        F = {q2};
-        {111011};
+    /// Test building the `Example1` machine from its parts, which must behave like the parsed one
+    fn from_parts() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut parsed, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        let mut instructions: Vec<TuringInstruction> = LIBRARIES[0]
+            .get_instructions()
+            .unwrap()
+            .into_values()
+            .collect();
+        instructions.extend([
+            instruction("p0", true, false, Movement::RIGHT, "p1"),
+            instruction("p1", true, true, Movement::RIGHT, "p1"),
+            instruction("p1", false, false, Movement::RIGHT, "p2"),
+        ]);
+
+        let tape = [true, true, true, true, true, false, true, true];
+        let (mut built, warnings) =
+            TuringMachine::from_parts(instructions, "p0", &["p2"], &tape).unwrap();
+
+        // The instructions of `sum` are never executed, since `Example1` does not reach its states
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings
+            .iter()
+            .all(|w| matches!(w, CompilerWarning::DeadInstruction { .. })));
+        assert_eq!(built, parsed);
+        assert_eq!(built.final_result(), parsed.final_result());
+
+        // The generated code compiles to the same machine
+        let (reparsed, _) = TuringMachine::new(built.code()).unwrap();
+        built.reset();
+        assert_eq!(reparsed, built);
+    }
+
+    #[test]
+    /// Test that building a machine from its parts is checked like parsing its code
+    fn from_parts_checks() {
+        let instructions = vec![
+            instruction("q0", true, true, Movement::RIGHT, "q0"),
+            instruction("q0", true, false, Movement::RIGHT, "q1"),
+        ];
+
+        let (_, warnings) =
+            TuringMachine::from_parts(instructions.clone(), "q0", &["q1"], &[true]).unwrap();
+        assert_eq!(
+            warnings,
+            vec![CompilerWarning::StateOverwrite {
+                position: ErrorPosition::new((0, 0), None),
+                state: String::from("q0"),
+                value_from: true
+            }]
+        );
+
+        let error =
+            TuringMachine::from_parts(instructions.clone(), "q0", &["q1"], &[false]).unwrap_err();
+        assert_eq!(error.message(), "Expected at least a 1 in the tape");
+
+        let error = TuringMachine::from_parts(instructions, "q5", &["q1"], &[true]).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "The initial state q5 has no instructions and is not a final state"
+        );
+    }
+
+    #[test]
+    /// Test building the `Example1` machine with the builder, which must behave like the parsed one
+    fn builder() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut parsed, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        let (mut built, warnings) = TuringMachineBuilder::new()
+            .compose("sum")
+            .tape("11111011")
+            .initial("p0")
+            .finals(["p2"])
+            .instr("p0", 1, 0, Movement::RIGHT, "p1")
+            .instr("p1", 1, 1, Movement::RIGHT, "p1")
+            .instr("p1", 0, 0, Movement::RIGHT, "p2")
+            .build()
+            .unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(built, parsed);
+        assert_eq!(built.final_result(), parsed.final_result());
+
+        // The generated code compiles to the same machine
+        let (reparsed, _) = TuringMachine::new(built.code()).unwrap();
+        built.reset();
+        assert_eq!(reparsed, built);
+    }
+
+    #[test]
+    /// Test that the builder is checked like the parsed code
+    fn builder_checks() {
+        let builder = TuringMachineBuilder::new()
+            .tape("1")
+            .finals(["q1"])
+            .instr("q0", 1, 1, Movement::RIGHT, "q0")
+            .instr("q0", 1, 0, Movement::RIGHT, "q1");
+
+        let error = builder.clone().build().unwrap_err();
+        assert_eq!(error.message(), "No initial state given");
+
+        let (_, warnings) = builder.clone().initial("q0").build().unwrap();
+        assert_eq!(
+            warnings,
+            vec![CompilerWarning::StateOverwrite {
+                position: ErrorPosition::new((1, 0), None),
+                state: String::from("q0"),
+                value_from: true
+            }]
+        );
+
+        let error = builder
+            .clone()
+            .initial("q0")
+            .tape("12")
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Expected a 0 or a 1 in the tape, found '2'"
+        );
+
+        let error = builder
+            .clone()
+            .initial("q0")
+            .instr("q1", 2, 0, Movement::HALT, "q1")
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Expected the values of the instruction to be 0 or 1"
+        );
+
+        let error = builder.initial("q0").compose("nope").build().unwrap_err();
+        assert_eq!(error.message(), "Could not find the library \"nope\"");
+    }
+
+    #[test]
+    /// Test the checks of the code of multi-tape machines
+    fn parse_multi_tape() {
+        let error =
+            MultiTapeMachine::new("tapes = 2; {1}; I = {q0}; F = {q1}; (q0, [1], [1], [R], q1);")
+                .unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Expected a value and a movement for each of the 2 tapes"
+        );
+
+        let error = MultiTapeMachine::new("tapes = 1; {1}; {1}; I = {q0}; F = {q0};").unwrap_err();
+        assert_eq!(error.message(), "The machine only has 1 tapes");
+
+        let error = MultiTapeMachine::new("{1}; I = {q0}; F = {q0};").unwrap_err();
+        assert_eq!(error.message(), "No number of tapes given");
+
+        let error = MultiTapeMachine::new("tapes = 0; {1}; I = {q0}; F = {q0};").unwrap_err();
+        assert_eq!(error.message(), "A machine needs at least one tape");
+
+        // Single-tape instructions are not accepted
+        assert!(
+            MultiTapeMachine::new("tapes = 1; {1}; I = {q0}; F = {q1}; (q0, 1, 1, R, q1);")
+                .is_err()
+        );
+    }
+
+    #[test]
+    /// Test the checks of the code of machines over an alphabet
+    fn parse_alphabet() {
+        let error = SymbolMachine::new("alphabet = {B, 1}; {1X}; I = {q0}; F = {q0};").unwrap_err();
+        assert_eq!(error.message(), "The symbol X is not in the alphabet");
+
+        let error = SymbolMachine::new("alphabet = {B, X}; {X}; I = {q0}; F = {q0};").unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Expected a mark, since there is no 1 in the alphabet"
+        );
+
+        let error =
+            SymbolMachine::new("alphabet = {B, X, B}; {X}; I = {q0}; F = {q0};").unwrap_err();
+        assert_eq!(error.message(), "The symbol B can only be declared once");
+
+        let error = SymbolMachine::new("alphabet = {B, X}; mark = X; {BB}; I = {q0}; F = {q0};")
+            .unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Expected at least a non-blank symbol in the tape"
+        );
+
+        let error = SymbolMachine::new(
+            "alphabet = {B, X}; mark = X; compose = {sum}; {X}; I = {q0}; F = {q0};",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.message(),
+            "The library sum needs the symbols 0 and 1 in the alphabet"
+        );
+
+        let (tm, _) =
+            SymbolMachine::new("alphabet = {B, X}; mark = X; {BB[X]}; I = {q0}; F = {q0};")
+                .unwrap();
+        assert_eq!(tm.head_offset(), 0);
+        assert_eq!(tm.values(), vec![0]);
+    }
+
+    /// Scans right over the 1s, with a `q7` cluster that no instruction reaches
+    const ORPHANED_CLUSTER: &str = "
+        {111};
         I = {q0};
-        ";
+        F = {q2};
 
-        let mut tm = match TuringMachine::new(test) {
-            Ok(t) => t.0,
-            Err(e) => {
-                println!("{:?}", e);
-                std::process::exit(1);
-            }
+        (q0, 1, 1, R, q0);
+        (q0, 0, 0, R, q1);
+        (q1, 0, 0, H, q2);
+        (q7, 1, 0, R, q8);
+        (q8, 0, 1, L, q7);
+        (q8, 1, 1, R, q2);
+    ";
+
+    #[test]
+    /// Test finding the states that can be reached from the initial state, and the instructions that can not
+    fn reachable_states() {
+        let (tm, _) = TuringMachine::new(ORPHANED_CLUSTER).unwrap();
+
+        assert_eq!(
+            tm.reachable_states(),
+            HashSet::from(["q0", "q1", "q2"].map(String::from))
+        );
+
+        let unreachable: Vec<String> = tm
+            .unreachable_instructions()
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        assert_eq!(
+            unreachable,
+            vec![
+                "(q7, 1, 0, R, q8)",
+                "(q8, 0, 1, L, q7)",
+                "(q8, 1, 1, R, q2)"
+            ]
+        );
+
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+        // The composed `sum` library is never reached from `p0`
+        let unreachable: Vec<&str> = tm
+            .unreachable_instructions()
+            .iter()
+            .map(|i| i.from_state.as_str())
+            .collect();
+        assert_eq!(unreachable, vec!["q0", "q1", "q1", "q2"]);
+    }
+
+    #[test]
+    /// Test listing the transitions missing from an incomplete copy of `Example1`
+    fn missing_transitions() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let incomplete = unparsed_file.replace("(p1, 0, 0, R, p2);", "");
+        let (tm, _) = TuringMachine::new(&incomplete).unwrap();
+
+        let missing = |state: &str, value: bool, reachable: bool| MissingTransition {
+            state: String::from(state),
+            value,
+            reachable,
         };
 
-        assert_eq!(tm.final_result(), TuringOutput::Defined((5, 3)));
+        // The states of the composed `sum` library are never reached from `p0`
+        assert_eq!(
+            tm.missing_transitions(),
+            vec![
+                missing("p0", false, true),
+                missing("p1", false, true),
+                missing("q0", false, false),
+                missing("q2", false, false),
+            ]
+        );
+
+        let (tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+        assert_eq!(tm.missing_transitions().len(), 3);
+    }
+
+    #[test]
+    /// Test the states and transitions of `Example1`, which must always be listed in the same order
+    fn introspection() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        assert_eq!(tm.states(), vec!["p0", "p1", "p2", "q0", "q1", "q2"]);
+        assert_eq!(tm.initial_state(), "p0");
+        assert_eq!(tm.final_states(), &[String::from("p2")]);
+        assert!(tm.is_final("p2"));
+        assert!(!tm.is_final("q2"));
+        assert_eq!(tm.alphabet(), vec![false, true]);
+
+        let transitions: Vec<String> = tm.transitions().iter().map(|i| i.to_string()).collect();
+        assert_eq!(transitions.len(), tm.instructions().len());
+        assert_eq!(transitions[0], "(p0, 1, 0, R, p1)");
+        assert_eq!(transitions[1], "(p1, 0, 0, R, p2)");
+        assert_eq!(transitions[2], "(p1, 1, 1, R, p1)");
+
+        let (again, _) = TuringMachine::new(&unparsed_file).unwrap();
+        assert_eq!(again.transitions(), tm.transitions());
+
+        // The states numbered from 10 come after the others, like in the transitions
+        let mut renamed = tm.clone();
+        renamed.rename_state("p1", "p10").unwrap();
+        assert_eq!(renamed.states(), vec!["p0", "p2", "p10", "q0", "q1", "q2"]);
+        let mut from_states: Vec<&str> = renamed
+            .transitions()
+            .iter()
+            .map(|i| i.from_state.as_str())
+            .collect();
+        from_states.dedup();
+        assert_eq!(from_states, vec!["p0", "p10", "q0", "q1", "q2"]);
+    }
+
+    #[test]
+    /// Test renaming the states of `Example1`, which must compute the same result
+    fn rename_state() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut original, _) = TuringMachine::new(&unparsed_file).unwrap();
+        let expected = original.final_result();
+        let (mut tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        tm.step_n(2);
+        tm.rename_state("p1", "scan").unwrap();
+        assert_eq!(tm.current_state(), "scan");
+        assert!(tm.state_frequencies().any(|(s, _, _)| s == "scan"));
+        assert!(tm.code().contains("(scan, 1, 1, R, scan);"));
+        // The library is still composed, since its instructions were not renamed
+        assert!(tm.code().contains("compose = {sum};"));
+
+        tm.rename_state("p2", "done").unwrap();
+        assert!(tm.is_final("done"));
+
+        // A state of the library is written out with the library
+        tm.rename_state("q1", "add").unwrap();
+        assert!(!tm.code().contains("compose"));
+        assert!(tm.code().contains("(add, 0, 0, R, q2);"));
+        assert!(tm.composed_libs().is_empty());
+
+        tm.reset();
+        // The instructions of `sum` written out are never executed
+        let (mut reparsed, warnings) = TuringMachine::new(tm.code()).unwrap();
+        assert!(warnings
+            .iter()
+            .all(|w| matches!(w, CompilerWarning::DeadInstruction { .. })));
+        assert_eq!(reparsed, tm);
+
+        assert_eq!(tm.final_result(), expected);
+        assert_eq!(reparsed.final_result(), expected);
 
         assert_eq!(
-            tm.to_string(),
-            "0 0 0 0 1 1 0 0 1 0 0 \n              ^       "
+            tm.rename_state("p5", "q9"),
+            Err(RenameError::UnknownState(String::from("p5")))
+        );
+        assert_eq!(
+            tm.rename_state("p0", "q0"),
+            Err(RenameError::ExistingState(String::from("q0")))
+        );
+        assert_eq!(
+            tm.rename_state("p0", "scan-right"),
+            Err(RenameError::InvalidName(String::from("scan-right")))
+        );
+        assert_eq!(
+            tm.rename_state("p0", "1q"),
+            Err(RenameError::InvalidName(String::from("1q")))
+        );
+    }
+
+    /// The code of the machine used to test editing instructions, which moves right to the end of its input
+    const EDITED: &str = "{111};\nI = {q0};\nF = {q1};\n(q0, 1, 1, R, q0);\n(q0, 0, 0, L, q1);\n";
+
+    #[test]
+    /// Test adding instructions to a machine, which must overwrite the existing ones and keep the code in sync
+    fn add_instruction() {
+        let (mut tm, _) = TuringMachine::new(EDITED).unwrap();
+
+        assert_eq!(
+            tm.add_instruction(instruction("q0", false, true, Movement::RIGHT, "q2")),
+            Some(CompilerWarning::StateOverwrite {
+                position: ErrorPosition::new((0, 0), None),
+                state: String::from("q0"),
+                value_from: false
+            })
+        );
+        assert_eq!(
+            tm.add_instruction(instruction("q2", false, false, Movement::LEFT, "q1")),
+            None
+        );
+        assert!(tm.code().contains("(q0, 0, 1, R, q2);"));
+        assert!(tm.code().contains("(q2, 0, 0, L, q1);"));
+        assert!(!tm.code().contains("(q0, 0, 0, L, q1);"));
+
+        let (mut reparsed, warnings) = TuringMachine::new(tm.code()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(reparsed.final_result(), tm.final_result());
+        assert!(matches!(tm.final_result(), TuringOutput::Defined((_, 4))));
+    }
+
+    #[test]
+    /// Test removing instructions from a machine, which must get stuck instead of panicking when it needs them
+    fn remove_instruction() {
+        let (mut tm, _) = TuringMachine::new(EDITED).unwrap();
+
+        tm.step_n(1);
+        assert_eq!(
+            tm.remove_instruction("q0", true),
+            Some(instruction("q0", true, true, Movement::RIGHT, "q0"))
+        );
+        assert_eq!(tm.remove_instruction("q0", true), None);
+        assert_eq!(tm.remove_instruction("q5", false), None);
+        assert!(!tm.code().contains("(q0, 1"));
+        assert!(tm.code().contains("(q0, 0, 0, L, q1);"));
+
+        assert_eq!(
+            tm.final_result(),
+            TuringOutput::Stuck {
+                steps: 1,
+                state: String::from("q0"),
+                value: true
+            }
         );
+        assert!(TuringMachine::new(tm.code()).is_ok());
+    }
+
+    #[test]
+    /// Test replacing the instructions of a machine, which must only change existing ones
+    fn replace_instruction() {
+        let (mut tm, _) = TuringMachine::new(EDITED).unwrap();
+        let code = String::from(tm.code());
+
+        assert_eq!(
+            tm.replace_instruction(instruction("q1", true, true, Movement::LEFT, "q0")),
+            None
+        );
+        assert_eq!(tm.code(), code);
+
+        assert_eq!(
+            tm.replace_instruction(instruction("q0", false, true, Movement::LEFT, "q1")),
+            Some(instruction("q0", false, false, Movement::LEFT, "q1"))
+        );
+        assert!(tm.code().contains("(q0, 0, 1, L, q1);"));
+        assert!(matches!(tm.final_result(), TuringOutput::Defined((_, 4))));
+    }
+
+    #[test]
+    /// Test that the instructions of states that cannot be reached are warned about, pointing at their line,
+    /// except the ones only reached through a composed library
+    fn dead_instructions() {
+        let unparsed_file =
+            fs::read_to_string("Examples/DeadInstructions.tm").expect("cannot read file");
+        let (mut tm, warnings) = TuringMachine::new(&unparsed_file).unwrap();
+
+        let dead: Vec<(&str, bool, usize)> = warnings
+            .iter()
+            .map(|w| match w {
+                CompilerWarning::DeadInstruction {
+                    position,
+                    state,
+                    value,
+                } => (state.as_str(), *value, position.start.0),
+                _ => panic!("Unexpected warning {:?}", w),
+            })
+            .collect();
+        assert_eq!(
+            dead,
+            vec![("r0", true, 12), ("r1", true, 13), ("r2", false, 14)]
+        );
+
+        assert_eq!(tm.final_result(), TuringOutput::Defined((9, 5)));
+    }
+
+    #[test]
+    /// Test that the parsed instructions remember where they were written, and that the span is not part of the JSON when missing
+    fn instruction_spans() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        let third = &tm.instructions()[&InstructionKey::new("p1", false)];
+        let span = third.span.unwrap();
+        assert_eq!(
+            span,
+            SourceSpan {
+                start: (12, 1),
+                end: (12, 19),
+                start_byte: 104,
+                end_byte: 122,
+            }
+        );
+        assert_eq!(
+            &unparsed_file[span.start_byte..span.end_byte],
+            "(p1, 0, 0, R, p2);"
+        );
+
+        // The spans of the composed instructions point at the code of their library
+        let library = &tm.instructions()[&InstructionKey::new("q0", true)];
+        assert!(library.span.is_some());
+        assert_eq!(
+            library,
+            &instruction("q0", true, false, Movement::RIGHT, "q1")
+        );
+
+        let halt = TuringInstruction::halt((String::from("p2"), false));
+        assert_eq!(halt.span, None);
+        assert!(!serde_json::to_string(&halt).unwrap().contains("span"));
+        assert!(serde_json::to_string(third).unwrap().contains("\"span\""));
+    }
+
+    #[test]
+    /// Test patching an instruction in the middle of a run, which must continue with the new movement
+    fn patch_instruction_source() {
+        let code = "{1111};\nI = {q0};\nF = {qf};\n\n(q0, 1, 1, R, q0);\n(q0, 0, 0, H, qf);\n";
+        let (mut tm, _) = TuringMachine::new(code).unwrap();
+
+        tm.advance();
+        tm.advance();
+        assert_eq!(tm.head_offset(), 2);
+
+        // A syntax error leaves the machine untouched
+        let error = tm.patch_instruction_source(4..5, "(q0, 1, 1, X, q0);");
+        match error {
+            Err(CompilerError::SyntaxError { position, .. }) => assert_eq!(position.start, (4, 12)),
+            other => panic!("Expected a syntax error, got {:?}", other),
+        }
+        assert!(tm
+            .patch_instruction_source(4..5, "(q0, 1, 1, L, q0); (q1, 1, 1, L, q1);")
+            .is_err());
+        assert!(tm
+            .patch_instruction_source(4..9, "(q0, 1, 1, L, q0);")
+            .is_err());
+        assert_eq!(tm.code(), code);
+
+        let warnings = tm
+            .patch_instruction_source(4..5, "(q0, 1, 1, L, q0);")
+            .unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(
+            tm.code(),
+            "{1111};\nI = {q0};\nF = {qf};\n\n(q0, 1, 1, L, q0);\n(q0, 0, 0, H, qf);\n"
+        );
+        assert_eq!(tm.steps, 2);
+        assert_eq!(tm.head_offset(), 2);
+        assert_eq!(
+            tm.next_instruction().unwrap().instruction(),
+            &instruction("q0", true, true, Movement::LEFT, "q0")
+        );
+
+        // The execution continues from the same cell, now moving left
+        tm.advance();
+        assert_eq!(tm.head_offset(), 1);
+
+        // Adding a line keeps the spans of the instructions after it in sync with the code
+        let warnings = tm
+            .patch_instruction_source(4..5, "\n(q0, 1, 1, L, q0);")
+            .unwrap();
+        assert!(warnings.is_empty());
+        let last = &tm.instructions()[&InstructionKey::new("q0", false)];
+        let span = last.span.unwrap();
+        assert_eq!(span.start, (6, 1));
+        assert_eq!(
+            &tm.code()[span.start_byte..span.end_byte],
+            "(q0, 0, 0, H, qf);"
+        );
+
+        // Writing an instruction over another line warns about the one it overwrites
+        let warnings = tm
+            .patch_instruction_source(3..4, "(q0, 1, 1, R, q0);")
+            .unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(
+            &warnings[0],
+            CompilerWarning::StateOverwrite { state, value_from: true, .. } if state == "q0"
+        ));
+    }
+
+    #[test]
+    /// Test that patching the code rejects the lines without instructions and gives the errors in the patched code
+    fn patch_instruction_source_errors() {
+        let code =
+            "{11};\nI = {q0};\nF = {qf};\n\n(q0, 1, 1, R, q0);\n// Halt\n(q0, 0, 0, H, qf);\n";
+        let (mut tm, _) = TuringMachine::new(code).unwrap();
+        let start = |result: Result<Vec<CompilerWarning>, CompilerError>| match result {
+            Err(CompilerError::SyntaxError { position, .. }) => position.start,
+            other => panic!("Expected a syntax error, got {:?}", other),
+        };
+
+        // The tape and the states cannot be replaced
+        assert_eq!(
+            start(tm.patch_instruction_source(0..1, "(q0, 1, 0, R, q0);")),
+            (0, 0)
+        );
+        assert_eq!(
+            start(tm.patch_instruction_source(2..5, "(q0, 1, 0, R, q0);")),
+            (2, 0)
+        );
+        assert_eq!(tm.code(), code);
+
+        // The errors of the instruction are on the patched line
+        assert_eq!(
+            start(tm.patch_instruction_source(4..5, "(q0, 2, 0, H, q1);")),
+            (4, 6)
+        );
+        assert_eq!(
+            start(tm.patch_instruction_source(4..5, "(q0, *, 0, H, q1);")),
+            (4, 6)
+        );
+        assert_eq!(
+            start(tm.patch_instruction_source(4..5, "  (q0, 1, 1, X, q0);")),
+            (4, 14)
+        );
+        assert_eq!(tm.code(), code);
+
+        // The comments, docs and empty lines can be replaced along with the instructions
+        tm.patch_instruction_source(4..7, "(q0, 1, 1, L, q0);")
+            .unwrap();
+        assert_eq!(
+            tm.code(),
+            "{11};\nI = {q0};\nF = {qf};\n\n(q0, 1, 1, L, q0);\n"
+        );
+        assert!(TuringMachine::new(tm.code()).is_ok());
+        tm.patch_instruction_source(3..4, "(q0, 0, 0, H, qf);")
+            .unwrap();
+        assert!(TuringMachine::new(tm.code()).is_ok());
+    }
+
+    #[test]
+    /// Test compiling a machine strictly, which must fail on a file with a duplicate instruction
+    fn warnings_as_errors() {
+        let unparsed_file =
+            fs::read_to_string("Examples/DuplicateInstruction.tm").expect("cannot read file");
+        let result = TuringMachine::compile(&unparsed_file).unwrap();
+        assert!(!result.is_clean());
+        assert!(result.composed.is_empty());
+
+        let warnings = result.clone().warnings_as_errors().unwrap_err();
+        assert_eq!(
+            warnings,
+            vec![CompilerWarning::StateOverwrite {
+                position: ErrorPosition::new((11, 1), Some((11, 19))),
+                state: String::from("p1"),
+                value_from: true,
+            }]
+        );
+
+        // The machine is still there for the callers that do not mind the warnings
+        let mut tm = result.into_machine();
+        assert_eq!(tm.final_result(), TuringOutput::Defined((7, 6)));
+
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let result = TuringMachine::compile(&unparsed_file).unwrap();
+        assert_eq!(result.composed, vec!["sum"]);
+        assert!(result.is_clean());
+
+        let (tm, warnings) = TuringMachine::new(&unparsed_file).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(tm, result.warnings_as_errors().unwrap());
+    }
+
+    #[test]
+    /// Test each of the options of the compiler, whose defaults must compile like `new`
+    fn parser_options() {
+        let defaults = ParserOptions::default();
+        let unparsed_file =
+            fs::read_to_string("Examples/DuplicateInstruction.tm").expect("cannot read file");
+        assert_eq!(
+            TuringMachine::new_with_options(&unparsed_file, &defaults).unwrap(),
+            TuringMachine::new(&unparsed_file).unwrap()
+        );
+
+        // Duplicate instructions can be ignored or rejected
+        let allow = ParserOptions {
+            duplicate_instruction: WarningLevel::Allow,
+            ..defaults.clone()
+        };
+        let (_, warnings) = TuringMachine::new_with_options(&unparsed_file, &allow).unwrap();
+        assert!(warnings.is_empty());
+
+        let deny = ParserOptions {
+            duplicate_instruction: WarningLevel::Deny,
+            ..defaults.clone()
+        };
+        match TuringMachine::new_with_options(&unparsed_file, &deny) {
+            Err(CompilerError::SyntaxError { position, .. }) => {
+                assert_eq!(position, ErrorPosition::new((11, 1), Some((11, 19))))
+            }
+            other => panic!("Expected a syntax error, got {:?}", other),
+        }
+
+        // A tape without 1s
+        let empty = "{000}; I = {q0}; F = {qf}; (q0, 0, 1, H, qf);";
+        assert!(TuringMachine::new(empty).is_err());
+        let allow_empty = ParserOptions {
+            allow_empty_tape: true,
+            ..defaults.clone()
+        };
+        let (mut tm, _) = TuringMachine::new_with_options(empty, &allow_empty).unwrap();
+        assert_eq!(tm.final_result(), TuringOutput::Defined((2, 1)));
+        let (mut tm, _) =
+            TuringMachine::new_with_options("{}; I = {qf}; F = {qf};", &allow_empty).unwrap();
+        assert_eq!(tm.final_result(), TuringOutput::Defined((1, 0)));
+
+        // The 0s at the start of the tape
+        let zeros = "{0011}; I = {q0}; F = {qf}; (q0, 1, 1, H, qf); (q0, 0, 0, H, qf);";
+        let (tm, _) = TuringMachine::new(zeros).unwrap();
+        assert!(tm.next_instruction().unwrap().instruction().from_value);
+        let preserve = ParserOptions {
+            preserve_leading_zeros: true,
+            ..defaults.clone()
+        };
+        let (tm, _) = TuringMachine::new_with_options(zeros, &preserve).unwrap();
+        assert!(!tm.next_instruction().unwrap().instruction().from_value);
+        assert_eq!(tm.head_offset(), 0);
+
+        // The states without instructions that are not final
+        let stuck = "{1}; I = {q0}; F = {qf}; (q0, 1, 1, R, q1); (q0, 0, 0, H, qf);";
+        assert!(TuringMachine::new(stuck).is_ok());
+        let strict = ParserOptions {
+            strict_states: true,
+            ..defaults
+        };
+        let error = TuringMachine::new_with_options(stuck, &strict).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "The state q1 has no instructions and is not a final state"
+        );
+        assert!(TuringMachine::new_with_options(&unparsed_file, &strict).is_ok());
+    }
+
+    #[test]
+    /// Test that the generated programs always compile and run without panicking, and that they only depend on the seed
+    fn generated_machines() {
+        for seed in 0..200 {
+            for n_states in 1..6 {
+                let code = generate_machine(seed, n_states);
+                assert_eq!(code, generate_machine(seed, n_states));
+
+                let (mut tm, _) = TuringMachine::new(&code)
+                    .unwrap_or_else(|e| panic!("{} did not compile: {:?}", code, e));
+                assert_eq!(tm.instructions().len(), 2 * n_states);
+                tm.run(100);
+            }
+
+            let tape = generate_tape(seed, 10);
+            assert_eq!(tape.len(), 13);
+            assert!(tape.contains('1'));
+
+            let instruction = generate_instruction(seed, 3);
+            let written = format!("{};", instruction);
+            let parsed = TuringParser::parse(Rule::instruction, &written)
+                .unwrap()
+                .next()
+                .unwrap();
+            assert_eq!(
+                TuringInstruction::from(parsed.into_inner()).unwrap(),
+                instruction
+            );
+        }
+    }
+
+    #[test]
+    /// Test the differences between `Example1` and a copy with a movement flipped and an instruction deleted
+    fn diff() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (original, _) = TuringMachine::new(&unparsed_file).unwrap();
+        assert!(original.diff(&original.clone()).is_empty());
+
+        let edited = unparsed_file
+            .replace("(p1, 1, 1, R, p1);", "(p1, 1, 1, L, p1);")
+            .replace("(p1, 0, 0, R, p2);", "");
+        let (edited, _) = TuringMachine::new(&edited).unwrap();
+
+        let diff = original.diff(&edited);
+        assert!(diff.added.is_empty());
+        assert_eq!(
+            diff.removed,
+            vec![instruction("p1", false, false, Movement::RIGHT, "p2")]
+        );
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].after.movement, Movement::LEFT);
+        assert_eq!(diff.initial_state, None);
+        assert_eq!(diff.final_states, None);
+        assert_eq!(diff.tape, None);
+        assert_eq!(
+            diff.to_string(),
+            "- (p1, 0, 0, R, p2);\n- (p1, 1, 1, R, p1);\n+ (p1, 1, 1, L, p1);\n"
+        );
+
+        // The other way around, the instruction is added
+        let diff = edited.diff(&original);
+        assert_eq!(diff.added.len(), 1);
+        assert!(diff.removed.is_empty());
+
+        let (other, _) = TuringMachine::new(
+            &unparsed_file
+                .replace("{11111011}", "{1101}")
+                .replace("F = {p2};", "F = {p2, p1};"),
+        )
+        .unwrap();
+        let diff = original.diff(&other);
+        assert_eq!(
+            diff.to_string(),
+            "- F = {p2};\n+ F = {p1, p2};\n- {11111011};\n+ {1101};\n"
+        );
+
+        let json = serde_json::to_string(&diff).unwrap();
+        assert_eq!(
+            serde_json::from_str::<crate::MachineDiff>(&json).unwrap(),
+            diff
+        );
+    }
+
+    #[test]
+    /// Test that compiling the code written by `to_code` gives back the same machine, for every example and library
+    fn to_code_round_trip() {
+        let mut codes: Vec<String> = fs::read_dir("Examples")
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+
+        for library in LIBRARIES.iter() {
+            let definitions = format!(
+                "{{1}}; I = {{{}}}; F = {{{}}};",
+                library.initial_state, library.final_state
+            );
+            codes.push(format!("compose = {{{}}}; {}", library.name, definitions));
+            codes.push(format!("{}\n{}", definitions, library.code));
+        }
+
+        codes.push(String::from(
+            "/// Bounded\ntape = bounded;\n{00[0]110};\nI = {q0};\nF = {q1};\nA = {q1};\n(q0, 0, 0, R, q0);\n(q0, 1, 1, H, q1);",
+        ));
+
+        for code in codes {
+            let (tm, _) = TuringMachine::new(&code).unwrap();
+            let written = tm.to_code();
+            let (compiled, _) = TuringMachine::new(&written)
+                .unwrap_or_else(|e| panic!("{} did not compile: {:?}", written, e));
+
+            assert_eq!(compiled, tm, "{}", written);
+            assert_eq!(compiled.description(), tm.description());
+            assert_eq!(compiled.composed_libs().len(), tm.composed_libs().len());
+            assert_eq!(compiled.accepting_states(), tm.accepting_states());
+            assert_eq!(compiled.tape_mode, tm.tape_mode);
+            assert_eq!(compiled.to_code(), written);
+        }
+
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+        assert_eq!(
+            tm.to_code(),
+            "/// a + b\n{11111011};\nI = {p0};\nF = {p2};\ncompose = {sum};\n\n(p0, 1, 0, R, p1);\n(p1, 0, 0, R, p2);\n(p1, 1, 1, R, p1);\n"
+        );
+    }
+
+    #[test]
+    /// Test that the messages of the errors name the rules of the grammar with phrases
+    fn rule_names_in_messages() {
+        let error = TuringMachine::new("I = {q0}; F = {q1}; (q0, 1, 0, R, q1);").unwrap_err();
+        assert_eq!(
+            error.get_message_expected(),
+            "Expected the tape section, found nothing"
+        );
+
+        let error = TuringMachine::new("{1}; I = {q0}; F = {q1}; (q0, 1, 0, R);").unwrap_err();
+        assert_eq!(
+            error.get_message_expected(),
+            "expected the end of the code, a doc comment like /// carry the borrow left, the tape section, a final states declaration, an initial state declaration, \
+            an accepting states declaration, a rejecting states declaration, a function like fn increment { ... }, a composition like compose = {sum};, \
+            a name declaration like name = \"adder\";, an import like import \"sum.tm\";, a blank declaration like blank = 0;, a tape mode declaration like tape = bounded;, an instruction like (q0, 1, 0, R, q1), \
+            or an alphabet declaration like alphabet = {a, b};"
+        );
+        assert_eq!(error.message(), error.get_message_expected());
+
+        // The rules that are not shown keep their names
+        assert_eq!(rule_name(Rule::file), "file");
+    }
+
+    #[test]
+    /// Test that the movements are serialized with their letters, and deserialized from the letters and the old names
+    fn movement_serialization() {
+        for movement in [
+            Movement::RIGHT,
+            Movement::LEFT,
+            Movement::HALT,
+            Movement::STAY,
+        ] {
+            let json = serde_json::to_string(&movement).unwrap();
+            assert_eq!(json, format!("\"{}\"", movement));
+            assert_eq!(serde_json::from_str::<Movement>(&json).unwrap(), movement);
+        }
+
+        for (names, movement) in [
+            (["\"D\"", "\"RIGHT\""], Movement::RIGHT),
+            (["\"I\"", "\"LEFT\""], Movement::LEFT),
+            (["\"N\"", "\"HALT\""], Movement::HALT),
+            (["\"Q\"", "\"STAY\""], Movement::STAY),
+        ] {
+            for name in names {
+                assert_eq!(serde_json::from_str::<Movement>(name).unwrap(), movement);
+            }
+        }
+        assert!(serde_json::from_str::<Movement>("\"X\"").is_err());
+
+        let old = r#"{"from_state":"q0","from_value":true,"to_value":false,"movement":"RIGHT","to_state":"q1"}"#;
+        let instruction: TuringInstruction = serde_json::from_str(old).unwrap();
+        assert_eq!(instruction.movement, Movement::RIGHT);
+        assert!(serde_json::to_string(&instruction)
+            .unwrap()
+            .contains("\"movement\":\"R\""));
+    }
+
+    #[test]
+    /// Test that the `///` comments before an instruction document it, without changing the description
+    fn instruction_docs() {
+        let code = "/// Moves to the end\n{111};\nI = {q0};\nF = {q1};\n\n/// carry the borrow right\n(q0, 1, 1, R, q0);\n// a comment\n(q1, 1, 1, H, q1);\n/// stop at the end\n///   of the tape\n(q0, 0, 0, H, q1);\n";
+        let (mut tm, _) = TuringMachine::new(code).unwrap();
+
+        assert_eq!(tm.description(), Some("Moves to the end"));
+        let doc = |tm: &TuringMachine, state: &str, value: bool| {
+            tm.instructions()[&InstructionKey::new(state, value)]
+                .doc
+                .clone()
+        };
+        assert_eq!(
+            doc(&tm, "q0", true).as_deref(),
+            Some("carry the borrow right")
+        );
+        assert_eq!(
+            doc(&tm, "q0", false).as_deref(),
+            Some("stop at the end\nof the tape")
+        );
+        assert_eq!(doc(&tm, "q1", true), None);
+
+        #[allow(deprecated)]
+        let current = tm.get_current_instruction().unwrap();
+        assert_eq!(current.doc.as_deref(), Some("carry the borrow right"));
+
+        tm.enable_trace();
+        tm.advance();
+        assert_eq!(
+            tm.trace()[0].instruction.doc.as_deref(),
+            Some("carry the borrow right")
+        );
+
+        // The docs are written back, and left out of the JSON when there are none
+        let (written, _) = TuringMachine::new(&tm.to_code()).unwrap();
+        assert_eq!(doc(&written, "q0", false), doc(&tm, "q0", false));
+        let json = serde_json::to_string(&tm.instructions()[&InstructionKey::new("q1", true)]);
+        assert!(!json.unwrap().contains("doc"));
+
+        let (tm, _) =
+            TuringMachine::new("/// a + b\n{1};\n/// nothing\nI = {q0};\nF = {q0};\n////\n")
+                .unwrap();
+        assert_eq!(tm.description(), Some("a + b"));
+        assert!(tm.instructions().values().all(|i| i.doc.is_none()));
+    }
+
+    #[test]
+    /// Test that `_` and `B` are read as blanks, which are 0s on the tape
+    fn parse_blanks() {
+        let code = "{1_1};\nI = {q0};\nF = {q1};\n(q0, 1, 1, R, q0);\n(q0, _, 1, R, q1);\n(q1, B, B, H, q1);";
+        let (tm, _) = TuringMachine::new(code).unwrap();
+
+        let (zeros, _) = TuringMachine::new(&code.replace(['_', 'B'], "0")).unwrap();
+        assert_eq!(tm, zeros);
+        assert_eq!(
+            tm.instructions()[&InstructionKey::new("q0", false)],
+            instruction("q0", false, true, Movement::RIGHT, "q1")
+        );
+        assert_eq!(
+            tm.instructions()[&InstructionKey::new("q1", false)],
+            instruction("q1", false, false, Movement::HALT, "q1")
+        );
+
+        let parsed = TuringParser::parse(Rule::tape, "{B[_]1};");
+        assert!(parsed.is_ok());
+
+        assert_eq!(tm.to_string(), zeros.to_string());
+        assert_eq!(format!("{:#}", tm), tm.to_string().replace('0', "_"));
+        assert!(format!("{:#}", tm).contains("1 _ 1"));
+    }
+
+    #[test]
+    /// Test the symbols of the code: any symbol is parsed, and it must be in the alphabet if one is declared
+    fn parse_symbols() {
+        // A binary machine may declare its alphabet
+        let code = "alphabet = {_, 0, 1};\n{101};\nI = {q0};\nF = {q1};\n(q0, 1, 0, R, q0);\n(q0, _, 1, H, q1);";
+        let (tm, _) = TuringMachine::new(code).unwrap();
+        assert_eq!(
+            tm.instructions()[&InstructionKey::new("q0", false)],
+            instruction("q0", false, true, Movement::HALT, "q1")
+        );
+
+        // The symbols that are not declared are rejected at the instruction that uses them
+        let code = "alphabet = {_, 0, 1};\n{101};\nI = {q0};\nF = {q1};\n(q0, 1, 0, R, q0);\n(q0, 0, X, H, q1);";
+        let error = TuringMachine::new(code).unwrap_err();
+        assert_eq!(error.message(), "The symbol X is not in the alphabet");
+        assert_eq!(error.line(), 5);
+        assert_eq!(error.code(), "(q0, 0, X, H, q1);");
+        assert_eq!(error.expected(), Rule::alphabet);
+
+        let error =
+            TuringMachine::new("alphabet = {0, 1};\n{1\"ab\"};\nI = {q0};\nF = {q1};").unwrap_err();
+        assert_eq!(error.message(), "The symbol ab is not in the alphabet");
+        assert_eq!(error.line(), 1);
+
+        // A declared symbol that is not binary needs a SymbolMachine
+        let code = "alphabet = {0, 1, X};\n{101};\nI = {q0};\nF = {q1};\n(q0, 1, X, H, q1);";
+        let error = TuringMachine::new(code).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Expected 0, 1 or a blank, found the symbol X, which needs a SymbolMachine"
+        );
+        assert_eq!(error.line(), 4);
+        assert_eq!(error.expected(), Rule::value);
+
+        let error = TuringMachine::new("{1X};\nI = {q0};\nF = {q1};").unwrap_err();
+        assert_eq!(error.expected(), Rule::value);
+
+        // The files without an alphabet keep working
+        for entry in fs::read_dir("Examples").unwrap() {
+            let code = fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert!(TuringMachine::new(&code).is_ok());
+        }
+
+        // Quoted symbols are declared and used without their quotes
+        let (tm, _) = SymbolMachine::new(
+            "alphabet = {B, 1, \"ab\"};\n{1\"ab\"};\nI = {q0};\nF = {q1};\n(q0, 1, \"ab\", R, q0);\n(q0, \"ab\", 1, H, q1);",
+        )
+        .unwrap();
+        assert!(tm.alphabet.symbol("ab").is_some());
+    }
+
+    #[test]
+    /// Test the wildcard `*`, which reads every value or writes back the value read
+    fn parse_wildcards() {
+        let code = "{101};\nI = {q0};\nF = {q1};\n(q0, *, *, L, q1);\n(q1, 1, *, R, q1);\n";
+        let (tm, warnings) = TuringMachine::new(code).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(tm.instructions().len(), 3);
+        assert_eq!(
+            tm.instructions()[&InstructionKey::new("q0", false)],
+            instruction("q0", false, false, Movement::LEFT, "q1")
+        );
+        assert_eq!(
+            tm.instructions()[&InstructionKey::new("q0", true)],
+            instruction("q0", true, true, Movement::LEFT, "q1")
+        );
+        assert_eq!(
+            tm.instructions()[&InstructionKey::new("q1", true)],
+            instruction("q1", true, true, Movement::RIGHT, "q1")
+        );
+
+        let code = "{101};\nI = {q0};\nF = {q1};\n(q0, *, 0, R, q1);\n";
+        let (tm, _) = TuringMachine::new(code).unwrap();
+        assert_eq!(
+            tm.instructions()[&InstructionKey::new("q0", true)],
+            instruction("q0", true, false, Movement::RIGHT, "q1")
+        );
+
+        // The instruction given with a value wins over the wildcard, before or after it, without warnings
+        for code in [
+            "{101};\nI = {q0};\nF = {q1};\n(q0, 1, 0, R, q0);\n(q0, *, *, H, q1);\n",
+            "{101};\nI = {q0};\nF = {q1};\n(q0, *, *, H, q1);\n(q0, 1, 0, R, q0);\n",
+        ] {
+            let (tm, warnings) = TuringMachine::new(code).unwrap();
+            assert!(warnings.is_empty());
+            assert_eq!(
+                tm.instructions()[&InstructionKey::new("q0", true)],
+                instruction("q0", true, false, Movement::RIGHT, "q0")
+            );
+            assert_eq!(
+                tm.instructions()[&InstructionKey::new("q0", false)],
+                instruction("q0", false, false, Movement::HALT, "q1")
+            );
+        }
+
+        // Two wildcards of the same state overwrite each other like any other instructions
+        let code = "{101};\nI = {q0};\nF = {q1};\n(q0, *, *, H, q1);\n(q0, *, 1, H, q1);\n";
+        let (tm, warnings) = TuringMachine::new(code).unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert!(tm.instructions().values().all(|i| i.to_value));
+
+        let pairs = TuringParser::parse(Rule::instruction, "(q0, *, 1, R, q1);")
+            .unwrap()
+            .next()
+            .unwrap();
+        let error = TuringInstruction::from(pairs.clone().into_inner()).unwrap_err();
+        assert_eq!(error.found(), Some(Rule::wildcard));
+        assert_eq!(
+            TuringInstruction::expand(pairs.into_inner()).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    /// Test the repeated values of the tape, which expand before the leading 0s are skipped
+    fn parse_repetitions() {
+        let code = |tape: &str| format!("{{{}}};\nI = {{q0}};\nF = {{q0}};\n", tape);
+
+        for (short, long) in [
+            ("1^5 0 1^3", "111110111"),
+            ("0^3 1^2", "00011"),
+            ("1^2 0[1]1^2", "110[1]11"),
+            ("_^2 1 B^1", "0010"),
+        ] {
+            let (tm, _) = TuringMachine::new(&code(short)).unwrap();
+            let (expected, _) = TuringMachine::new(&code(long)).unwrap();
+            assert_eq!(tm, expected, "{}", short);
+        }
+
+        let (tm, _) = TuringMachine::new(&code("1^57 0 1^12")).unwrap();
+        assert_eq!(tm.tape_trimmed().0.iter().filter(|v| **v).count(), 69);
+
+        let error = TuringMachine::new(&code("1^0 1")).unwrap_err();
+        assert_eq!(error.message(), "A value cannot be repeated 0 times");
+        assert_eq!(error.position(), ErrorPosition::new((0, 2), Some((0, 5))));
+
+        let error = TuringMachine::new(&code("0^5")).unwrap_err();
+        assert_eq!(error.message(), "Expected at least a 1 in the tape");
+
+        let error = TuringMachine::new(&code("1 1^99999999999999999999999")).unwrap_err();
+        assert_eq!(error.expected(), Rule::count);
+        let error =
+            TuringMachine::new(&code(&format!("1 1^{}", DEFAULT_MAX_TAPE_CELLS))).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "The repetition 1^1000000 makes the tape longer than 1000000 cells"
+        );
+
+        let small = ParserOptions {
+            max_tape_cells: 10,
+            ..ParserOptions::default()
+        };
+        assert!(TuringMachine::new_with_options(&code("1^10"), &small).is_ok());
+        assert!(TuringMachine::new_with_options(&code("1 1^10"), &small).is_err());
+        assert!(TuringMachine::new_with_options(&code("4, 4"), &small).is_err());
+    }
+
+    #[test]
+    /// Test that the `//` and `#` comments are skipped anywhere a space can be written, and found by `comments`
+    fn line_comments() {
+        let plain = "/// sum\n{111011};\nI = {q0};\nF = {q2};\ncompose = {sum, div2};\n\n(q2, 0, 0, H, q3);\n";
+        let commented = "/// sum\n\
+            // the input\n\
+            {111011}; # 2 and 1\n\
+            I = {q0}; F = {q2}; // the states\n\
+            compose = {sum, // adds them\n\
+            div2};\n\
+            \n\
+            # halts\n\
+            (q2, // reading a 0\n\
+            0, 0, H, q3); // the end";
+
+        let (tm, warnings) = TuringMachine::new(commented).unwrap();
+        let (expected, expected_warnings) = TuringMachine::new(plain).unwrap();
+        assert_eq!(tm, expected);
+        assert_eq!(warnings.len(), expected_warnings.len());
+        assert_eq!(tm.description(), expected.description());
+
+        let comments = tm.comments();
+        assert_eq!(
+            comments.iter().map(|c| c.text.as_str()).collect::<Vec<_>>(),
+            vec![
+                "// the input",
+                "# 2 and 1",
+                "// the states",
+                "// adds them",
+                "# halts",
+                "// reading a 0",
+                "// the end"
+            ]
+        );
+        let comment = &comments[1];
+        assert_eq!(comment.span.start, (2, 11));
+        assert_eq!(
+            &tm.code()[comment.span.start_byte..comment.span.end_byte],
+            "# 2 and 1"
+        );
+
+        // The # of the alphabets is still a symbol
+        let (tm, _) = SymbolMachine::new(
+            "alphabet = {B, 1, #}; # the symbols\n{1#1};\nI = {q0};\nF = {q0};\n(q0, #, 1, R, q0);",
+        )
+        .unwrap();
+        assert!(tm.alphabet.symbol("#").is_some());
+    }
+
+    #[test]
+    /// Test a description of several lines, whose empty lines separate paragraphs
+    fn parse_multiline_description() {
+        let code = "/// Adds two numbers.\n///\n///   The result is written in unary.\n{111011};\nI = {q0};\nF = {q2};\ncompose = {sum};\n";
+        let (tm, _) = TuringMachine::new(code).unwrap();
+        assert_eq!(
+            tm.description(),
+            Some("Adds two numbers.\n\n  The result is written in unary.")
+        );
+        assert!(tm
+            .to_code()
+            .starts_with("/// Adds two numbers.\n///\n///   The result"));
+        assert_eq!(
+            TuringMachine::new(&tm.to_code()).unwrap().0.description(),
+            tm.description()
+        );
+
+        let (tm, _) = SymbolMachine::new(
+            &code
+                .replace("compose = {sum};\n", "")
+                .replace("{q0}", "{q2}"),
+        )
+        .unwrap();
+        assert_eq!(
+            tm.description.as_deref(),
+            Some("Adds two numbers.\n\n  The result is written in unary.")
+        );
+
+        // The docs after the definitions are still the docs of the instructions
+        let (tm, _) = TuringMachine::new(
+            "/// a\n/// b\n{1};\nI = {q0};\nF = {q1};\n/// c\n(q0, 1, 1, H, q1);",
+        )
+        .unwrap();
+        assert_eq!(tm.description(), Some("a\nb"));
+        assert_eq!(
+            tm.instructions()[&InstructionKey::new("q0", true)]
+                .doc
+                .as_deref(),
+            Some("c")
+        );
+    }
+
+    #[test]
+    /// Test that a comma after the last element of the lists between braces is accepted with a warning
+    fn parse_trailing_commas() {
+        let code = "{1};\nI = {q0};\nF = {q2,};\nA = {q2, };\nR = {q3,};\ncompose = {sum,};\n(q0, 1, 1, H, q2);";
+        let (tm, warnings) = TuringMachine::new(code).unwrap();
+        assert_eq!(tm.final_states(), vec!["q2", "q3"]);
+        assert_eq!(tm.accepting_states(), vec!["q2"]);
+        assert_eq!(tm.rejecting_states(), vec!["q3"]);
+        assert_eq!(tm.composed_libs().len(), 1);
+        assert_eq!(
+            warnings
+                .iter()
+                .filter(|w| matches!(w, CompilerWarning::StyleWarning { .. }))
+                .count(),
+            4
+        );
+
+        let (symbols, warnings) = SymbolMachine::new(
+            "alphabet = {_, a,};\nmark = a;\n{a};\nI = {q0};\nF = {q1,};\n(q0, a, _, R, q1);",
+        )
+        .unwrap();
+        assert_eq!(symbols.alphabet.symbols(), ["_", "a"]);
+        assert_eq!(warnings.len(), 2);
+
+        let (multi, warnings) = MultiTapeMachine::new(
+            "tapes = 2;\n{1};\n{0};\nI = {q0};\nF = {q1,};\n(q0, [1, 0], [1, 1], [R, R], q1);",
+        )
+        .unwrap();
+        assert_eq!(multi.final_states, vec!["q1"]);
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    /// Test that the warning of a trailing comma points at the comma
+    fn trailing_comma_position() {
+        let (_, warnings) =
+            TuringMachine::new("{1};\nI = {q0};\nF = {q2, q5,};\n(q0, 1, 1, H, q5);").unwrap();
+        assert_eq!(
+            warnings,
+            vec![CompilerWarning::StyleWarning {
+                position: ErrorPosition::new((2, 12), Some((2, 13))),
+                message: String::from("The comma after the last element of the list is not needed"),
+            }]
+        );
+    }
+
+    #[test]
+    /// Test that the semicolon of the last instruction can be left out, but not the one of the others
+    fn missing_last_semicolon() {
+        let (tm, warnings) = TuringMachine::new(
+            "{1};\nI = {q0};\nF = {q1};\n(q0, 1, 0, R, q0);\n(q0, 0, 0, H, q1)\n",
+        )
+        .unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(tm.instructions().len(), 2);
+
+        let (symbols, _) = SymbolMachine::new(
+            "alphabet = {_, a};\nmark = a;\n{a};\nI = {q0};\nF = {q1};\n(q0, a, _, R, q1)",
+        )
+        .unwrap();
+        assert_eq!(symbols.instructions.len(), 1);
+
+        let (multi, _) = MultiTapeMachine::new(
+            "tapes = 2;\n{1};\n{0};\nI = {q0};\nF = {q1};\n(q0, [1, 0], [1, 1], [R, R], q1) // done",
+        )
+        .unwrap();
+        assert_eq!(multi.instructions.len(), 1);
+
+        assert!(TuringMachine::new(
+            "{1};\nI = {q0};\nF = {q1};\n(q0, 1, 0, R, q0)\n(q0, 0, 0, H, q1);"
+        )
+        .is_err());
+    }
+
+    #[test]
+    /// Test that the code written from a machine has no trailing commas and ends every instruction with a semicolon
+    fn relaxed_code_normalized() {
+        let (tm, _) =
+            TuringMachine::new("{1};\nI = {q0};\nF = {q1,};\ncompose = {sum,};\n(q0, 0, 0, H, q1)")
+                .unwrap();
+        let code = tm.to_code();
+        assert!(!code.contains(",}"));
+        assert!(code.contains("F = {q1};"));
+        assert!(code.contains("compose = {sum};"));
+        assert!(code.contains("(q0, 0, 0, H, q1);"));
+    }
+
+    #[test]
+    /// Test that the cells of the tape written in the code are the initial cells when the leading 0s are preserved,
+    /// and that they are skipped otherwise
+    fn preserve_leading_zeros() {
+        let code = "{0011}; I = {q0}; F = {qf}; (q0, 0, 0, R, q0); (q0, 1, 1, H, qf);";
+        let preserve = ParserOptions {
+            preserve_leading_zeros: true,
+            ..ParserOptions::default()
+        };
+
+        let (skipped, _) = TuringMachine::new(code).unwrap();
+        let (short, _) =
+            TuringMachine::new("{11}; I = {q0}; F = {qf}; (q0, 0, 0, R, q0); (q0, 1, 1, H, qf);")
+                .unwrap();
+        assert_eq!(skipped.tape_vec(), short.tape_vec());
+        assert_eq!(skipped.read_cell(skipped.origin()), Some(true));
+        assert_eq!(skipped.head_offset(), 0);
+
+        let (mut tm, _) = TuringMachine::new_with_options(code, &preserve).unwrap();
+        let origin = tm.origin();
+        let cells: Vec<Option<bool>> = (0..4).map(|i| tm.read_cell(origin + i)).collect();
+        assert_eq!(
+            cells,
+            vec![Some(false), Some(false), Some(true), Some(true)]
+        );
+        assert_eq!(tm.head_offset(), 0);
+        assert_eq!(tm.used_cells(), 4);
+
+        // The head walks over the two 0s before reaching the first 1
+        assert_eq!(tm.final_result(), TuringOutput::Defined((4, 2)));
+        assert_eq!(tm.head_offset(), 2);
+
+        // The new inputs map onto the cells like the tape of the code, in both modes
+        let input = [false, false, true, true];
+        tm.set_input_tape(&input).unwrap();
+        let (compiled, _) = TuringMachine::new_with_options(code, &preserve).unwrap();
+        assert_eq!(tm.tape_vec(), compiled.tape_vec());
+        assert_eq!(tm.head_offset(), compiled.head_offset());
+        assert_eq!(tm.final_result(), TuringOutput::Defined((4, 2)));
+
+        let mut skipped = skipped;
+        skipped.set_input_tape(&input).unwrap();
+        assert_eq!(skipped.tape_vec(), short.tape_vec());
+        assert_eq!(skipped.final_result(), TuringOutput::Defined((2, 2)));
+
+        let results = tm.run_batch(&[input.to_vec(), vec![true]], 10);
+        assert!(matches!(
+            results[0],
+            ExecutionResult::Halted { steps: 4, .. }
+        ));
+        assert!(matches!(
+            results[1],
+            ExecutionResult::Halted { steps: 2, .. }
+        ));
+    }
+
+    #[test]
+    /// Test that the name of the machine is parsed, serialized and written again, and that it can only be given once
+    fn parse_machine_name() {
+        let code = "/// Adds two numbers\nname = \"adder\";\ncompose = {sum};\n{11011};\nI = {q0};\nF = {q2};\n";
+        let (tm, _) = TuringMachine::new(code).unwrap();
+        assert_eq!(tm.name(), Some("adder"));
+
+        let written = tm.to_code();
+        assert!(written.contains("name = \"adder\";\n"));
+        let (compiled, _) = TuringMachine::new(&written).unwrap();
+        assert_eq!(compiled.name(), Some("adder"));
+        assert_eq!(compiled.to_code(), written);
+
+        let json = serde_json::to_string(&tm).unwrap();
+        assert!(json.contains("\"name\":\"adder\""));
+        let deserialized: TuringMachine = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.name(), Some("adder"));
+
+        let (unnamed, _) = TuringMachine::new("{1}; I = {q0}; F = {q0};").unwrap();
+        assert_eq!(unnamed.name(), None);
+        assert!(!unnamed.to_code().contains("name"));
+
+        match TuringMachine::new("name = \"a\";\n{1};\nname = \"b\";\nI = {q0};\nF = {q0};") {
+            Err(CompilerError::SyntaxError {
+                position, message, ..
+            }) => {
+                assert_eq!(message, "The name can only be given once");
+                assert_eq!(position, ErrorPosition::new((2, 1), Some((2, 12))));
+            }
+            other => panic!("Expected a syntax error, got {:?}", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_composition {
+    use std::{
+        collections::{HashMap, HashSet},
+        fs,
+    };
+
+    use crate::natural_cmp;
+    use crate::warnings::ErrorPosition;
+    use crate::CompilerError;
+    use crate::CompilerWarning;
+    use crate::ExecutionResult;
+    use crate::InstructionKey;
+    use crate::Movement;
+    use crate::Outcome;
+    use crate::ParserOptions;
+    use crate::Rule;
+    use crate::SymbolMachine;
+    use crate::TuringInstruction;
+    use crate::TuringMachine;
+    use crate::TuringOutput;
+    use crate::TuringParser;
+    use crate::LIBRARIES;
+    use pest::{consumes_to, parses_to};
+
+    #[test]
+    fn parse_composition_function_name_valid() {
+        let test = "sum_test";
+
+        parses_to! {
+            parser: TuringParser,
+            input: test,
+            rule: Rule::function_name,
+            tokens: [
+                function_name(0, 8)
+            ]
+        }
+    }
+
+    #[test]
+    fn parse_composition_valid() {
+        let test = "compose = { sum_test };";
+
+        parses_to! {
+            parser: TuringParser,
+            input: test,
+            rule: Rule::composition,
+            tokens: [
+                composition(0, 23, [
+                    function_name(12, 20)
+                ])
+            ]
+        }
+    }
+
+    #[test]
+    fn parse_multiple_compositions() {
+        let test = "compose = {sum, diff};";
+
+        parses_to! {
+            parser: TuringParser,
+            input: test,
+            rule: Rule::composition,
+            tokens: [
+                composition(0, 22, [
+                    function_name(11, 14),
+                    function_name(16, 20)
+                ])
+            ]
+        }
+    }
+
+    #[test]
+    /// Test that all the libraries are correctly parsed
+    /// (should not panic)
+    fn libraries() {
+        for lib in LIBRARIES {
+            let _ = lib.get_instructions();
+        }
+    }
+
+    #[test]
+    /// Test compiling a program that uses composition and nothing else (no extra code)
+    /// Also tests that you can write the `compose`, tape (`{111011}`), initial state (`I = {q0}`) and final state (`F = {q2}`) in any order
+    fn composition() {
+        let test = "
+        compose = {sum};
+        
+        F = {q2};
+        {111011};
+        I = {q0};
+        ";
+
+        let mut tm = match TuringMachine::new(test) {
+            Ok(t) => t.0,
+            Err(e) => {
+                println!("{:?}", e);
+                std::process::exit(1);
+            }
+        };
+
+        assert_eq!(tm.final_result(), TuringOutput::Defined((5, 3)));
+
+        assert_eq!(
+            tm.to_string(),
+            "0 0 0 0 1 1 0 0 1 0 0 \n              ^       "
+        );
+    }
+
+    /// Runs a machine with a plain interpreter over its instructions, without the transition table,
+    /// and returns the number of steps and 1s like `final_result`, or None if it gets stuck
+    fn interpret(tm: &TuringMachine) -> Option<(usize, u32)> {
+        let mut tape: HashMap<isize, bool> = tm
+            .tape()
+            .enumerate()
+            .map(|(i, v)| (i as isize, v))
+            .collect();
+        let mut head = tm.tape_position as isize;
+        let mut state = String::from(tm.current_state());
+        let mut steps = 0;
+
+        loop {
+            let halting = tm.final_states().contains(&state);
+            let value = tape.get(&head).copied().unwrap_or(false);
+
+            match tm.instructions().get(&InstructionKey::new(&state, value)) {
+                Some(i) => {
+                    tape.insert(head, i.to_value);
+                    head += match i.movement {
+                        Movement::LEFT => -1,
+                        Movement::RIGHT => 1,
+                        Movement::HALT | Movement::STAY => 0,
+                    };
+                    state = i.to_state.clone();
+                }
+                None if halting => {}
+                None => return None,
+            }
+            steps += 1;
+
+            if halting {
+                return Some((steps, tape.values().filter(|v| **v).count() as u32));
+            }
+        }
+    }
+
+    #[test]
+    /// Test that the transition table runs every library exactly like a plain interpreter
+    fn libraries_transition_table() {
+        let inputs = [
+            ("sum", "11101111"),
+            ("x2", "1111"),
+            ("mod", "11111110111"),
+            ("div2", "1111111"),
+            ("bound_diff", "111110111"),
+        ];
+
+        for (name, input) in inputs {
+            let lib = LIBRARIES.iter().find(|l| l.name == name).unwrap();
+            let code = format!(
+                "compose = {{{}}}; {{{}}}; I = {{{}}}; F = {{{}}};",
+                lib.name, input, lib.initial_state, lib.final_state
+            );
+
+            let (mut tm, _) = TuringMachine::new(&code).unwrap();
+            let (steps, ones) = interpret(&tm).unwrap();
+
+            assert_eq!(
+                tm.final_result(),
+                TuringOutput::Defined((steps, ones)),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    /// Test that the step loop gives the right result for the `mod` library on a large input
+    /// (the result is the number of 1s left on the tape)
+    fn mod_large_input() {
+        let code = format!(
+            "compose = {{mod}}; {{{}0{}}}; I = {{q0}}; F = {{qf}};",
+            "1".repeat(1001),
+            "1".repeat(8)
+        );
+
+        let (mut tm, _) = TuringMachine::new(&code).unwrap();
+        tm.loop_threshold = usize::MAX;
+
+        let (steps, ones) = interpret(&tm).unwrap();
+
+        assert_eq!(ones, 1000 % 7);
+        assert_eq!(tm.final_result(), TuringOutput::Defined((steps, ones)));
+    }
+
+    #[test]
+    /// Test running the `sum` library against several inputs without parsing the code again
+    fn set_input_values() {
+        let (mut tm, _) = TuringMachine::new("compose = {sum}; {1}; I = {q0}; F = {q2};").unwrap();
+
+        for (x, y) in [(0, 0), (2, 1), (5, 7), (10, 3)] {
+            tm.set_input_values(&[x, y]).unwrap();
+            assert_eq!(tm.values(), vec![x, y]);
+
+            let TuringOutput::Defined((_, ones)) = tm.final_result() else {
+                panic!("The machine did not halt");
+            };
+            assert_eq!(ones, x + y);
+        }
+    }
+
+    #[test]
+    /// Test that the input tape is validated like the tape given in the code
+    fn set_input_tape() {
+        let (mut tm, _) = TuringMachine::new("compose = {sum}; {1}; I = {q0}; F = {q2};").unwrap();
+
+        assert_eq!(
+            tm.set_input_tape(&[false, false]).unwrap_err().message(),
+            "Expected at least a 1 in the tape"
+        );
+
+        tm.set_input_tape(&[false, true, true, true, false, true, true])
+            .unwrap();
+        assert_eq!(tm.head_offset(), 0);
+        assert_eq!(tm.values(), vec![2, 1]);
+        assert_eq!(tm.final_result(), TuringOutput::Defined((5, 3)));
+
+        tm.reset();
+        assert_eq!(tm.values(), vec![2, 1]);
+    }
+
+    #[test]
+    /// Test decoding the results of the libraries
+    fn numeric_result() {
+        let (mut tm, _) =
+            TuringMachine::new("compose = {sum}; {111011}; I = {q0}; F = {q2};").unwrap();
+        assert_eq!(tm.numeric_result(), None);
+        assert!(tm.numeric_results().is_empty());
+
+        tm.final_result();
+        assert_eq!(tm.numeric_result(), Some(3));
+
+        for (x, y) in [(3, 1), (4, 0), (1, 3), (2, 2)] {
+            let (mut tm, _) =
+                TuringMachine::new("compose = {bound_diff}; {1}; I = {q0}; F = {qf};").unwrap();
+            tm.set_input_values(&[x, y]).unwrap();
+            tm.final_result();
+
+            assert_eq!(tm.numeric_result(), Some(x.saturating_sub(y)));
+        }
+
+        // A machine that halts right away leaves its inputs
+        let (mut tm, _) = TuringMachine::new("{1101110}; I = {q0}; F = {q0};").unwrap();
+        tm.final_result();
+        assert_eq!(tm.numeric_results(), vec![1, 2]);
+    }
+
+    #[test]
+    /// Test that the `sum` library halts and adds every pair of numbers up to 5
+    fn verify_halts_sum() {
+        let (tm, _) = TuringMachine::new("compose = {sum}; {1}; I = {q0}; F = {q2};").unwrap();
+
+        let inputs = (0..=5).flat_map(|x| (0..=5).map(move |y| vec![x, y]));
+        let report = tm.verify_halts(inputs, 1000);
+
+        assert_eq!(report.results.len(), 36);
+        assert!(report.all_halted());
+        for result in report.results.iter() {
+            assert_eq!(result.output, Some(result.input[0] + result.input[1]));
+        }
+
+        // The report can be attached to other metadata
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(
+            serde_json::from_str::<crate::VerificationReport>(&json).unwrap(),
+            report
+        );
+
+        let report = tm.verify_halts([vec![], vec![5, 5]], 5);
+        let outcomes: Vec<&Outcome> = report.failures().map(|r| &r.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                &Outcome::InvalidInput {
+                    message: String::from("Expected at least a value")
+                },
+                &Outcome::StepLimitReached
+            ]
+        );
+    }
+
+    #[test]
+    /// Test that running the `mod` library over a batch of inputs gives the sequential results in order
+    fn run_batch_mod() {
+        let (tm, _) = TuringMachine::new("compose = {mod}; {1}; I = {q0}; F = {qf};").unwrap();
+
+        let inputs: Vec<Vec<bool>> = (0..100)
+            .map(|i| {
+                let mut input = vec![true; i / 10 + 1];
+                input.push(false);
+                input.extend(vec![true; i % 10 + 2]);
+                input
+            })
+            .collect();
+
+        let results = tm.run_batch(&inputs, 100_000);
+        assert_eq!(results.len(), 100);
+
+        for (i, (input, result)) in inputs.iter().zip(results).enumerate() {
+            let mut sequential = tm.clone();
+            sequential.set_input_tape(input).unwrap();
+
+            assert_eq!(result, sequential.run(100_000));
+            let TuringOutput::Defined((_, ones)) = result.output() else {
+                panic!("The machine did not halt");
+            };
+            assert_eq!(ones as usize, (i / 10) % (i % 10 + 1));
+        }
+
+        // The machine itself is not run
+        assert_eq!(tm.steps, 0);
+        assert_eq!(
+            tm.run_batch(&[vec![false]], 10),
+            vec![ExecutionResult::InvalidInput {
+                message: String::from("Expected at least a 1 in the tape")
+            }]
+        );
+    }
+
+    #[test]
+    /// Test comparing the `x2` and `div2` libraries with a hand-written doubling machine
+    fn equivalent_on_x2() {
+        // Erases the first 1, and then moves each of the other 1s to the right as two 1s
+        let doubling = "
+        {1};
+        I = {q0};
+        F = {qf};
+
+        (q0, 1, 0, R, s);
+        (s, 1, 0, R, a);
+        (s, 0, 0, H, qf);
+        (a, 1, 1, R, a);
+        (a, 0, 0, R, b);
+        (b, 1, 1, R, b);
+        (b, 0, 1, R, w);
+        (w, 0, 1, L, c);
+        (c, 1, 1, L, c);
+        (c, 0, 0, L, d);
+        (d, 1, 1, L, d);
+        (d, 0, 0, R, s);
+        ";
+        let (doubling, _) = TuringMachine::new(doubling).unwrap();
+        let (x2, _) = TuringMachine::new("compose = {x2}; {1}; I = {q0}; F = {qf};").unwrap();
+        let (div2, _) = TuringMachine::new("compose = {div2}; {1}; I = {q0}; F = {qf};").unwrap();
+
+        let report = doubling.equivalent_on(&x2, (0..=6).map(|x| vec![x]), 10_000);
+        assert_eq!(report.inputs, 7);
+        assert!(report.is_equivalent());
+
+        let report = doubling.equivalent_on(&div2, (0..=6).map(|x| vec![x]), 10_000);
+        assert_eq!(
+            report.counterexample,
+            Some(crate::Counterexample {
+                input: vec![1],
+                expected: Some(2),
+                got: Some(0),
+            })
+        );
+
+        // Two machines that do not halt agree, but halting is different from not halting
+        let report = doubling.equivalent_on(&x2, [vec![6]], 10);
+        assert!(report.is_equivalent());
+        let (looping, _) =
+            TuringMachine::new("{1}; I = {q0}; F = {qf}; (q0, 1, 1, H, q0);").unwrap();
+        let report = doubling.equivalent_on(&looping, [vec![0]], 10);
+        let counterexample = report.counterexample.unwrap();
+        assert_eq!(counterexample.expected, Some(0));
+        assert_eq!(counterexample.got, None);
+        assert_eq!(doubling.steps, 0);
+    }
+
+    #[test]
+    /// Test that overwriting an instruction of a library warns about it, wherever the composition is in the code
+    fn library_override() {
+        let before = "compose = {sum}; {111011}; I = {q0}; F = {q2}; (q1, 0, 0, H, q2);";
+        // The instructions always come after the definitions, which can be in any order
+        let after = "{111011}; I = {q0}; F = {q2}; compose = {sum}; (q1, 0, 0, H, q2);";
+
+        for code in [before, after] {
+            let (tm, warnings) = TuringMachine::new(code).unwrap();
+
+            assert_eq!(warnings.len(), 1);
+            assert!(matches!(
+                &warnings[0],
+                CompilerWarning::LibraryOverride { state, value_from: false, library, .. }
+                    if state == "q1" && library == "sum"
+            ));
+            assert_eq!(
+                tm.instructions()[&InstructionKey::new("q1", false)].movement,
+                Movement::HALT
+            );
+        }
+
+        // A library overwriting another one is a collision of the composition
+        let (_, warnings) =
+            TuringMachine::new("compose = {sum, div2}; {111011}; I = {q0}; F = {qf};").unwrap();
+        let overwritten: Vec<(&str, bool, &str)> = warnings
+            .iter()
+            .map(|w| match w {
+                CompilerWarning::CompositionCollision {
+                    state,
+                    value_from,
+                    library,
+                    composed,
+                    ..
+                } if composed == "div2" => (state.as_str(), *value_from, library.as_str()),
+                _ => panic!("Unexpected warning {:?}", w),
+            })
+            .collect();
+        assert_eq!(
+            overwritten,
+            vec![
+                ("q0", true, "sum"),
+                ("q1", false, "sum"),
+                ("q1", true, "sum"),
+                ("q2", true, "sum")
+            ]
+        );
+    }
+
+    #[test]
+    /// Test that decoding the encoding of some numbers gives them back
+    fn encode_decode() {
+        let encode = |values: &[u32]| TuringMachine::encode_input(values).unwrap();
+
+        assert_eq!(encode(&[0]), vec![true]);
+        assert_eq!(
+            encode(&[3, 1]),
+            vec![true, true, true, true, false, true, true]
+        );
+        assert_eq!(
+            TuringMachine::encode_input(&[]).unwrap_err().message(),
+            "Expected at least a value"
+        );
+
+        // The blank cells around the numbers are ignored
+        assert_eq!(
+            TuringMachine::decode_output(&[false, false, true, false, false, true, true, false]),
+            vec![0, 1]
+        );
+        assert!(TuringMachine::decode_output(&[false, false]).is_empty());
+
+        // Every list of up to 4 numbers up to 5, and some longer ones
+        let mut inputs: Vec<Vec<u32>> = vec![vec![100], vec![7; 30], (0..50).collect()];
+        for len in 1..=4u32 {
+            for i in 0..6u32.pow(len) {
+                inputs.push((0..len).map(|d| i / 6u32.pow(d) % 6).collect());
+            }
+        }
+
+        for values in inputs {
+            let mut tape = vec![false; values.len() % 3];
+            tape.extend(encode(&values));
+            tape.extend(vec![false; values[0] as usize % 4]);
+
+            assert_eq!(TuringMachine::decode_output(&tape), values);
+        }
+
+        // The machines read their inputs the same way
+        let (mut tm, _) = TuringMachine::new("compose = {sum}; {1}; I = {q0}; F = {q2};").unwrap();
+        tm.set_input_values(&[4, 2]).unwrap();
+        assert_eq!(tm.tape_trimmed().0, encode(&[4, 2]).as_slice());
+        assert_eq!(tm.values(), vec![4, 2]);
+    }
+
+    #[test]
+    /// Test that minimizing a machine merges its equivalent states and keeps computing the same numbers
+    fn minimized() {
+        // The states a and b both keep moving right over the 1s, and add a 1 at the end
+        let code = "
+        {1};
+        I = {q0};
+        F = {qf};
+
+        (q0, 1, 1, R, a);
+        (a, 1, 1, R, b);
+        (b, 1, 1, R, a);
+        (a, 0, 1, H, qf);
+        (b, 0, 1, H, qf);
+        ";
+        let (tm, _) = TuringMachine::new(code).unwrap();
+        let minimized = tm.minimized().unwrap();
+
+        assert_eq!(minimized.states(), vec!["m0", "m1", "m2"]);
+        assert_eq!(
+            minimized.code(),
+            "{1};\nI = {m0};\nF = {m2};\n\n(m0, 1, 1, R, m1);\n(m1, 0, 1, H, m2);\n(m1, 1, 1, R, m1);\n"
+        );
+        assert!(tm
+            .equivalent_on(&minimized, (0..=10).map(|x| vec![x]), 1000)
+            .is_equivalent());
+
+        // The composed libraries are written out with the new names
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+        let minimized = tm.minimized().unwrap();
+
+        assert!(minimized.composed_libs().is_empty());
+        assert!(minimized.states().len() <= tm.states().len());
+        assert_eq!(minimized.initial_state(), "m0");
+        assert_eq!(minimized.clone().final_result(), tm.clone().final_result());
+
+        let inputs = (0..=5).flat_map(|x| (0..=5).map(move |y| vec![x, y]));
+        let report = tm.equivalent_on(&minimized, inputs, 10_000);
+        assert_eq!(report.inputs, 36);
+        assert!(report.is_equivalent());
+
+        // Minimizing again gives the same machine
+        assert_eq!(minimized.minimized().unwrap().code(), minimized.code());
+    }
+
+    #[test]
+    /// Test that a minimized machine is compiled with the options of the original one
+    fn minimized_with_options() {
+        let code =
+            "{0}; I = {q0}; F = {qf}; (q0, 0, 1, R, a); (a, 0, 1, H, qf); (q0, 1, 1, H, qf);";
+        let options = ParserOptions {
+            allow_empty_tape: true,
+            ..ParserOptions::default()
+        };
+        let (tm, _) = TuringMachine::new_with_options(code, &options).unwrap();
+        let mut minimized = tm.minimized().unwrap();
+
+        assert_eq!(minimized.states(), vec!["m0", "m1", "m2"]);
+        assert_eq!(minimized.tape_value(), TuringOutput::Defined((0, 0)));
+        assert_eq!(minimized.final_result(), tm.clone().final_result());
+        assert!(minimized.set_input_tape(&[false, false]).is_ok());
+
+        // Without the options, the blank tape of the code is rejected
+        assert!(TuringMachine::new(minimized.code()).is_err());
+    }
+
+    #[test]
+    /// Test that the instructions of the `mod` library are listed in natural order of their states (`q2` before `q10`),
+    /// both by `instructions_sorted`, in the code written again and in the serialized machine
+    fn instructions_sorted() {
+        assert_eq!(natural_cmp("q2", "q10"), std::cmp::Ordering::Less);
+        assert_eq!(natural_cmp("q10", "q10"), std::cmp::Ordering::Equal);
+        assert_eq!(natural_cmp("q10", "qf"), std::cmp::Ordering::Less);
+        assert_eq!(natural_cmp("q", "q0"), std::cmp::Ordering::Less);
+        assert_eq!(natural_cmp("q01", "q1"), std::cmp::Ordering::Less);
+
+        let (mut tm, _) =
+            TuringMachine::new("compose = {mod}; {11111110111}; I = {q0}; F = {qf};").unwrap();
+
+        let mut expected: Vec<(String, bool)> = vec![(String::from("q0"), true)];
+        for state in 1..=11 {
+            if state != 3 {
+                expected.push((format!("q{}", state), false));
+            }
+            expected.push((format!("q{}", state), true));
+        }
+
+        let sorted: Vec<(String, bool)> = tm
+            .instructions_sorted()
+            .into_iter()
+            .map(|i| (i.from_state.clone(), i.from_value))
+            .collect();
+        assert_eq!(sorted, expected);
+
+        let json = serde_json::to_value(&tm).unwrap();
+        let serialized: Vec<(String, bool)> = json["program"]["instructions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| {
+                (
+                    String::from(i["from_state"].as_str().unwrap()),
+                    i["from_value"].as_bool().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(serialized, expected);
+
+        // Removing an instruction writes the library out in the code
+        tm.remove_instruction("q11", false);
+        expected.pop();
+        expected.pop();
+        expected.push((String::from("q11"), true));
+
+        let written: Vec<(String, bool)> = tm
+            .code()
+            .lines()
+            .filter(|l| l.starts_with('('))
+            .map(|l| {
+                let fields: Vec<&str> = l.trim_matches(|c| "();".contains(c)).split(", ").collect();
+                (String::from(fields[0]), fields[1] == "1")
+            })
+            .collect();
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    /// Test that the instructions are found by their key, and that they can be sorted and put in sets
+    fn instruction_keys() {
+        let code = "{1}; I = {q10}; F = {q1};\n(q10, 1, 0, R, q2);\n(q2, 0, 1, L, q10);\n(q10, 0, 0, H, q1);";
+        let (tm, _) = TuringMachine::new(code).unwrap();
+
+        for (key, instruction) in tm.instructions() {
+            assert_eq!(&instruction.key(), key);
+        }
+        let key = InstructionKey::from((String::from("q2"), false));
+        assert_eq!(key, InstructionKey::new("q2", false));
+        assert_eq!(tm.instructions()[&key].to_state, "q10");
+
+        let mut keys: Vec<&InstructionKey> = tm.instructions().keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys.iter().map(|k| k.to_string()).collect::<Vec<_>>(),
+            vec!["(q2, 0)", "(q10, 0)", "(q10, 1)"]
+        );
+
+        let mut instructions: Vec<TuringInstruction> =
+            tm.instructions().values().cloned().collect();
+        instructions.sort();
+        assert_eq!(
+            instructions,
+            tm.instructions_sorted()
+                .into_iter()
+                .cloned()
+                .collect::<Vec<_>>()
+        );
+
+        let set: HashSet<TuringInstruction> = instructions.iter().cloned().collect();
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&tm.instructions()[&key]));
+    }
+
+    #[test]
+    /// Test the tapes given as decimal numbers, which are encoded like `encode_input`
+    fn decimal_tapes() {
+        let code = "compose = {sum};\nF = {q2};\n{3, 5};\nI = {q0};\n";
+        let (mut tm, _) = TuringMachine::new(code).unwrap();
+        let (mut binary, _) = TuringMachine::new(&code.replace("3, 5", "11110111111")).unwrap();
+        assert_eq!(tm.tape_vec(), binary.tape_vec());
+        assert_eq!(tm.code(), code);
+        assert_eq!(tm.final_result(), binary.final_result());
+        assert_eq!(tm.final_result(), TuringOutput::Defined((7, 8)));
+
+        let (single, _) = TuringMachine::new(&code.replace("3, 5", "2,")).unwrap();
+        assert_eq!(
+            single.tape_trimmed().0,
+            TuringMachine::encode_input(&[2]).unwrap()
+        );
+
+        let error = TuringMachine::new(&code.replace("3, 5", "1[1]0, 2")).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "A tape is either some numbers separated by commas or its cells, not both"
+        );
+        assert_eq!(error.line(), 2);
+
+        let error = TuringMachine::new(&code.replace("3, 5", "3, 99999999999")).unwrap_err();
+        assert_eq!(error.expected(), Rule::number);
+    }
+
+    #[test]
+    /// Test that the instructions of the imported files are added before the ones of the code,
+    /// reading the files with a resolver kept in memory
+    fn import_files() {
+        let files: HashMap<String, String> = HashMap::from([
+            (
+                String::from("erase.tm"),
+                String::from("import \"halt.tm\";\n(q0, 1, 0, R, q0);\n"),
+            ),
+            (String::from("halt.tm"), String::from("(q0, 0, 0, H, q1);")),
+        ]);
+        let code = "{111};\nI = {q0};\nF = {q1};\nimport \"erase.tm\";\nimport \"halt.tm\";\n";
+
+        let (mut tm, warnings) = TuringMachine::new_with_resolver(code, &files).unwrap();
+        assert!(warnings.is_empty());
+        assert!(tm
+            .instructions()
+            .contains_key(&InstructionKey::new("q0", true)));
+        assert_eq!(tm.final_result(), TuringOutput::Defined((5, 0)));
+
+        // The code can overwrite the imported instructions
+        let (_, warnings) =
+            TuringMachine::new_with_resolver(&format!("{}(q0, 1, 1, R, q0);", code), &files)
+                .unwrap();
+        assert!(matches!(
+            warnings[..],
+            [CompilerWarning::StateOverwrite { .. }]
+        ));
+
+        // Without a resolver the files cannot be read
+        assert!(matches!(
+            TuringMachine::new(code),
+            Err(CompilerError::ImportError { .. })
+        ));
+    }
+
+    #[test]
+    /// Test that importing a file that does not exist is an error at the import
+    fn import_missing_file() {
+        let files: HashMap<String, String> = HashMap::new();
+        let code = "{1};\nI = {q0};\nF = {q0};\nimport \"missing.tm\";";
+
+        match TuringMachine::new_with_resolver(code, &files) {
+            Err(error @ CompilerError::ImportError { .. }) => {
+                assert_eq!(error.position(), ErrorPosition::new((3, 1), Some((3, 21))));
+                assert_eq!(
+                    error.message(),
+                    "Could not import the file missing.tm: The file missing.tm does not exist"
+                );
+            }
+            other => panic!("Expected an import error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    /// Test that two files that import each other are an error instead of an endless import
+    fn import_cycle() {
+        let files: HashMap<String, String> = HashMap::from([
+            (
+                String::from("a.tm"),
+                String::from("import \"b.tm\";\n(q0, 1, 1, R, q0);"),
+            ),
+            (
+                String::from("b.tm"),
+                String::from("import \"a.tm\";\n(q0, 0, 0, H, q1);"),
+            ),
+        ]);
+        let code = "{1};\nI = {q0};\nF = {q1};\nimport \"a.tm\";";
+
+        match TuringMachine::new_with_resolver(code, &files) {
+            Err(CompilerError::ImportError { path, source, .. }) => {
+                assert_eq!(path, "a.tm");
+                assert!(source.ends_with("The file imports itself through a.tm -> b.tm -> a.tm"));
+            }
+            other => panic!("Expected an import error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    /// Test that a function defined in the code is composed like a library, and written again by `to_code`
+    fn compose_local_function() {
+        let code = "{11};\nI = {q0};\nF = {qf};\n/// Adds one to the number\n\
+            fn increment {\n    I = {q0};\n    F = {qf};\n    (q0, 1, 1, R, q0);\n    (q0, 0, 1, H, qf);\n}\n\
+            compose = {increment};\n";
+        let (mut tm, warnings) = TuringMachine::new(code).unwrap();
+        assert!(warnings.is_empty());
+
+        let library = &tm.composed_libs()[0];
+        assert_eq!(library.name, "increment");
+        assert_eq!(library.description, "Adds one to the number");
+        assert_eq!(library.initial_state, "q0");
+        assert_eq!(library.final_state, "qf");
+        assert_eq!(library.used_states.to_vec(), vec!["q0", "qf"]);
+        assert!(!library.is_builtin());
+        assert!(LIBRARIES.iter().all(|l| l.is_builtin()));
+        assert_eq!(tm.final_result(), TuringOutput::Defined((4, 3)));
+
+        let written = tm.to_code();
+        assert!(written.contains("fn increment {\n"));
+        let (compiled, _) = TuringMachine::new(&written).unwrap();
+        assert_eq!(compiled.to_code(), written);
+        assert_eq!(compiled.instructions(), tm.instructions());
+    }
+
+    #[test]
+    /// Test that a function with the name of a built-in library replaces it with a warning
+    fn local_function_shadows_library() {
+        let code = "fn sum {\n    I = {q0};\n    F = {q1};\n    (q0, 1, 0, H, q1);\n}\n\
+            compose = {sum};\n{1};\nI = {q0};\nF = {q1};\n";
+        let (tm, warnings) = TuringMachine::new(code).unwrap();
+        assert_eq!(
+            warnings,
+            vec![CompilerWarning::LibraryShadowed {
+                position: ErrorPosition::new((0, 4), Some((0, 7))),
+                library: String::from("sum"),
+            }]
+        );
+        assert_eq!(tm.instructions().len(), 1);
+        assert!(!tm.composed_libs()[0].is_builtin());
+
+        let twice = format!(
+            "fn sum {{ I = {{q0}}; F = {{q1}}; (q0, 1, 0, H, q1); }}\n{}",
+            code
+        );
+        assert!(TuringMachine::new(&twice).is_err());
+    }
+
+    #[test]
+    /// Test that libraries composed with an alias get their states prefixed, so libraries with the same states do not collide
+    fn compose_with_alias() {
+        let code = "{111011};\nI = {s_q0};\nF = {d_qf};\ncompose = {sum as s, div2 as d};\n";
+        let (tm, warnings) = TuringMachine::new(code).unwrap();
+        assert!(warnings.is_empty());
+
+        let sum = LIBRARIES.iter().find(|l| l.name == "sum").unwrap();
+        let div2 = LIBRARIES.iter().find(|l| l.name == "div2").unwrap();
+        assert_eq!(
+            tm.instructions().len(),
+            sum.get_instructions().unwrap().len() + div2.get_instructions().unwrap().len()
+        );
+
+        let composed = tm.composed_libs();
+        assert_eq!(composed[0].alias.as_deref(), Some("s"));
+        assert_eq!(composed[1].composed_name(), "div2 as d");
+        // The library keeps the states of its code, and `prefixed` gives them in the machine
+        assert_eq!(composed[0].initial_state, "q0");
+        assert_eq!(composed[0].prefixed(&composed[0].initial_state), "s_q0");
+
+        let from_sum = &tm.instructions()[&InstructionKey::new("s_q1", false)];
+        assert_eq!(from_sum.to_state, "s_q2");
+        assert_eq!(tm.library_of(from_sum), Some("sum as s"));
+        let from_div2 = &tm.instructions()[&InstructionKey::new("d_q1", false)];
+        assert_eq!(from_div2.to_state, "d_qf");
+        assert_eq!(tm.library_of(from_div2), Some("div2 as d"));
+
+        let written = tm.to_code();
+        assert!(written.contains("compose = {sum as s, div2 as d};\n"));
+        let (compiled, _) = TuringMachine::new(&written).unwrap();
+        assert_eq!(compiled.instructions(), tm.instructions());
+
+        // Without the aliases, div2 overwrites the instructions of sum
+        let (_, warnings) =
+            TuringMachine::new("{111011};\nI = {q0};\nF = {qf};\ncompose = {sum, div2};\n")
+                .unwrap();
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings.iter().all(|w| matches!(
+            w,
+            CompilerWarning::CompositionCollision { library, composed, .. }
+                if library == "sum" && composed == "div2"
+        )));
+
+        // The libraries of a symbol machine cannot have an alias
+        assert!(SymbolMachine::new(
+            "alphabet = {0, 1};\n{111};\nI = {s_q0};\nF = {s_q2};\ncompose = {sum as s};\n"
+        )
+        .is_err());
+    }
+}
+
+#[cfg(test)]
+mod test_execution {
+    use std::{
+        fs,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::warnings::ErrorPosition;
+    use crate::CompilerWarning;
+    use crate::ExecutionResult;
+    use crate::Exploration;
+    use crate::InstructionKey;
+    use crate::Movement;
+    use crate::MultiTapeMachine;
+    use crate::MultiTapeResult;
+    use crate::MultiTapeStep;
+    use crate::ParserOptions;
+    use crate::ResolvedInstruction;
+    use crate::StepResult;
+    use crate::SymbolMachine;
+    use crate::SymbolResult;
+    use crate::TapeError;
+    use crate::TapeMode;
+    use crate::TuringMachine;
+    use crate::TuringMachineBuilder;
+    use crate::TuringOutput;
+    use crate::Verdict;
+
+    #[test]
+    /// Test that resetting the machine makes it run exactly like a freshly compiled one
+    fn reset() {
+        let test = "
+        compose = {sum};
+        {111011};
+        I = {q0};
+        F = {q2};
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+
+        let first = tm.final_result();
+        let first_tape = tm.to_string();
+
+        tm.reset();
+        assert_eq!(
+            tm.to_string(),
+            TuringMachine::new(test).unwrap().0.to_string()
+        );
+
+        assert_eq!(tm.final_result(), first);
+        assert_eq!(tm.to_string(), first_tape);
+    }
+
+    #[test]
+    /// Test that iterating over the machine yields one configuration per step
+    fn iterator() {
+        let test = "
+        compose = {sum};
+        {111011};
+        I = {q0};
+        F = {q2};
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        let configurations: Vec<_> = tm.iter().collect();
+
+        let (mut expected, _) = TuringMachine::new(test).unwrap();
+        let TuringOutput::Defined((steps, _)) = expected.final_result() else {
+            panic!("The machine should halt");
+        };
+
+        assert_eq!(configurations.len(), steps);
+        assert!(configurations.iter().all(|c| !c.undefined));
+        assert_eq!(configurations.last().unwrap().state, "q2");
+        assert_eq!(configurations.last().unwrap().tape, expected.tape_vec());
+    }
+
+    #[test]
+    /// Test that the iterator stops with an undefined configuration when the machine gets stuck
+    fn iterator_undefined() {
+        let test = "
+        {11};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 1, R, q1);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        let configurations: Vec<_> = tm.iter().take(1000).collect();
+
+        assert_eq!(configurations.len(), 2);
+        assert!(configurations[1].undefined);
+        assert_eq!(configurations[1].state, "q1");
+    }
+
+    #[test]
+    /// Test that stepping back undoes the steps exactly, even when cells were inserted at the front of the tape
+    fn step_back() {
+        let test = "
+        {11};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 0, L, q1);
+        (q1, 0, 1, L, q1);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.enable_history(10);
+
+        let mut configurations = Vec::new();
+        for _ in 0..5 {
+            configurations.push((
+                tm.to_string(),
+                String::from(tm.current_state()),
+                tm.frequencies(),
+            ));
+            tm.advance();
+        }
+
+        assert_eq!(tm.history_len(), 5);
+
+        while let Some((tape, state, frequencies)) = configurations.pop() {
+            assert!(tm.step_back());
+            assert_eq!(tm.to_string(), tape);
+            assert_eq!(tm.current_state(), state);
+            assert_eq!(tm.frequencies(), frequencies);
+        }
+
+        assert!(!tm.step_back());
+        assert_eq!(tm.previous_state(), None);
+    }
+
+    #[test]
+    /// Test that the history only keeps the last steps
+    fn step_back_bounded() {
+        let test = "
+        {11};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 0, L, q1);
+        (q1, 0, 1, L, q1);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.enable_history(2);
+
+        for _ in 0..5 {
+            tm.advance();
+        }
+
+        assert!(tm.step_back());
+        assert!(tm.step_back());
+        assert!(!tm.step_back());
+        assert_eq!(tm.frequency("q1", false), 2);
+    }
+
+    #[test]
+    /// Test that running a machine that never halts stops at the step limit
+    fn run_step_limit() {
+        let test = "
+        {1};
+        I = {q0};
+        F = {q1};
+
+        (q0, 1, 1, R, q0);
+        (q0, 0, 0, R, q0);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+
+        assert_eq!(
+            tm.run(1000),
+            ExecutionResult::StepLimitReached { steps: 1000 }
+        );
+    }
+
+    #[test]
+    /// Test that running a machine that halts returns its output
+    fn run_halted() {
+        let test = "
+        compose = {sum};
+        {111011};
+        I = {q0};
+        F = {q2};
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+
+        assert_eq!(
+            tm.run(1000),
+            ExecutionResult::Halted {
+                steps: 5,
+                output: TuringOutput::Defined((5, 3)),
+                verdict: None,
+            }
+        );
+    }
+
+    #[test]
+    /// Test that advancing the machine tells halting and getting stuck apart
+    fn advance() {
+        let test = "
+        {11};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 1, R, q1);
+        (q1, 1, 1, R, q2);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+
+        assert_eq!(tm.advance(), StepResult::Continued);
+        assert_eq!(tm.advance(), StepResult::Halted);
+
+        let test = "
+        {11};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 1, R, q1);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+
+        assert_eq!(tm.advance(), StepResult::Continued);
+        assert_eq!(
+            tm.advance(),
+            StepResult::Undefined {
+                state: String::from("q1"),
+                value: true
+            }
+        );
+    }
+
+    #[test]
+    /// Test that the observer is called once per step, including the implicit halt instruction
+    fn final_result_with() {
+        let test = "
+        {1011};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 1, R, q1);
+        (q1, 0, 0, R, q2);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        let mut fired = Vec::new();
+
+        let output = tm.final_result_with(|instruction, position, state| {
+            fired.push((instruction.to_string(), position, String::from(state)));
+        });
+
+        assert_eq!(output, TuringOutput::Defined((3, 3)));
+        assert_eq!(
+            fired,
+            vec![
+                (String::from("(q0, 1, 1, R, q1)"), 4, String::from("q1")),
+                (String::from("(q1, 0, 0, R, q2)"), 5, String::from("q2")),
+                (String::from("(q2, 1, 1, H, q2)"), 5, String::from("q2")),
+            ]
+        );
+    }
+
+    #[test]
+    /// Test that the final result of a machine that never halts is undefined instead of hanging
+    fn final_result_loop() {
+        let test = "
+        {1};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 1, R, q1);
+        (q0, 0, 0, R, q1);
+        (q1, 0, 0, L, q0);
+        (q1, 1, 1, L, q0);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.loop_threshold = 100;
+
+        assert_eq!(tm.final_result(), TuringOutput::Undefined(201));
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+
+        assert_eq!(
+            tm.final_result(),
+            TuringOutput::Undefined(2 * crate::DEFAULT_LOOP_THRESHOLD + 1)
+        );
+    }
+
+    #[test]
+    /// Test that the final result of a machine that gets stuck tells where, with the steps executed before getting stuck
+    fn final_result_stuck() {
+        let test = "
+        {11};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 1, R, q0);
+        (q0, 0, 0, R, q1);
+        (q1, 1, 1, R, q2);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+
+        let stuck = TuringOutput::Stuck {
+            steps: 3,
+            state: String::from("q1"),
+            value: false,
+        };
+
+        assert_eq!(tm.final_result(), stuck);
+        assert_eq!(tm.tape_value(), stuck);
+        assert_eq!(tm.current_state(), "q1");
+        assert!(tm.is_undefined());
+        assert!(!stuck.is_defined());
+        assert_eq!(stuck.steps(), 3);
+        assert_eq!(stuck.to_string(), "Undefined (stuck in q1 reading 0)");
+
+        tm.reset();
+        assert_eq!(tm.run(100).output(), stuck);
+    }
+
+    #[test]
+    /// Test that executing several steps at once behaves like executing them one by one
+    fn step_n() {
+        let test = "
+        compose = {sum};
+        {111011};
+        I = {q0};
+        F = {q2};
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        let TuringOutput::Defined((steps, _)) = tm.final_result() else {
+            panic!("The machine should halt");
+        };
+
+        let (mut fast, _) = TuringMachine::new(test).unwrap();
+        assert_eq!(fast.step_n(1000), steps);
+        assert_eq!(fast.frequencies(), tm.frequencies());
+        assert_eq!(fast.to_string(), tm.to_string());
+
+        let (mut slow, _) = TuringMachine::new(test).unwrap();
+        for _ in 0..2 {
+            slow.advance();
+        }
+
+        let (mut fast, _) = TuringMachine::new(test).unwrap();
+        assert_eq!(fast.step_n(2), 2);
+        assert_eq!(fast.frequencies(), slow.frequencies());
+    }
+
+    #[test]
+    /// Test that running until a predicate holds stops right after the step that satisfies it
+    fn run_until() {
+        let test = "
+        compose = {sum};
+        {111011};
+        I = {q0};
+        F = {q2};
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+
+        assert_eq!(
+            tm.run_until(|tm| tm.values() == vec![1, 1], 100),
+            ExecutionResult::PredicateMet { steps: 1 }
+        );
+        assert_eq!(
+            tm.run_until(|tm| tm.current_state() == "q2", 100),
+            ExecutionResult::PredicateMet { steps: 3 }
+        );
+        assert!(matches!(
+            tm.run_until(|_| false, 100),
+            ExecutionResult::Halted { steps: 1, .. }
+        ));
+        assert_eq!(
+            tm.run_until(|_| true, 0),
+            ExecutionResult::StepLimitReached { steps: 0 }
+        );
+    }
+
+    #[test]
+    /// Test that a repeated configuration is detected as an infinite loop right away
+    fn detect_cycles() {
+        let test = "
+        {1};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 1, R, q1);
+        (q1, 0, 0, L, q0);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.detect_cycles(true);
+
+        assert_eq!(tm.run(1000), ExecutionResult::InfiniteLoop { steps: 2 });
+        assert!(tm.is_cycle_detected());
+        assert!(!tm.is_infinite_loop(1));
+
+        tm.reset();
+        assert!(!tm.is_cycle_detected());
+
+        tm.detect_cycles(false);
+        assert_eq!(
+            tm.run(1000),
+            ExecutionResult::StepLimitReached { steps: 1000 }
+        );
+    }
+
+    #[test]
+    /// Test that a machine that keeps changing the tape is not detected as an infinite loop
+    fn detect_cycles_growing() {
+        let test = "
+        {1};
+        I = {q0};
+        F = {q1};
+
+        (q0, 1, 1, R, q0);
+        (q0, 0, 1, R, q0);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.detect_cycles(true);
+
+        assert_eq!(
+            tm.run(100),
+            ExecutionResult::StepLimitReached { steps: 100 }
+        );
+    }
+
+    #[test]
+    /// Test that alternating the value read in the same state does not look like an infinite loop
+    fn frequencies_per_value() {
+        let test = "
+        {101};
+        I = {q1};
+        F = {q2};
+
+        (q1, 1, 1, R, q1);
+        (q1, 0, 0, R, q1);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.step_n(4);
+
+        assert!(!tm.is_infinite_loop(2));
+        assert!(tm.is_infinite_loop(1));
+        assert!(!tm.is_probably_looping());
+
+        let mut frequencies: Vec<_> = tm.state_frequencies().collect();
+        frequencies.sort();
+        assert_eq!(frequencies, vec![("q1", false, 2), ("q1", true, 2)]);
+    }
+
+    #[test]
+    /// Test that the output reports the number of steps executed so far
+    fn tape_value_steps() {
+        let test = "
+        compose = {sum};
+        {111011};
+        I = {q0};
+        F = {q2};
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        assert_eq!(tm.tape_value(), TuringOutput::Defined((0, 5)));
+
+        for _ in 0..3 {
+            tm.advance();
+        }
+        assert_eq!(tm.tape_value(), TuringOutput::Defined((3, 4)));
+
+        assert_eq!(tm.final_result(), TuringOutput::Defined((5, 3)));
+
+        tm.reset();
+        assert_eq!(tm.steps, 0);
+    }
+
+    #[test]
+    /// Test that a machine serialized to JSON and back runs exactly like the original
+    fn serde_round_trip() {
+        let test = "
+        compose = {sum};
+        {111011};
+        I = {q0};
+        F = {q2};
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.advance();
+
+        let json = serde_json::to_string(&tm).unwrap();
+        let mut deserialized: TuringMachine = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized.to_string(), tm.to_string());
+        assert_eq!(deserialized.instructions().len(), tm.instructions().len());
+        assert_eq!(deserialized.final_result(), tm.final_result());
+        assert_eq!(deserialized.to_string(), tm.to_string());
+
+        deserialized.reset();
+        tm.reset();
+        assert_eq!(deserialized.to_string(), tm.to_string());
+        assert_eq!(
+            serde_json::to_string(&deserialized).unwrap(),
+            serde_json::to_string(&tm).unwrap()
+        );
+    }
+
+    #[test]
+    /// Test that a serialized machine with its head, origin or states out of range cannot be deserialized
+    fn serde_invalid_machine() {
+        let (tm, _) = TuringMachine::new("compose = {sum}; {111011}; I = {q0}; F = {q2};").unwrap();
+        let value = serde_json::to_value(&tm).unwrap();
+        assert!(serde_json::from_value::<TuringMachine>(value.clone()).is_ok());
+
+        // The snapshot of a machine that was just compiled, without padding at the right, can be restored
+        let mut restored = tm.clone();
+        restored.restore(tm.snapshot()).unwrap();
+
+        for (field, invalid) in [
+            ("tape_position", serde_json::json!(999)),
+            ("tape_position", serde_json::json!(0)),
+            ("origin", serde_json::json!(999)),
+            ("initial_tape_position", serde_json::json!(999)),
+            ("current_state", serde_json::json!(999)),
+            ("previous_state", serde_json::json!(999)),
+            ("program.initial_state", serde_json::json!(999)),
+        ] {
+            let mut corrupted = value.clone();
+            match field.strip_prefix("program.") {
+                Some(field) => corrupted["program"][field] = invalid,
+                None => corrupted[field] = invalid,
+            }
+
+            assert!(
+                serde_json::from_value::<TuringMachine>(corrupted).is_err(),
+                "{} was accepted",
+                field
+            );
+        }
+    }
+
+    #[test]
+    /// Test that machines compiled from differently formatted code are equal,
+    /// and that they stop being equal when their configuration changes
+    fn machine_equality() {
+        let a = "
+        compose = {sum};
+        {111011};
+        I = {q0};
+        F = {q2};
+        ";
+        let b = "/// The same machine
+        {111011}; I = {q0};   F = {q2};
+
+
+           compose = { sum };";
+
+        let (mut tm_a, _) = TuringMachine::new(a).unwrap();
+        let (mut tm_b, _) = TuringMachine::new(b).unwrap();
+
+        assert_eq!(tm_a, tm_b);
+
+        tm_a.advance();
+        assert_ne!(tm_a, tm_b);
+
+        tm_b.advance();
+        assert_eq!(tm_a, tm_b);
+
+        let (tm_c, _) = TuringMachine::new(&a.replace("{111011}", "{11011}")).unwrap();
+        assert_ne!(TuringMachine::new(a).unwrap().0, tm_c);
+    }
+
+    #[test]
+    /// Test that the head offset is relative to the parsed tape even when the tape grows leftwards
+    fn head_offset_left_growth() {
+        let test = "
+        {11};
+        I = {q0};
+        F = {q3};
+
+        (q0, 1, 1, L, q0);
+        (q0, 0, 1, L, q1);
+        (q1, 0, 1, L, q2);
+        (q2, 0, 0, H, q3);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        assert_eq!(tm.head_offset(), 0);
+
+        tm.advance();
+        assert_eq!(tm.head_offset(), -1);
+
+        let origin = tm.origin();
+        let offsets: Vec<isize> = (0..3)
+            .map(|_| {
+                tm.advance();
+                tm.head_offset()
+            })
+            .collect();
+
+        assert_eq!(offsets, vec![-2, -3, -3]);
+        assert!(tm.origin() > origin);
+        assert_eq!(
+            tm.tape_slice(tm.origin() - 2..tm.origin() + 2),
+            &[true, true, true, true]
+        );
+    }
+
+    #[test]
+    /// Test that a long run growing the tape leftwards does not need to shift the whole tape on every step
+    fn long_left_scan() {
+        let test = "
+        {1};
+        I = {q0};
+        F = {q1};
+
+        (q0, 1, 1, L, q0);
+        (q0, 0, 1, L, q0);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        let steps = 200_000;
+
+        assert_eq!(tm.run(steps), ExecutionResult::StepLimitReached { steps });
+        assert_eq!(tm.head_offset(), -(steps as isize));
+        assert_eq!(tm.tape().filter(|v| *v).count(), steps);
+        assert_eq!(tm.tape_vec().len(), tm.tape_len());
+    }
+
+    #[test]
+    /// Test that a long run gives the expected result and reports the state names it went through
+    fn long_run_states() {
+        let n = 50_000;
+        let test = format!(
+            "
+        {{{}}};
+        I = {{q0}};
+        F = {{q2}};
+
+        (q0, 1, 1, R, q0);
+        (q0, 0, 0, L, q1);
+        (q1, 1, 1, L, q1);
+        (q1, 0, 0, H, q2);
+        ",
+            "1".repeat(n)
+        );
+
+        let (mut tm, _) = TuringMachine::new(&test).unwrap();
+        tm.loop_threshold = usize::MAX;
+
+        assert_eq!(tm.initial_state(), "q0");
+        assert_eq!(
+            tm.final_result(),
+            TuringOutput::Defined((2 * n + 3, n as u32))
+        );
+        assert_eq!(tm.current_state(), "q2");
+        assert_eq!(tm.previous_state(), Some("q2"));
+
+        let mut frequencies: Vec<(&str, bool, usize)> = tm.state_frequencies().collect();
+        frequencies.sort();
+        assert_eq!(
+            frequencies,
+            vec![
+                ("q0", false, 1),
+                ("q0", true, n),
+                ("q1", false, 1),
+                ("q1", true, n),
+                ("q2", false, 1)
+            ]
+        );
+    }
+
+    /// A machine that appends a 1 to its input, which only works if the head starts on the last cell
+    const APPEND_ONE: &str = "
+        (q0, 1, 1, R, q1);
+        (q1, 0, 1, H, q2);
+        ";
+
+    #[test]
+    /// Test a machine that needs the head to start at the right end of the tape
+    fn initial_head_marker() {
+        let (mut tm, _) =
+            TuringMachine::new(&format!("{{111[1]}}; I = {{q0}}; F = {{q2}}; {APPEND_ONE}"))
+                .unwrap();
+
+        assert_eq!(tm.head_offset(), 3);
+        assert_eq!(tm.final_result(), TuringOutput::Defined((3, 5)));
+
+        tm.reset();
+        assert_eq!(tm.head_offset(), 3);
+
+        let (mut tm, _) =
+            TuringMachine::new(&format!("{{1111}}; I = {{q0}}; F = {{q2}}; {APPEND_ONE}")).unwrap();
+        assert!(matches!(tm.run(10), ExecutionResult::Stuck { .. }));
+    }
+
+    #[test]
+    /// Test placing the head programmatically before the first step
+    fn set_head_position() {
+        let (mut tm, _) =
+            TuringMachine::new(&format!("{{1111}}; I = {{q0}}; F = {{q2}}; {APPEND_ONE}")).unwrap();
+
+        assert_eq!(
+            tm.set_head_position(4),
+            Err(TapeError::HeadOutOfRange { offset: 4, len: 4 })
+        );
+        assert_eq!(
+            tm.set_head_position(-1),
+            Err(TapeError::HeadOutOfRange { offset: -1, len: 4 })
+        );
+
+        tm.set_head_position(3).unwrap();
+        assert_eq!(tm.final_result(), TuringOutput::Defined((3, 5)));
+
+        tm.reset();
+        assert_eq!(tm.head_offset(), 3);
+        assert_eq!(tm.final_result(), TuringOutput::Defined((3, 5)));
+    }
+
+    #[test]
+    /// Test that moving left of the first cell of a left-bounded tape is an error
+    fn left_bounded_error() {
+        let test = "
+        tape = bounded;
+        {11};
+        I = {q0};
+        F = {q1};
+
+        (q0, 1, 1, L, q0);
+        (q0, 0, 0, H, q1);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        assert_eq!(tm.tape_mode, TapeMode::LeftBounded);
+
+        assert_eq!(
+            tm.run(10),
+            ExecutionResult::OutOfTape {
+                state: String::from("q0"),
+                value: true,
+                steps: 0
+            }
+        );
+        assert_eq!(tm.head_offset(), 0);
+
+        tm.tape_mode = TapeMode::Unbounded;
+        assert_eq!(tm.final_result(), TuringOutput::Defined((3, 2)));
+    }
+
+    #[test]
+    /// Test that a machine that never moves left of its input behaves the same in both tape modes
+    fn left_bounded_same() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut unbounded, _) = TuringMachine::new(&unparsed_file).unwrap();
+        let (mut bounded, _) = TuringMachine::new(&unparsed_file).unwrap();
+        bounded.tape_mode = TapeMode::LeftBounded;
+
+        assert_eq!(bounded.final_result(), unbounded.final_result());
+        assert_eq!(bounded.to_string(), unbounded.to_string());
+    }
+
+    /// Copies the first tape into the second one
+    const TWO_TAPE_COPY: &str = "
+        tapes = 2;
+        {111};
+        I = {q0};
+        F = {q1};
+
+        (q0, [1, 0], [1, 1], [R, R], q0);
+        (q0, [0, 0], [0, 0], [L, L], q1);
+    ";
+
+    #[test]
+    /// Test a two-tape machine copying its first tape into the second one
+    fn two_tape_copy() {
+        let (mut tm, warnings) = MultiTapeMachine::new(TWO_TAPE_COPY).unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(tm.tape_count(), 2);
+        assert_eq!(tm.values(0), vec![2]);
+        assert!(tm.values(1).is_empty());
+        assert_eq!(tm.read(), vec![true, false]);
+
+        assert_eq!(tm.advance(), MultiTapeStep::Continued);
+        assert_eq!(tm.read(), vec![true, false]);
+
+        // The remaining copy steps, the step into the final state and the halting one
+        assert_eq!(tm.run(usize::MAX), MultiTapeResult::Halted { steps: 4 });
+        assert_eq!(tm.steps, 5);
+        assert_eq!(tm.values(0), vec![2]);
+        assert_eq!(tm.values(1), vec![2]);
+        assert_eq!(tm.head_offset(0), Some(2));
+        assert_eq!(tm.head_offset(1), Some(2));
+        assert_eq!(
+            tm.to_string(),
+            format!(
+                "{}\n\n{}",
+                tm.tape_to_string(0).unwrap(),
+                tm.tape_to_string(1).unwrap()
+            )
+        );
+        assert_eq!(
+            tm.tape_to_string(1).unwrap(),
+            "0 0 0 1 1 1 0 0 0 0 \n          ^         "
+        );
+
+        tm.reset();
+        assert!(tm.values(1).is_empty());
+        assert_eq!(tm.run(3), MultiTapeResult::StepLimitReached { steps: 3 });
+    }
+
+    #[test]
+    /// Test that a multi-tape machine gets stuck when no instruction matches all the values read
+    fn multi_tape_stuck() {
+        let code = "
+            tapes = 3;
+            {11};
+            {};
+            {[1]};
+            I = {q0};
+            F = {q1};
+
+            (q0, [1, 0, 1], [1, 0, 0], [R, H, L], q0);
+        ";
+        let (mut tm, _) = MultiTapeMachine::new(code).unwrap();
+
+        assert_eq!(
+            tm.run(10),
+            MultiTapeResult::Stuck {
+                state: String::from("q0"),
+                values: vec![true, false, false],
+                steps: 1
+            }
+        );
+        assert_eq!(tm.head_offset(2), Some(-1));
+    }
+
+    /// Guesses which 1 is the last one of the tape, and verifies that a blank cell follows it
+    const GUESS_LAST_ONE: &str = "
+        {1111};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 1, R, q0);
+        (q0, 1, 1, R, q1);
+        (q1, 0, 0, H, q2);
+    ";
+
+    #[test]
+    /// Test that exploring a non-deterministic machine finds the accepting guess that the deterministic engine misses
+    fn explore_guess_and_verify() {
+        let (mut tm, warnings) = TuringMachine::new_nondeterministic(GUESS_LAST_ONE).unwrap();
+
+        assert!(warnings.is_empty());
+        assert!(tm.is_nondeterministic());
+        assert_eq!(
+            tm.choices().unwrap()[&InstructionKey::new("q0", true)].len(),
+            2
+        );
+
+        let exploration = tm.explore(10);
+        assert!(exploration.accepted);
+        assert!(!exploration.truncated);
+
+        let path: Vec<String> = exploration.path.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            path,
+            vec![
+                "(q0, 1, 1, R, q0)",
+                "(q0, 1, 1, R, q0)",
+                "(q0, 1, 1, R, q0)",
+                "(q0, 1, 1, R, q1)",
+                "(q1, 0, 0, H, q2)"
+            ]
+        );
+
+        // Exploring does not change the machine, which deterministically guesses the first 1
+        assert!(matches!(tm.run(100), ExecutionResult::Stuck { .. }));
+
+        // Too shallow to reach the final state
+        tm.reset();
+        let exploration = tm.explore(4);
+        assert!(!exploration.accepted);
+        assert!(exploration.truncated);
+        assert!(exploration.path.is_empty());
+
+        // The choices are kept when serializing
+        let json = serde_json::to_string(&tm).unwrap();
+        let restored: TuringMachine = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.choices(), tm.choices());
+    }
+
+    #[test]
+    /// Test that deterministic machines keep overwriting instructions, and are explored along their only branch
+    fn explore_deterministic() {
+        let (mut tm, warnings) = TuringMachine::new(GUESS_LAST_ONE).unwrap();
+
+        assert_eq!(warnings.len(), 1);
+        assert!(!tm.is_nondeterministic());
+        assert!(tm.choices().is_none());
+
+        assert_eq!(
+            tm.explore(10),
+            Exploration {
+                accepted: false,
+                path: Vec::new(),
+                configurations: 2,
+                truncated: false
+            }
+        );
+        assert!(matches!(
+            tm.run(100),
+            ExecutionResult::Stuck { steps: 1, .. }
+        ));
+    }
+
+    /// Accepts the tapes with some 0s followed by as many 1s, crossing them out as X and Y
+    const ZEROS_THEN_ONES: &str = "
+        alphabet = {B, 0, 1, X, Y};
+        mark = Y;
+        {0011};
+        I = {q0};
+        F = {q4};
+
+        (q0, 0, X, R, q1);
+        (q0, Y, Y, R, q3);
+        (q1, 0, 0, R, q1);
+        (q1, Y, Y, R, q1);
+        (q1, 1, Y, L, q2);
+        (q2, 0, 0, L, q2);
+        (q2, Y, Y, L, q2);
+        (q2, X, X, R, q0);
+        (q3, Y, Y, R, q3);
+        (q3, B, B, H, q4);
+    ";
+
+    #[test]
+    /// Test a machine over an alphabet with more symbols than 0 and 1
+    fn symbol_machine() {
+        let (mut tm, warnings) = TuringMachine::new_with_alphabet(ZEROS_THEN_ONES).unwrap();
+
+        assert!(warnings.is_empty());
+        assert_eq!(tm.alphabet.len(), 5);
+        assert_eq!(tm.alphabet.name(tm.alphabet.blank()), "B");
+        assert_eq!(tm.alphabet.name(tm.alphabet.mark()), "Y");
+        assert_eq!(tm.to_string(), "B B B 0 0 1 1 \n      ^       ");
+
+        assert!(matches!(tm.run(100), SymbolResult::Halted { .. }));
+        assert_eq!(
+            tm.to_string(),
+            "B B B X X Y Y B B B B \n              ^       "
+        );
+        assert_eq!(tm.tape_value(), TuringOutput::Defined((tm.steps, 2)));
+        assert_eq!(tm.values(), vec![1]);
+
+        let code = ZEROS_THEN_ONES.replace("{0011}", "{001}");
+        let (mut tm, _) = SymbolMachine::new(&code).unwrap();
+        assert!(matches!(
+            tm.run(100),
+            SymbolResult::Stuck { state, .. } if state == "q1"
+        ));
+        let steps = tm.steps;
+        assert_eq!(tm.final_result(100), TuringOutput::Undefined(steps));
+    }
+
+    #[test]
+    /// Test that the code of a binary machine compiles to a machine over the default alphabet that runs the same
+    fn symbol_machine_binary() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut binary, _) = TuringMachine::new(&unparsed_file).unwrap();
+        let (mut symbols, _) = SymbolMachine::new(&unparsed_file).unwrap();
+
+        assert_eq!(symbols.alphabet.symbols(), ["0", "1"]);
+        assert_eq!(symbols.to_string(), binary.to_string());
+        assert_eq!(symbols.values(), binary.values());
+
+        assert_eq!(symbols.final_result(usize::MAX), binary.final_result());
+        assert_eq!(symbols.to_string(), binary.to_string());
+
+        symbols.reset();
+        binary.reset();
+        assert_eq!(symbols.to_string(), binary.to_string());
+    }
+
+    #[test]
+    /// Test the values of a tape, with and without the n + 1 ones convention
+    fn values_raw() {
+        let (mut tm, _) = TuringMachine::new("{0110110}; I = {q0}; F = {q0};").unwrap();
+        assert_eq!(tm.values_raw(), vec![2, 2]);
+        assert_eq!(tm.values(), vec![1, 1]);
+
+        // The blank cells padded while running are not counted
+        tm.run(10);
+        assert_eq!(tm.values_raw(), vec![2, 2]);
+
+        let (tm, _) = TuringMachine::new("{010}; I = {q0}; F = {q0};").unwrap();
+        assert_eq!(tm.values_raw(), vec![1]);
+        assert_eq!(tm.values(), vec![0]);
+    }
+
+    #[test]
+    /// Test the next instruction of a final state with and without an explicit halting instruction
+    #[allow(deprecated)]
+    fn next_instruction() {
+        let (mut tm, _) = TuringMachine::new(
+            "{11}; I = {q0}; F = {q1};
+
+            (q0, 1, 1, R, q0);
+            (q0, 0, 0, L, q1);
+            (q1, 1, 0, H, q1);",
+        )
+        .unwrap();
+
+        let next = tm.next_instruction().unwrap();
+        assert!(!next.is_implicit_halt());
+        assert_eq!(next.instruction().to_string(), "(q0, 1, 1, R, q0)");
+        assert!(!tm.would_halt());
+
+        assert_eq!(tm.run_until(|tm| tm.finished(), 10).steps(), 3);
+
+        // The explicit halting instruction of the final state
+        let next = tm.next_instruction().unwrap();
+        assert_eq!(
+            next,
+            ResolvedInstruction::Explicit(
+                tm.instructions()[&InstructionKey::new("q1", true)].clone()
+            )
+        );
+        assert!(tm.would_halt());
+        assert_eq!(
+            tm.get_current_instruction(),
+            Some(next.instruction().clone())
+        );
+
+        let (mut tm, _) =
+            TuringMachine::new("{11}; I = {q0}; F = {q1}; (q0, 1, 1, R, q1);").unwrap();
+        tm.advance();
+
+        // The implicit halt of the final state, which the old lookup did not give
+        let next = tm.next_instruction().unwrap();
+        assert!(next.is_implicit_halt());
+        assert_eq!(next.instruction().to_string(), "(q1, 1, 1, H, q1)");
+        assert!(tm.would_halt());
+        assert_eq!(tm.get_current_instruction(), None);
+
+        let (tm, _) = TuringMachine::new("{1}; I = {q0}; F = {q1}; (q0, 0, 0, R, q1);").unwrap();
+        assert_eq!(tm.next_instruction(), None);
+        assert!(!tm.would_halt());
+    }
+
+    #[test]
+    /// Test cancelling a machine that never halts from another thread, and stepping it afterwards
+    fn run_with_cancel() {
+        let (mut tm, _) =
+            TuringMachine::new("{1}; I = {q0}; F = {qf}; (q0, 1, 1, R, q1); (q1, 0, 0, L, q0);")
+                .unwrap();
+
+        let cancel = AtomicBool::new(true);
+        assert_eq!(
+            tm.run_with_cancel(10, &cancel),
+            ExecutionResult::Cancelled { steps: 0 }
+        );
+        assert_eq!(tm.steps, 0);
+
+        cancel.store(false, Ordering::Relaxed);
+        let result = thread::scope(|s| {
+            s.spawn(|| {
+                thread::sleep(Duration::from_millis(50));
+                cancel.store(true, Ordering::Relaxed);
+            });
+
+            tm.run_with_cancel(usize::MAX, &cancel)
+        });
+
+        let ExecutionResult::Cancelled { steps } = result else {
+            panic!("The machine was not cancelled: {:?}", result);
+        };
+        assert!(steps > 0);
+        assert_eq!(tm.steps, steps);
+
+        // The machine is left between two steps, so it can go on
+        let state = if steps % 2 == 0 { "q0" } else { "q1" };
+        assert_eq!(tm.current_state(), state);
+        assert_eq!(tm.advance(), StepResult::Continued);
+        assert_eq!(tm.steps, steps + 1);
+        assert_eq!(tm.run(10), ExecutionResult::StepLimitReached { steps: 10 });
+    }
+
+    #[test]
+    /// Test a machine that accepts the blocks of 1s of even length and rejects the odd ones
+    fn accept_reject() {
+        let decider = |tape: &str| {
+            format!(
+                "{{{}}}; I = {{e}}; A = {{yes}}; R = {{no}};
+                (e, 1, 1, R, o); (o, 1, 1, R, e); (e, 0, 0, H, yes); (o, 0, 0, H, no);",
+                tape
+            )
+        };
+
+        let (mut tm, _) = TuringMachine::new(&decider("1111")).unwrap();
+        assert_eq!(
+            tm.final_states(),
+            &[String::from("yes"), String::from("no")]
+        );
+        assert_eq!(tm.verdict(), None);
+
+        let result = tm.run(100);
+        assert_eq!(result.verdict(), Some(Verdict::Accept));
+        assert_eq!(tm.verdict(), Some(Verdict::Accept));
+
+        let (mut tm, _) = TuringMachine::new(&decider("111")).unwrap();
+        assert_eq!(tm.run(100).verdict(), Some(Verdict::Reject));
+
+        // The states given with `F` halt without a verdict
+        let (mut tm, _) = TuringMachine::new("{1}; I = {q0}; F = {q0}; A = {q1};").unwrap();
+        assert!(matches!(
+            tm.run(100),
+            ExecutionResult::Halted { verdict: None, .. }
+        ));
+
+        let error = TuringMachine::new("{1}; I = {q0}; A = {q0}; R = {q0};").unwrap_err();
+        assert_eq!(
+            error.message(),
+            "The state q0 cannot both accept and reject"
+        );
+
+        // The kinds of final states survive writing the code
+        let (built, _) = TuringMachineBuilder::new()
+            .tape("11")
+            .initial("e")
+            .accepting(["yes"])
+            .rejecting(["no"])
+            .instr("e", 1, 1, Movement::RIGHT, "o")
+            .instr("o", 1, 1, Movement::RIGHT, "e")
+            .instr("e", 0, 0, Movement::HALT, "yes")
+            .instr("o", 0, 0, Movement::HALT, "no")
+            .build()
+            .unwrap();
+        let (mut tm, _) = TuringMachine::new(built.code()).unwrap();
+        assert_eq!(tm.rejecting_states(), &[String::from("no")]);
+        assert_eq!(tm.run(100).verdict(), Some(Verdict::Accept));
+    }
+
+    #[test]
+    /// Test that the number of 1s kept by the tape matches the 1s on it after writing, undoing and growing
+    fn ones_count() {
+        // Flips the cells it goes through, and never halts
+        let (mut tm, _) = TuringMachine::new(
+            "{1}; I = {q0}; F = {qf};
+            (q0, 0, 1, R, q1); (q0, 1, 0, R, q1); (q1, 0, 1, L, q0); (q1, 1, 0, R, q0);",
+        )
+        .unwrap();
+        let scan = |tm: &TuringMachine| tm.tape().filter(|v| *v).count();
+
+        tm.enable_history(100);
+        assert_eq!(
+            tm.run(10_000),
+            ExecutionResult::StepLimitReached { steps: 10_000 }
+        );
+        assert_eq!(tm.tape.ones(), scan(&tm));
+
+        for _ in 0..100 {
+            tm.step_back();
+        }
+        assert_eq!(tm.tape.ones(), scan(&tm));
+
+        tm.write_cell(0, true).unwrap();
+        assert_eq!(tm.tape.ones(), scan(&tm));
+
+        tm.set_input_values(&[3, 4]).unwrap();
+        assert_eq!(tm.tape.ones(), 9);
+        tm.run(10_000);
+        tm.reset();
+        assert_eq!(tm.tape.ones(), 9);
+        assert_eq!(tm.tape_value(), TuringOutput::Defined((0, 9)));
+    }
+
+    #[test]
+    /// Test that the most frequent pair kept while running matches the frequencies after a long run
+    fn most_frequent_state() {
+        let code = format!(
+            "compose = {{mod}}; {{{}0{}}}; I = {{q0}}; F = {{qf}};",
+            "1".repeat(301),
+            "1".repeat(8)
+        );
+        let (mut tm, _) = TuringMachine::new(&code).unwrap();
+        assert_eq!(tm.most_frequent_state(), None);
+
+        let recomputed = |tm: &TuringMachine| tm.state_frequencies().map(|(_, _, f)| f).max();
+
+        tm.enable_history(1000);
+        tm.run(100_000);
+        let (state, frequency) = tm.most_frequent_state().unwrap();
+        assert_eq!(Some(frequency), recomputed(&tm));
+        assert!(tm
+            .state_frequencies()
+            .any(|(s, _, f)| s == state && f == frequency));
+        assert!(tm.is_infinite_loop(frequency - 1));
+        assert!(!tm.is_infinite_loop(frequency));
+
+        for _ in 0..1000 {
+            tm.step_back();
+        }
+        assert_eq!(tm.most_frequent_state().map(|(_, f)| f), recomputed(&tm));
+
+        tm.reset();
+        assert_eq!(tm.most_frequent_state(), None);
+    }
+
+    #[test]
+    /// Test that fast-forwarding the scanning loops of the `mod` library on a large input
+    /// gives the same steps, tape and frequencies as executing them step by step
+    fn fast_forward() {
+        let code = format!(
+            "compose = {{mod}}; {{{}0{}}}; I = {{q0}}; F = {{qf}};",
+            "1".repeat(2001),
+            "1".repeat(13)
+        );
+
+        let (mut plain, _) = TuringMachine::new(&code).unwrap();
+        let mut fast = plain.clone();
+        fast.enable_fast_forward();
+
+        for max_steps in [1, 2, 1000, 123_457, usize::MAX] {
+            plain.reset();
+            fast.reset();
+
+            assert_eq!(fast.run(max_steps), plain.run(max_steps), "{}", max_steps);
+            assert_eq!(fast.steps, plain.steps);
+            assert_eq!(fast.tape_vec(), plain.tape_vec());
+            assert_eq!(fast.tape_position, plain.tape_position);
+            assert_eq!(fast.origin(), plain.origin());
+            assert_eq!(fast.frequencies(), plain.frequencies());
+            assert_eq!(fast.most_frequent_state(), plain.most_frequent_state());
+        }
+        assert_eq!(fast.tape().filter(|v| *v).count(), 2000 % 12);
+
+        // The head stops at the first cell of a left-bounded tape, where the next step is an error
+        let test = "
+        {111};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 1, R, q0);
+        (q0, 0, 0, L, q1);
+        (q1, 1, 1, L, q1);
+        ";
+        let (mut plain, _) = TuringMachine::new(test).unwrap();
+        plain.tape_mode = TapeMode::LeftBounded;
+        let mut fast = plain.clone();
+        fast.enable_fast_forward();
+
+        let expected = ExecutionResult::OutOfTape {
+            state: String::from("q1"),
+            value: true,
+            steps: 6,
+        };
+        assert_eq!(plain.run(100), expected);
+        assert_eq!(fast.run(100), expected);
+        assert_eq!(fast.steps, plain.steps);
+        assert_eq!(fast.tape_vec(), plain.tape_vec());
+    }
+
+    #[test]
+    /// Test that a machine running right forever stops when its tape reaches the limit,
+    /// without counting the blank cells kept around the head
+    fn tape_limit() {
+        let test = "
+        {1};
+        I = {q0};
+        F = {q1};
+
+        (q0, 1, 1, R, q0);
+        (q0, 0, 0, R, q0);
+        ";
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        assert_eq!(tm.tape_limit(), None);
+        assert_eq!(tm.used_cells(), 1);
+
+        tm.set_tape_limit(1000);
+        let mut fast = tm.clone();
+        fast.enable_fast_forward();
+
+        let expected = ExecutionResult::TapeLimitExceeded {
+            cells: 1000,
+            steps: 999,
+        };
+        assert_eq!(tm.run(usize::MAX), expected);
+        assert_eq!(fast.run(usize::MAX), expected);
+        assert_eq!(fast.tape_vec(), tm.tape_vec());
+
+        assert_eq!(tm.used_cells(), 1000);
+        assert_eq!(tm.head_offset(), 999);
+        assert!(tm.tape_len() <= 1000 + 6);
+        assert_eq!(tm.advance(), StepResult::TapeLimitExceeded { cells: 1000 });
+
+        tm.remove_tape_limit();
+        assert_eq!(tm.run(10), ExecutionResult::StepLimitReached { steps: 10 });
+        assert_eq!(tm.used_cells(), 1010);
+    }
+
+    #[test]
+    /// Test that the clones of a machine share its program, so cloning is cheap, but not their tapes
+    fn clone_shares_program() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        let start = Instant::now();
+        let mut clones: Vec<TuringMachine> = (0..10_000).map(|_| tm.clone()).collect();
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        assert!(clones
+            .iter()
+            .all(|c| Arc::ptr_eq(&c.program, &tm.program) && c.code() == tm.code()));
+
+        clones[0].write_cell(tm.tape_position, false).unwrap();
+        clones[0].run(100);
+        assert_eq!(clones[1], tm);
+        assert_eq!(clones[1].steps, 0);
+        assert_ne!(clones[0].tape_vec(), clones[1].tape_vec());
+
+        assert_eq!(clones[1].final_result(), tm.clone().final_result());
+    }
+
+    #[test]
+    /// Test that a million steps over 3 states are counted without growing the frequencies
+    fn frequencies_by_state() {
+        let code = "{1}; I = {a}; F = {f}; (a, 1, 1, H, b); (b, 1, 1, H, c); (c, 1, 1, H, a);";
+        let (mut tm, _) = TuringMachine::new(code).unwrap();
+        let capacity = tm.frequencies.capacity();
+
+        tm.run(1_000_000);
+        assert_eq!(tm.steps, 1_000_000);
+        assert_eq!(tm.frequency("a", true), 333_334);
+        assert_eq!(tm.frequency("b", true), 333_333);
+        assert_eq!(tm.total_steps_in_state("c"), 333_333);
+        assert_eq!(tm.total_steps_in_state("f"), 0);
+        assert_eq!(tm.frequency("unknown", true), 0);
+        assert_eq!(tm.most_frequent_state(), Some(("a", 333_334)));
+        assert_eq!(tm.frequencies().len(), 3);
+        assert_eq!(tm.frequencies.capacity(), capacity);
+
+        // Resetting them keeps their memory
+        tm.reset_frequencies();
+        assert_eq!(tm.state_frequencies().count(), 0);
+        assert_eq!(tm.frequencies.capacity(), capacity);
+        assert_eq!(tm.most_frequent_state(), None);
+    }
+
+    #[test]
+    /// Test a machine whose blank is 1, which walks off its input and scans back for the 0
+    fn blank_declaration() {
+        let code = "blank = 1;\n{01};\nI = {q0};\nF = {q4};\n\n(q0, 0, 0, R, q1);\n(q1, 1, 1, R, q2);\n(q2, 1, 1, L, q3);\n(q3, 0, 0, H, q4);\n(q3, 1, 1, L, q3);\n";
+        let (mut tm, _) = TuringMachine::new(code).unwrap();
+        assert!(tm.blank);
+        assert!(tm.tape().filter(|v| !*v).eq([false]));
+        assert_eq!(format!("{:#}", tm).split_whitespace().next(), Some("_"));
+
+        assert!(matches!(tm.run(100), ExecutionResult::Halted { .. }));
+        assert_eq!(tm.current_state(), "q4");
+        assert_eq!(tm.head_offset(), 0);
+        assert!(tm.tape().filter(|v| !*v).eq([false]));
+        assert_eq!(tm.tape_trimmed(), (&[false][..], 0));
+        assert_eq!(tm.to_code(), code);
+
+        // With the default blank, the cells past the input are 0s
+        let (mut tm, _) = TuringMachine::new(&code.replace("blank = 1;\n{01}", "{[0]1}")).unwrap();
+        assert!(matches!(
+            tm.run(100),
+            ExecutionResult::Stuck { value: false, .. }
+        ));
+
+        let error = TuringMachine::new(&format!("blank = 0;\n{}", code)).unwrap_err();
+        assert_eq!(error.message(), "The blank can only be given once");
+
+        let error = TuringMachine::new(&format!(
+            "alphabet = {{0, 1}};\nblank = _;\n{}",
+            &code[11..]
+        ))
+        .unwrap_err();
+        assert_eq!(error.message(), "The symbol _ is not in the alphabet");
+        assert_eq!(error.line(), 1);
+    }
+
+    #[test]
+    /// Test a counting loop that writes without moving with `S` before moving to the next 1
+    fn stay_movement() {
+        let code = "{111};\nI = {q0};\nF = {qf};\n(q0, 1, 0, S, q1);\n(q1, 0, 1, R, q0);\n(q0, 0, 1, H, qf);\n";
+        let (mut tm, _) = TuringMachine::new(code).unwrap();
+        assert_eq!(
+            tm.instructions()[&InstructionKey::new("q0", true)].movement,
+            Movement::STAY
+        );
+
+        assert_eq!(tm.advance(), StepResult::Continued);
+        assert_eq!(tm.head_offset(), 0);
+        assert_eq!(tm.current_state(), "q1");
+        assert_eq!(tm.read_cell(tm.tape_position), Some(false));
+
+        assert!(matches!(tm.run(100), ExecutionResult::Halted { .. }));
+        assert_eq!(tm.tape_trimmed(), (&[true, true, true, true][..], 3));
+        assert_eq!(tm.current_state(), "qf");
+
+        // The Spanish letter is the same movement, written back as S
+        let (tm, _) = TuringMachine::new(&code.replace('S', "Q")).unwrap();
+        assert!(tm.to_code().contains("(q0, 1, 0, S, q1);"));
+    }
+
+    #[test]
+    /// Test a machine that halts in one of its two final states depending on its input
+    fn several_final_states() {
+        let code = |tape: &str| {
+            format!(
+                "{{{}}};\nI = {{q0}};\nF = {{qa, qb}};\n(q0, 1, 1, R, q1);\n(q1, 1, 1, H, qa);\n(q1, 0, 0, H, qb);\n",
+                tape
+            )
+        };
+
+        for (tape, state) in [("11", "qa"), ("10", "qb")] {
+            let (mut tm, _) = TuringMachine::new(&code(tape)).unwrap();
+            assert!(matches!(tm.run(10), ExecutionResult::Halted { .. }));
+            assert_eq!(tm.current_state(), state);
+            assert!(tm.is_final(state));
+        }
+    }
+
+    #[test]
+    /// Test that the 3-state busy beaver writes its 6 ones starting from a blank tape, if empty tapes are allowed
+    fn busy_beaver_from_blank_tape() {
+        let code = "{0};\nI = {a};\nF = {h};\n\
+            (a, 0, 1, R, b);\n(a, 1, 1, R, h);\n\
+            (b, 0, 0, R, c);\n(b, 1, 1, R, b);\n\
+            (c, 0, 1, L, c);\n(c, 1, 1, L, a);";
+        assert!(TuringMachine::new(code).is_err());
+
+        let options = ParserOptions {
+            allow_empty_tape: true,
+            empty_tape_cells: 4,
+            ..ParserOptions::default()
+        };
+        let (mut tm, _) = TuringMachine::new_with_options(code, &options).unwrap();
+        assert_eq!(tm.head_offset(), 0);
+        assert_eq!(tm.used_cells(), 4);
+        assert!(tm.values().is_empty());
+        assert_eq!(tm.tape_value(), TuringOutput::Defined((0, 0)));
+
+        assert_eq!(tm.final_result(), TuringOutput::Defined((15, 6)));
+        assert_eq!(tm.values_raw(), vec![6]);
+
+        // The options are kept for the new inputs, which can be blank too
+        tm.set_input_tape(&[]).unwrap();
+        assert_eq!(tm.used_cells(), 4);
+        assert_eq!(tm.final_result(), TuringOutput::Defined((15, 6)));
+        let results = tm.run_batch(&[vec![false], vec![false; 10]], 100);
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, ExecutionResult::Halted { steps: 15, .. })));
+
+        let (mut strict, _) = TuringMachine::new("{1}; I = {h}; F = {h};").unwrap();
+        assert_eq!(
+            strict.set_input_tape(&[false]).unwrap_err().message(),
+            "Expected at least a 1 in the tape"
+        );
+    }
+
+    #[test]
+    /// Test that the machines of a file with several blocks compile and run independently,
+    /// and that a file without blocks is a single unnamed machine
+    fn several_machines() {
+        let code = "/// An exercise with its solution and a test harness\n\
+            machine solution {\n    compose = {sum};\n    {11011};\n    I = {q0};\n    F = {q2};\n}\n\
+            machine harness {\n    {111};\n    I = {q0};\n    F = {q1};\n    \
+            (q0, 1, 0, R, q0);\n    (q0, 0, 0, H, q1)\n}\n";
+        let mut machines = TuringMachine::parse_all(code).unwrap();
+        assert_eq!(machines.len(), 2);
+
+        let (solution, warnings) = machines.get_mut("solution").unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(solution.final_result(), TuringOutput::Defined((4, 2)));
+
+        let (harness, _) = machines.get_mut("harness").unwrap();
+        assert!(harness.code().starts_with("{111};"));
+        assert_eq!(harness.final_result(), TuringOutput::Defined((5, 0)));
+
+        let single = TuringMachine::parse_all("{1}; I = {q0}; F = {q0};").unwrap();
+        assert_eq!(single.keys().collect::<Vec<_>>(), vec![""]);
+
+        // The warnings and the errors are placed in the whole file
+        let (_, warnings) =
+            &TuringMachine::parse_all("machine a {\n  {1}; I = {q0}; F = {q0,};\n}").unwrap()["a"];
+        assert_eq!(
+            warnings,
+            &vec![CompilerWarning::StyleWarning {
+                position: ErrorPosition::new((1, 25), Some((1, 26))),
+                message: String::from("The comma after the last element of the list is not needed"),
+            }]
+        );
+        assert!(TuringMachine::parse_all(
+            "machine a { {1}; I = {q0}; F = {q0}; }\nmachine a { {1}; I = {q0}; F = {q0}; }"
+        )
+        .is_err());
+    }
+}
+
+#[cfg(test)]
+mod test_debugging {
+    use std::fs;
+
+    use crate::program::StateId;
+    use crate::replay;
+    use crate::BinaryError;
+    use crate::ConfigKey;
+    use crate::DebugSession;
+    use crate::ExecutionResult;
+    use crate::InstructionKey;
+    use crate::Language;
+    use crate::MachineSnapshot;
+    use crate::SnapshotError;
+    use crate::StepResult;
+    use crate::TimeTravelError;
+    use crate::TuringMachine;
+    use crate::Watchpoint;
+    use crate::BINARY_VERSION;
+
+    #[test]
+    /// Test that running to a breakpoint stops every time the machine enters the state
+    fn breakpoints() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        tm.add_breakpoint("p0");
+        tm.add_breakpoint("p1");
+
+        assert_eq!(
+            tm.run_to_breakpoint(100),
+            ExecutionResult::Breakpoint {
+                state: String::from("p0"),
+                steps: 0
+            }
+        );
+        assert_eq!(
+            tm.run_to_breakpoint(100),
+            ExecutionResult::Breakpoint {
+                state: String::from("p1"),
+                steps: 1
+            }
+        );
+        assert_eq!(
+            tm.run_to_breakpoint(100),
+            ExecutionResult::Breakpoint {
+                state: String::from("p1"),
+                steps: 1
+            }
+        );
+
+        assert!(tm.remove_breakpoint("p1"));
+        assert!(!tm.remove_breakpoint("p1"));
+
+        assert!(matches!(
+            tm.run_to_breakpoint(100),
+            ExecutionResult::Halted { .. }
+        ));
+    }
+
+    #[test]
+    /// Test that clearing the breakpoints makes the machine run like `run`
+    fn clear_breakpoints() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+        let (mut expected, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        tm.add_breakpoint("p0");
+        tm.clear_breakpoints();
+
+        assert_eq!(tm.run_to_breakpoint(100), expected.run(100));
+    }
+
+    #[test]
+    /// Test that a cell watchpoint keeps pointing to the same cell when the tape grows to the left
+    fn cell_watch() {
+        let test = "
+        {1};
+        I = {q0};
+        F = {q3};
+
+        (q0, 1, 1, L, q1);
+        (q1, 0, 0, L, q2);
+        (q2, 0, 1, R, q3);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.add_cell_watch(-2);
+
+        assert_eq!(
+            tm.run_to_breakpoint(100),
+            ExecutionResult::Watchpoint {
+                watchpoint: Watchpoint::Cell(-2),
+                steps: 3
+            }
+        );
+        assert!(tm.read_cell(tm.origin() - 2).unwrap());
+        assert_eq!(tm.origin, 5);
+    }
+
+    #[test]
+    /// Test that a write watchpoint triggers when a cell changes to the given value
+    fn write_watch() {
+        let test = "
+        compose = {sum};
+        {111011};
+        I = {q0};
+        F = {q2};
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.add_write_watch(true);
+
+        assert!(matches!(
+            tm.run_to_breakpoint(100),
+            ExecutionResult::Halted { .. }
+        ));
+
+        tm.reset();
+        tm.add_write_watch(false);
+
+        assert_eq!(
+            tm.run_to_breakpoint(100),
+            ExecutionResult::Watchpoint {
+                watchpoint: Watchpoint::Write(false),
+                steps: 1
+            }
+        );
+    }
+
+    #[test]
+    /// Test that replaying the trace reconstructs the tape at every step
+    fn trace() {
+        let test = "
+        compose = {sum};
+        {111011};
+        I = {q0};
+        F = {q2};
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.enable_trace();
+        tm.final_result();
+
+        let trace = tm.trace();
+        assert_eq!(trace.len(), 5);
+        assert_eq!(trace[0].from_state, "q0");
+        assert!(trace[0].read);
+        assert!(!trace[0].written);
+        assert_eq!(trace[3].to_state, "q2");
+        assert_eq!(replay(trace, tm.trace_initial_tape()), tm.tape_vec());
+
+        for k in 0..trace.len() {
+            let (mut expected, _) = TuringMachine::new(test).unwrap();
+            expected.step_n(k);
+
+            assert_eq!(
+                replay(&trace[..k], tm.trace_initial_tape()),
+                expected.tape_vec()
+            );
+        }
+    }
+
+    #[test]
+    /// Test the statistics collected while running `Examples/Example1.tm`
+    fn stats() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        assert!(tm.stats().is_none());
+
+        tm.enable_stats();
+        tm.final_result();
+
+        let stats = tm.stats().unwrap().clone();
+        assert_eq!(stats.steps, 7);
+        assert_eq!(stats.moves_right, 6);
+        assert_eq!(stats.moves_left, 0);
+        assert_eq!(stats.head_travel(), 6);
+        assert_eq!(stats.cells_visited(), 7);
+        assert_eq!((stats.leftmost, stats.rightmost), (0, 6));
+        assert_eq!(stats.writes.len(), 7);
+        assert!(stats.writes.values().all(|w| *w == 1));
+
+        tm.reset();
+        assert_eq!(tm.stats().unwrap().steps, 0);
+
+        tm.final_result();
+        assert_eq!(tm.stats(), Some(&stats));
+    }
+
+    #[test]
+    /// Test that the statistics identify the cells correctly when the tape grows to the left
+    fn stats_left() {
+        let test = "
+        {1};
+        I = {q0};
+        F = {q3};
+
+        (q0, 1, 1, L, q1);
+        (q1, 0, 0, L, q2);
+        (q2, 0, 1, R, q3);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.enable_stats();
+        tm.final_result();
+
+        let stats = tm.stats().unwrap();
+        assert_eq!(stats.steps, 4);
+        assert_eq!((stats.moves_left, stats.moves_right), (2, 1));
+        assert_eq!((stats.leftmost, stats.rightmost), (-2, 0));
+        assert_eq!(stats.writes.get(&-1), Some(&2));
+        assert_eq!(stats.cells_visited(), 3);
+    }
+
+    #[test]
+    /// Test that a snapshot restored on a machine compiled from the same code continues exactly like the original
+    fn snapshot() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+        tm.step_n(3);
+
+        let json = serde_json::to_string(&tm.snapshot()).unwrap();
+        let snapshot: MachineSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot, tm.snapshot());
+
+        let (mut restored, _) = TuringMachine::new(&unparsed_file).unwrap();
+        restored.restore(snapshot).unwrap();
+
+        assert_eq!(restored.to_string(), tm.to_string());
+        assert_eq!(restored.final_result(), tm.final_result());
+        assert_eq!(restored.to_string(), tm.to_string());
+        assert_eq!(restored.frequencies(), tm.frequencies());
+    }
+
+    #[test]
+    /// Test that a snapshot cannot be restored on a machine with a different program
+    fn snapshot_different_program() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        let (mut other, _) =
+            TuringMachine::new(&unparsed_file.replace("(p1, 0, 0, R, p2);", "")).unwrap();
+        let before = other.to_string();
+
+        assert_eq!(
+            other.restore(tm.snapshot()),
+            Err(SnapshotError::DifferentProgram)
+        );
+        assert_eq!(other.to_string(), before);
+    }
+
+    #[test]
+    /// Test the coverage of the instructions of the `x2` library
+    fn coverage() {
+        let code = "compose = {x2}; {111}; I = {q0}; F = {qf};";
+
+        let (mut tm, _) = TuringMachine::new(code).unwrap();
+        assert!(tm.coverage().is_none());
+        assert_eq!(tm.uncovered_instructions().len(), tm.instructions().len());
+
+        tm.enable_coverage();
+        tm.final_result();
+        assert!(tm.uncovered_instructions().is_empty());
+
+        let coverage = tm.coverage().unwrap();
+        assert_eq!(coverage.len(), tm.instructions().len());
+        assert_eq!(coverage[&(String::from("q0"), true)], 1);
+        assert_eq!(coverage[&(String::from("q5"), false)], 2);
+
+        // The counts survive resetting the frequencies, but not resetting the machine
+        tm.reset_frequencies();
+        assert!(tm.uncovered_instructions().is_empty());
+        tm.reset();
+        assert!(tm.coverage().unwrap().is_empty());
+
+        tm.set_input_values(&[0]).unwrap();
+        tm.final_result();
+
+        let uncovered: Vec<&str> = tm
+            .uncovered_instructions()
+            .iter()
+            .map(|i| i.from_state.as_str())
+            .collect();
+        assert_eq!(
+            uncovered,
+            vec!["q1", "q2", "q2", "q3", "q3", "q4", "q4", "q5", "q5"]
+        );
+    }
+
+    #[test]
+    /// Test that applying the deltas of every step of the `mod` library to the initial tape gives the final tape
+    fn step_deltas() {
+        let (mut tm, _) =
+            TuringMachine::new("compose = {mod}; {1111111101111}; I = {q0}; F = {qf};").unwrap();
+
+        let mut tape = tm.tape_vec();
+        let mut origin = tm.origin() as isize;
+        let mut head = tm.head_offset();
+
+        while let Some(delta) = tm.step_delta() {
+            tape.splice(0..0, vec![false; delta.grew_left]);
+            tape.extend(vec![false; delta.grew_right]);
+            origin += delta.grew_left as isize;
+
+            if let Some((cell, value)) = delta.written_cell {
+                assert_eq!(cell, head);
+                tape[(origin + cell) as usize] = value;
+            }
+            head += delta.head_move as isize;
+
+            assert_eq!(delta.step, tm.steps);
+            assert_eq!(delta.new_state, tm.current_state());
+
+            if delta.halted {
+                break;
+            }
+        }
+
+        assert!(tm.finished());
+        assert_eq!(tape, tm.tape_vec());
+        assert_eq!(origin, tm.origin() as isize);
+        assert_eq!(head, tm.head_offset());
+        assert_eq!(tm.numeric_result(), Some(7 % 3));
+    }
+
+    #[test]
+    /// Test jumping back and forth on the timeline of a debugging session
+    fn debug_session() {
+        let code = format!(
+            "compose = {{mod}}; {{{}0{}}}; I = {{q0}}; F = {{qf}};",
+            "1".repeat(41),
+            "1".repeat(8)
+        );
+        let fresh = |steps: usize| {
+            let (mut tm, _) = TuringMachine::new(&code).unwrap();
+            tm.run(steps);
+            tm
+        };
+
+        let (mut tm, _) = TuringMachine::new(&code).unwrap();
+        tm.enable_trace();
+        let initial_tape = tm.tape_vec();
+        let mut session = DebugSession::new(tm, 50);
+
+        for step in [200, 37, 150] {
+            assert_eq!(session.goto_step(step), step);
+
+            let expected = fresh(step);
+            assert_eq!(session.machine().tape_vec(), expected.tape_vec());
+            assert_eq!(session.current(), expected.snapshot());
+
+            // The trace is cut back to the current step
+            let trace = session.machine().trace();
+            assert_eq!(trace.len(), step);
+            assert_eq!(replay(trace, &initial_tape), session.machine().tape_vec());
+        }
+        // The checkpoints of steps 0, 50, 100, 150 and 200
+        assert_eq!(session.checkpoints(), 5);
+
+        assert_eq!(session.step_back(), 149);
+        assert_eq!(session.step_forward(), 150);
+
+        // The machine cannot go past its halting step
+        let (mut finished, _) = TuringMachine::new(&code).unwrap();
+        let ExecutionResult::Halted { steps, .. } = finished.run(100_000) else {
+            panic!("The machine did not halt");
+        };
+        assert_eq!(session.goto_step(100_000), steps);
+        assert_eq!(session.current(), finished.snapshot());
+        assert_eq!(session.step_forward(), steps);
+
+        // Breakpoints stop the session, which can go back to before them
+        session.goto_step(0);
+        session.machine_mut().add_breakpoint("qf");
+        assert_eq!(
+            session.run_to_breakpoint(100_000),
+            ExecutionResult::Breakpoint {
+                state: String::from("qf"),
+                steps: steps - 1
+            }
+        );
+        assert_eq!(session.goto_step(10), 10);
+    }
+
+    #[test]
+    /// Test that running the same program twice gives the same configuration keys, even after a reset or a restore
+    fn configuration_keys() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+        let (mut other, _) = TuringMachine::new(&unparsed_file).unwrap();
+
+        let keys = tm.run_collect_configs(1000);
+        assert_eq!(other.run_collect_configs(1000), keys);
+        assert_eq!(keys.len(), tm.steps + 1);
+        assert_eq!(keys.last(), Some(&tm.configuration_key()));
+
+        tm.reset();
+        assert_eq!(tm.configuration_key(), keys[0]);
+        tm.step_n(5);
+        let snapshot = tm.snapshot();
+        assert_eq!(tm.run_collect_configs(1000), keys[5..]);
+
+        tm.restore(snapshot).unwrap();
+        assert_eq!(tm.configuration_key(), keys[5]);
+
+        // The head moves back and forth over the same cells, so the first configuration is visited again
+        let test = "
+        {1};
+        I = {q0};
+        F = {q2};
+
+        (q0, 1, 1, R, q1);
+        (q1, 0, 0, L, q0);
+        ";
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        let keys = tm.run_collect_configs(4);
+
+        assert_eq!(
+            keys[0],
+            ConfigKey {
+                state: String::from("q0"),
+                head: 0,
+                start: 0,
+                cells: vec![true],
+            }
+        );
+        assert_eq!(keys.len(), 5);
+        assert_eq!(keys[2], keys[0]);
+        assert_eq!(keys[4], keys[0]);
+        assert_ne!(keys[1], keys[0]);
+    }
+
+    #[test]
+    /// Test the explanations of the first steps of `Example1`, which must be the same in the trace
+    fn explain_next_step() {
+        let unparsed_file = fs::read_to_string("Examples/Example1.tm").expect("cannot read file");
+        let (mut tm, _) = TuringMachine::new(&unparsed_file).unwrap();
+        tm.enable_trace();
+
+        let mut explanations = Vec::new();
+        for _ in 0..3 {
+            let before = tm.clone();
+            explanations.push(tm.explain_next_step().unwrap());
+            assert_eq!(tm, before);
+            assert_eq!(tm.steps, before.steps);
+
+            tm.advance();
+        }
+
+        assert_eq!(
+            explanations,
+            vec![
+                "In state p0 reading 1: write 0, move Right, go to p1",
+                "In state p1 reading 1: write 1, move Right, go to p1",
+                "In state p1 reading 1: write 1, move Right, go to p1",
+            ]
+        );
+        assert_eq!(
+            tm.trace()
+                .iter()
+                .map(|e| e.explanation.as_str())
+                .collect::<Vec<_>>(),
+            explanations
+        );
+        assert_eq!(
+            tm.explain_next_step_in(Language::Spanish).unwrap(),
+            "En el estado p1 leyendo 1: escribir 1, mover a la Derecha, ir a p1"
+        );
+
+        tm.run(100);
+        assert_eq!(
+            tm.explain_next_step().unwrap(),
+            "In state p2 reading 1: halt, since p2 is a final state"
+        );
+
+        // The instructions of a composed library are explained with its name
+        let (mut tm, _) =
+            TuringMachine::new("compose = {sum}; {11011}; I = {q0}; F = {q2};").unwrap();
+        assert_eq!(
+            tm.explain_next_step().unwrap(),
+            "In state q0 reading 1: write 0, move Right, go to q1 (from the library sum)"
+        );
+
+        tm.run_until(|tm| tm.finished(), 100);
+        assert_eq!(
+            tm.explain_next_step_in(Language::Spanish).unwrap(),
+            "En el estado q2 leyendo 1: escribir 0, no moverse, ir a q2 (de la librería sum)"
+        );
+
+        let instruction = tm.instructions()[&InstructionKey::new("q0", true)].clone();
+        assert_eq!(tm.library_of(&instruction), Some("sum"));
+
+        // Changing an instruction of the library writes all of them in the code
+        tm.remove_instruction("q2", true);
+        assert_eq!(
+            tm.explain_next_step().unwrap(),
+            "In state q2 reading 1: halt, since q2 is a final state"
+        );
+        assert_eq!(tm.library_of(&instruction), None);
+    }
+
+    #[test]
+    /// Test that going to a step with the checkpoints gives the same machine as running that many steps from the start
+    fn goto_step() {
+        let code = "compose = {x2}; {11111111}; I = {q0}; F = {qf};";
+        let (mut tm, _) = TuringMachine::new(code).unwrap();
+        assert_eq!(tm.goto_step(0), Err(TimeTravelError::NotEnabled));
+
+        tm.enable_checkpoints(10);
+        let ExecutionResult::Halted { steps, .. } = tm.run(10_000) else {
+            panic!("The machine did not halt");
+        };
+        assert_eq!(tm.checkpoint_count(), steps / 10 + 1);
+
+        for k in [steps / 2, 0, 37, 36, 10, 9, steps - 1, 1, steps] {
+            tm.goto_step(k).unwrap();
+
+            let (mut fresh, _) = TuringMachine::new(code).unwrap();
+            fresh.run(k);
+            assert_eq!(tm.snapshot(), fresh.snapshot(), "at step {}", k);
+        }
+
+        // Going to a step does not take more snapshots
+        assert_eq!(tm.checkpoint_count(), steps / 10 + 1);
+        assert_eq!(
+            tm.goto_step(steps + 1),
+            Err(TimeTravelError::NotRecorded {
+                step: steps + 1,
+                recorded: (0, steps)
+            })
+        );
+        assert_eq!(tm.steps, steps);
+
+        // Resetting the machine starts taking them again
+        tm.reset();
+        assert_eq!(tm.checkpoint_count(), 1);
+        assert!(tm.goto_step(1).is_err());
+
+        // A machine changed after the checkpoints cannot execute the steps again
+        let (mut tm, _) =
+            TuringMachine::new("{1}; I = {q0}; F = {qf}; (q0, 1, 1, R, q0); (q0, 0, 1, R, q0);")
+                .unwrap();
+        tm.enable_checkpoints(2);
+        tm.run(7);
+        tm.set_tape_limit(1);
+        assert!(matches!(
+            tm.goto_step(5),
+            Err(TimeTravelError::Diverged {
+                step: 5,
+                result: StepResult::TapeLimitExceeded { .. }
+            })
+        ));
+        assert_eq!(tm.steps, 4);
+    }
+
+    #[test]
+    /// Test that a machine written in the binary format is loaded back with its runtime state
+    fn binary_round_trip() {
+        let code = fs::read_to_string("Examples/Example1.tm").unwrap();
+        let (mut tm, _) = TuringMachine::new(&code).unwrap();
+        tm.advance();
+        tm.advance();
+
+        let bytes = tm.to_bytes();
+        let mut loaded = TuringMachine::from_bytes(&bytes).unwrap();
+
+        assert_eq!(loaded, tm);
+        assert_eq!(loaded.steps, tm.steps);
+        assert_eq!(loaded.code(), tm.code());
+        assert_eq!(loaded.frequencies(), tm.frequencies());
+        assert_eq!(loaded.to_bytes(), bytes);
+        assert!(loaded.choices().is_none());
+        assert!(bytes.len() < serde_json::to_vec(&tm).unwrap().len());
+        assert_eq!(
+            serde_json::to_string(&loaded).unwrap(),
+            serde_json::to_string(&tm).unwrap()
+        );
+        assert_eq!(loaded.final_result(), tm.clone().final_result());
+
+        // The choices, spans and docs, which are only written when there are some, are kept too
+        let (tm, _) = TuringMachine::new_nondeterministic(
+            "{1};\nI = {q0};\nF = {q1};\n/// move\n(q0, 1, 1, R, q0);\n(q0, 1, 1, H, q1);\n(q0, 0, 0, H, q1);",
+        )
+        .unwrap();
+        let loaded = TuringMachine::from_bytes(&tm.to_bytes()).unwrap();
+        assert_eq!(loaded.choices(), tm.choices());
+        assert_eq!(loaded.instructions_sorted(), tm.instructions_sorted());
+        assert_eq!(
+            loaded.instructions_sorted()[1].span,
+            tm.instructions_sorted()[1].span
+        );
+    }
+
+    #[test]
+    /// Test that data that is not a machine of this version of the binary format is rejected
+    fn binary_invalid_data() {
+        let (tm, _) = TuringMachine::new("{1}; I = {q0}; F = {q1}; (q0, 1, 1, H, q1);").unwrap();
+        let bytes = tm.to_bytes();
+
+        let mut corrupted = bytes.clone();
+        corrupted[0] = b'X';
+        assert_eq!(
+            TuringMachine::from_bytes(&corrupted).unwrap_err(),
+            BinaryError::InvalidHeader
+        );
+        assert_eq!(
+            TuringMachine::from_bytes(&[]).unwrap_err(),
+            BinaryError::InvalidHeader
+        );
+
+        let mut newer = bytes.clone();
+        newer[4] = BINARY_VERSION + 1;
+        assert_eq!(
+            TuringMachine::from_bytes(&newer).unwrap_err(),
+            BinaryError::UnsupportedVersion {
+                found: BINARY_VERSION + 1,
+                supported: BINARY_VERSION
+            }
+        );
+
+        assert!(matches!(
+            TuringMachine::from_bytes(&bytes[..bytes.len() - 3]),
+            Err(BinaryError::Invalid(_))
+        ));
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(matches!(
+            TuringMachine::from_bytes(&longer),
+            Err(BinaryError::Invalid(_))
+        ));
+
+        // A valid header with a head or a state out of range is not loaded
+        let mut far_head = tm.clone();
+        far_head.tape_position = 999;
+        let mut unknown_state = tm.clone();
+        unknown_state.current_state = StateId(999);
+        for corrupted in [far_head, unknown_state] {
+            assert!(matches!(
+                TuringMachine::from_bytes(&corrupted.to_bytes()),
+                Err(BinaryError::Invalid(_))
+            ));
+        }
+    }
+
+    #[test]
+    /// Test that the trace of a machine whose blank is 1 replays with 1s, and that its inputs skip the leading 1s
+    fn blank_trace_and_input() {
+        let code =
+            "blank = 1;\n{0};\nI = {q0};\nF = {qf};\n(q0, 0, 0, R, q0);\n(q0, 1, 1, R, q0);\n";
+        let (mut tm, _) = TuringMachine::new(code).unwrap();
+        tm.enable_trace();
+        tm.run(6);
+
+        let trace = tm.trace();
+        assert!(trace.iter().all(|e| e.blank));
+        assert_eq!(replay(trace, tm.trace_initial_tape()), tm.tape_vec());
+        assert!(tm.tape_vec().ends_with(&[true, true, true]));
+
+        // The leading blanks are skipped, but not the 0s
+        tm.set_input_tape(&[true, true, false, false]).unwrap();
+        assert_eq!(tm.tape_trimmed(), (&[false, false][..], 0));
+        tm.set_input_tape(&[false, true, false]).unwrap();
+        assert_eq!(tm.tape_trimmed(), (&[false, true, false][..], 0));
+
+        let error = tm.set_input_tape(&[true, true]).unwrap_err();
+        assert_eq!(error.message(), "Expected at least a 0 in the tape");
+    }
+}
+
+#[cfg(test)]
+mod test_rendering {
+    use crate::TapeError;
+    use crate::TuringMachine;
+    use crate::TuringOutput;
+
+    const LONG_TAPE: &str = "
+        {11111111111111111111};
+        I = {q0};
+        F = {q1};
+
+        (q0, 1, 1, R, q0);
+        (q0, 0, 0, H, q1);
+        ";
+
+    #[test]
+    /// Test rendering a window with the head near the left edge of the tape
+    fn render_window_left() {
+        let (tm, _) = TuringMachine::new(LONG_TAPE).unwrap();
+
+        assert_eq!(tm.render_window(5), "0 0 0 [1] 1 1 1 1 1 (14)…");
+    }
+
+    #[test]
+    /// Test rendering a window with the head in the middle of the tape
+    fn render_window_middle() {
+        let (mut tm, _) = TuringMachine::new(LONG_TAPE).unwrap();
+        tm.step_n(10);
+
+        assert_eq!(tm.render_window(2), "…(11) 1 1 [1] 1 1 (7)…");
+        assert_eq!(tm.render_window(0), "…(13) [1] (9)…");
+    }
+
+    #[test]
+    /// Test rendering a window with the head near the right edge of the tape
+    fn render_window_right() {
+        let (mut tm, _) = TuringMachine::new(LONG_TAPE).unwrap();
+        tm.step_n(20);
+
+        assert_eq!(tm.render_window(5), "…(18) 1 1 1 1 1 [0] 0 0 0");
+    }
+
+    #[test]
+    /// Test the compressed rendering of a tape with a single 1
+    fn render_compressed_single() {
+        let test = "
+        {1};
+        I = {q0};
+        F = {q1};
+
+        (q0, 1, 1, R, q0);
+        ";
+
+        let (tm, _) = TuringMachine::new(test).unwrap();
+
+        assert_eq!(tm.render_compressed(), "0{3} 1 @cell 3 (run 1)");
+        assert_eq!(format!("{}", tm.compressed()), tm.render_compressed());
+    }
+
+    #[test]
+    /// Test the compressed rendering when the head is on the first cell of a run
+    fn render_compressed_boundary() {
+        let (mut tm, _) = TuringMachine::new(LONG_TAPE).unwrap();
+        tm.step_n(20);
+
+        assert_eq!(tm.render_compressed(), "0{3} 1{20} 0{4} @cell 23 (run 2)");
+        assert_eq!(
+            tm.compressed().runs(),
+            vec![(false, 3), (true, 20), (false, 4)]
+        );
+    }
+
+    #[test]
+    /// Test that writing past the right edge of the tape extends it, and the machine keeps stepping correctly
+    fn write_cell() {
+        let (mut tm, _) = TuringMachine::new(LONG_TAPE).unwrap();
+        let len = tm.tape_len();
+
+        assert_eq!(tm.read_cell(len), None);
+        assert_eq!(
+            tm.write_cell(len + 1, true),
+            Err(TapeError::OutOfBounds {
+                index: len + 1,
+                len
+            })
+        );
+
+        tm.write_cell(len, true).unwrap();
+        assert_eq!(tm.read_cell(len), Some(true));
+        assert_eq!(tm.tape_slice(len - 1..), &[true, true]);
+        assert_eq!(tm.tape_slice(..3), &[false, false, false]);
+        assert_eq!(tm.tape_slice(len + 5..len + 10), &[] as &[bool]);
+
+        // The head now runs over 21 ones, reads the blank and then takes the halting step
+        assert_eq!(tm.final_result(), TuringOutput::Defined((23, 21)));
+    }
+
+    #[test]
+    /// Test that the padding added while the tape grows leftwards does not change the cells given in the code
+    fn padding_keeps_cells() {
+        let test = "
+        {101};
+        I = {q0};
+        F = {q1};
+
+        (q0, 1, 1, L, q0);
+        (q0, 0, 0, L, q0);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        tm.step_n(10);
+
+        assert_eq!(tm.head_offset(), -10);
+        assert!(tm.tape_position >= 3);
+        assert_eq!(
+            tm.tape_slice(tm.origin()..tm.origin() + 3),
+            &[true, false, true]
+        );
+        assert_eq!(
+            tm.to_string(),
+            format!("{}1 0 1 \n      ^ {}", "0 ".repeat(13), "  ".repeat(12))
+        );
+    }
+
+    #[test]
+    /// Test that the trimmed tape does not depend on the amount of padding
+    fn tape_trimmed() {
+        let test = "
+        {1011};
+        I = {q0};
+        F = {q4};
+
+        (q0, 1, 1, L, q1);
+        (q1, 0, 0, L, q2);
+        (q2, 0, 0, R, q3);
+        (q3, 0, 0, R, q4);
+        ";
+
+        let (mut tm, _) = TuringMachine::new(test).unwrap();
+        let len = tm.tape_len();
+        assert_eq!(tm.tape_trimmed(), (&[true, false, true, true][..], 0));
+
+        tm.step_n(2);
+        assert_eq!(tm.tape_trimmed(), (&[true, false, true, true][..], -2));
+
+        tm.step_n(2);
+        assert!(tm.tape_len() > len);
+        assert_eq!(tm.tape_trimmed(), (&[true, false, true, true][..], 0));
+        assert_eq!(tm.values(), vec![0, 1]);
+    }
+
+    #[test]
+    /// Test that trimming a tape without 1s gives an empty slice
+    fn tape_trimmed_empty() {
+        let (mut tm, _) = TuringMachine::new(LONG_TAPE).unwrap();
+        for i in 0..tm.tape_len() {
+            tm.write_cell(i, false).unwrap();
+        }
+
+        assert_eq!(tm.tape_trimmed(), (&[][..], 0));
+        assert!(tm.values().is_empty());
     }
 }
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use log::{debug, error};
+use pest::Parser;
+
+use crate::{
+    warnings::ErrorPosition, CompilerError, CompilerWarning, Rule, TuringMachine, TuringParser,
+};
+
+impl TuringMachine {
+    /// Compiles every machine of a file with several `machine <name> { ... }` blocks, each one with the code of a file,
+    /// by their names. The code of each machine is the code of its block, and the errors and warnings are given
+    /// in the positions of the whole file.
+    ///
+    /// A file without any `machine` block is compiled like `new`, into a single machine with an empty name
+    pub fn parse_all(
+        code: &str,
+    ) -> Result<HashMap<String, (TuringMachine, Vec<CompilerWarning>)>, CompilerError> {
+        if TuringParser::parse(Rule::machines_start, code).is_err() {
+            debug!("The code has no machine blocks, compiling a single machine");
+
+            return Ok(HashMap::from([(String::new(), Self::new(code)?)]));
+        }
+
+        let file = match TuringParser::parse(Rule::machines_file, code) {
+            Ok(mut f) => f.next().unwrap(),
+            Err(error) => {
+                return Err(CompilerError::FileRuleError {
+                    error: Box::new(error),
+                })
+            }
+        };
+
+        let mut machines: HashMap<String, (TuringMachine, Vec<CompilerWarning>)> = HashMap::new();
+
+        for record in file.into_inner().filter(|r| r.as_rule() == Rule::machine) {
+            let mut inner = record.into_inner();
+            let name = inner.next().unwrap();
+            let body = inner.next().unwrap();
+
+            if machines.contains_key(name.as_str()) {
+                error!("The machine {} was given more than once", name.as_str());
+
+                return Err(CompilerError::SyntaxError {
+                    position: name.as_span().into(),
+                    message: format!("The machine {} can only be given once", name.as_str()),
+                    code: String::from(name.as_str()),
+                    expected: Rule::machine,
+                    found: Some(Rule::machine_name),
+                });
+            }
+
+            debug!("Compiling the machine {}", name.as_str());
+
+            // The positions in the code of the block are moved to where the block starts in the file
+            let (line, column) = body.line_col();
+            let shift = |position: &mut ErrorPosition| {
+                for (l, c) in std::iter::once(&mut position.start).chain(position.end.as_mut()) {
+                    if *l == 0 {
+                        *c += column.max(1) - 1;
+                    }
+                    *l += line - 1;
+                }
+            };
+
+            let (tm, mut warnings) = Self::new(body.as_str()).map_err(|mut error| {
+                if let CompilerError::SyntaxError { position, .. }
+                | CompilerError::ImportError { position, .. } = &mut error
+                {
+                    shift(position);
+                }
+                error
+            })?;
+
+            for warning in warnings.iter_mut() {
+                match warning {
+                    CompilerWarning::StateOverwrite { position, .. }
+                    | CompilerWarning::LibraryOverride { position, .. }
+                    | CompilerWarning::CompositionCollision { position, .. }
+                    | CompilerWarning::DeadInstruction { position, .. }
+                    | CompilerWarning::RepeatedFinalState { position, .. }
+                    | CompilerWarning::LibraryShadowed { position, .. }
+                    | CompilerWarning::StyleWarning { position, .. } => shift(position),
+                }
+            }
+
+            machines.insert(String::from(name.as_str()), (tm, warnings));
+        }
+
+        Ok(machines)
+    }
+}
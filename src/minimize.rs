@@ -0,0 +1,144 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    warnings::ErrorPosition, CompilerError, InstructionKey, Movement, TuringInstruction,
+    TuringMachine,
+};
+
+/// What a state does when reading a value, with the block of the state it goes to
+type Behavior = Option<(bool, Movement, usize)>;
+
+impl TuringMachine {
+    /// Returns a machine that behaves like this one with the fewest states, by merging the states
+    /// that are equivalent: they write the same values, move the same way and go to equivalent states.
+    /// The final, accepting and rejecting states are never merged with the other kinds of states.
+    ///
+    /// The states are renamed `m0, m1…` in the order they are reached from the initial state, which is `m0`,
+    /// and the code is written again from the minimized instructions, with the composed libraries written out.
+    /// The new machine starts with the initial tape of this one and is compiled with the same `ParserOptions`,
+    /// returning the error if they reject it
+    pub fn minimized(&self) -> Result<TuringMachine, CompilerError> {
+        let states = self.states();
+
+        // The initial split keeps apart the states that halt differently
+        let kind = |state: &str| {
+            (
+                self.is_final(state),
+                self.accepting_states().iter().any(|s| s == state),
+                self.rejecting_states().iter().any(|s| s == state),
+            )
+        };
+        let mut blocks: HashMap<&str, usize> = HashMap::new();
+        let mut kinds: HashMap<(bool, bool, bool), usize> = HashMap::new();
+        for state in states.iter() {
+            let next = kinds.len();
+            blocks.insert(state, *kinds.entry(kind(state)).or_insert(next));
+        }
+        let mut count = kinds.len();
+
+        // Each pass splits the blocks whose states behave differently, until no block is split
+        loop {
+            let behavior = |state: &str, value: bool| -> Behavior {
+                self.instructions()
+                    .get(&InstructionKey::new(state, value))
+                    .map(|i| (i.to_value, i.movement, blocks[i.to_state.as_str()]))
+            };
+
+            let mut signatures: HashMap<(usize, Behavior, Behavior), usize> = HashMap::new();
+            let mut refined: HashMap<&str, usize> = HashMap::new();
+            for state in states.iter() {
+                let signature = (blocks[state], behavior(state, false), behavior(state, true));
+                let next = signatures.len();
+                refined.insert(state, *signatures.entry(signature).or_insert(next));
+            }
+
+            blocks = refined;
+            if signatures.len() == count {
+                break;
+            }
+            count = signatures.len();
+        }
+
+        let names = self.minimized_names(&states, &blocks);
+        let rename = |state: &str| names[&blocks[state]].clone();
+
+        // Every state of a block has the same instructions, so the ones of any of them are kept
+        let mut instructions: HashMap<InstructionKey, TuringInstruction> = HashMap::new();
+        for instruction in self.instructions().values() {
+            let from_state = rename(&instruction.from_state);
+
+            instructions
+                .entry(InstructionKey::new(&from_state, instruction.from_value))
+                .or_insert_with(|| TuringInstruction {
+                    from_state,
+                    from_value: instruction.from_value,
+                    to_value: instruction.to_value,
+                    movement: instruction.movement,
+                    to_state: rename(&instruction.to_state),
+                    span: None,
+                    doc: None,
+                });
+        }
+
+        let rename_all = |list: &[String]| {
+            let mut renamed: Vec<String> = list.iter().map(|s| rename(s)).collect();
+            renamed.sort();
+            renamed.dedup();
+            renamed
+        };
+
+        let mut parts = self.machine_parts();
+        parts.composed.clear();
+        parts.initial_state = Some(rename(self.initial_state()));
+        parts.final_states = rename_all(&parts.final_states);
+        parts.accepting_states = rename_all(&parts.accepting_states);
+        parts.rejecting_states = rename_all(&parts.rejecting_states);
+        parts.instructions = instructions
+            .into_values()
+            .map(|i| (i, ErrorPosition::new((0, 0), None)))
+            .collect();
+        parts.code = parts.write_code();
+
+        let (tm, _) = Self::from_machine_parts_with(parts, &self.program.options)?;
+
+        Ok(tm)
+    }
+
+    /// Names the blocks of states `m0, m1…` in the order they are reached from the initial state,
+    /// followed by the unreachable ones in the order of their first state
+    fn minimized_names(
+        &self,
+        states: &[&str],
+        blocks: &HashMap<&str, usize>,
+    ) -> HashMap<usize, String> {
+        let mut names: HashMap<usize, String> = HashMap::new();
+        let mut name = |block: usize| {
+            let next = names.len();
+            names.entry(block).or_insert_with(|| format!("m{}", next));
+        };
+
+        let mut visited: HashSet<&str> = HashSet::from([self.initial_state()]);
+        let mut pending: VecDeque<&str> = VecDeque::from([self.initial_state()]);
+        while let Some(state) = pending.pop_front() {
+            name(blocks[state]);
+
+            for value in [false, true] {
+                if let Some(instruction) =
+                    self.instructions().get(&InstructionKey::new(state, value))
+                {
+                    let next = instruction.to_state.as_str();
+                    if visited.insert(next) {
+                        pending.push_back(next);
+                    }
+                }
+            }
+        }
+
+        // The states are sorted, so the unreachable blocks get their names deterministically too
+        for state in states {
+            name(blocks[state]);
+        }
+
+        names
+    }
+}
@@ -0,0 +1,642 @@
+use log::{debug, error, info, warn};
+use pest::{iterators::Pair, Parser};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{
+    tape::Tape,
+    turing::{binary_value, list_items, parse_description, parse_tape, Rule},
+    warnings::ErrorPosition,
+    CompilerError, CompilerWarning, Movement, TuringMachine, TuringParser, DEFAULT_MAX_TAPE_CELLS,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// An instruction of a multi-tape machine, which reads, writes and moves every head at once
+pub struct MultiTapeInstruction {
+    pub from_state: String,
+    pub from_values: Vec<bool>,
+    pub to_values: Vec<bool>,
+    pub movements: Vec<Movement>,
+    pub to_state: String,
+}
+
+impl Display for MultiTapeInstruction {
+    /// Display an instruction as a string
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values = |values: &[bool]| {
+            values
+                .iter()
+                .map(|v| if *v { "1" } else { "0" })
+                .collect::<Vec<&str>>()
+                .join(", ")
+        };
+        let movements: Vec<String> = self.movements.iter().map(|m| m.to_string()).collect();
+
+        write!(
+            f,
+            "({}, [{}], [{}], [{}], {})",
+            self.from_state,
+            values(&self.from_values),
+            values(&self.to_values),
+            movements.join(", "),
+            self.to_state
+        )
+    }
+}
+
+impl MultiTapeInstruction {
+    /// Create an instruction from a `multi_instruction` record
+    fn from(record: Pair<Rule>) -> Result<Self, CompilerError> {
+        let mut code = record.into_inner();
+
+        let from_state = match code.next() {
+            Some(s) => String::from(s.as_str()),
+            None => panic!("The instruction lacks an initial state"),
+        };
+        let from_values = match code.next() {
+            Some(s) => s
+                .into_inner()
+                .map(|v| binary_value(&v))
+                .collect::<Result<_, _>>()?,
+            None => panic!("The instruction lacks the initial tape values"),
+        };
+        let to_values = match code.next() {
+            Some(s) => s
+                .into_inner()
+                .map(|v| binary_value(&v))
+                .collect::<Result<_, _>>()?,
+            None => panic!("The instruction lacks the target tape values"),
+        };
+
+        let mut movements = Vec::new();
+        match code.next() {
+            Some(s) => {
+                for m in s.into_inner() {
+                    match Movement::from_str(m.as_str()) {
+                        Ok(movement) => movements.push(movement),
+                        Err(message) => {
+                            return Err(CompilerError::SyntaxError {
+                                position: ErrorPosition::from(&m),
+                                message,
+                                code: String::from(m.as_str()),
+                                expected: Rule::movement,
+                                found: None,
+                            })
+                        }
+                    }
+                }
+            }
+            None => panic!("The instruction lacks the movements"),
+        }
+
+        let to_state = match code.next() {
+            Some(s) => String::from(s.as_str()),
+            None => panic!("The instruction lacks a target state"),
+        };
+
+        Ok(Self {
+            from_state,
+            from_values,
+            to_values,
+            movements,
+            to_state,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The result of executing a single step of a multi-tape machine
+pub enum MultiTapeStep {
+    /// The step was executed and the machine is not in a final state
+    Continued,
+
+    /// The step was executed and the machine is in a final state
+    Halted,
+
+    /// There is no instruction for the current state and values, so no step was executed
+    Undefined {
+        /// The state the machine is stuck in
+        state: String,
+        /// The values read from the tapes
+        values: Vec<bool>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The result of running a multi-tape machine for a bounded number of steps
+pub enum MultiTapeResult {
+    /// The machine executed the halting step of a final state
+    Halted { steps: usize },
+
+    /// The machine executed the maximum number of steps without halting
+    StepLimitReached { steps: usize },
+
+    /// There is no instruction for the current state and values, and the state is not final
+    Stuck {
+        /// The state the machine got stuck in
+        state: String,
+        /// The values read from the tapes
+        values: Vec<bool>,
+        /// The number of steps executed before getting stuck
+        steps: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// One of the tapes of a multi-tape machine, with its own head
+struct HeadTape {
+    /// The cells of the tape, padded with blank cells around the head
+    cells: Tape,
+
+    /// The position of the head on the tape
+    position: usize,
+
+    /// The index in the tape of the first cell of the tape given in the code
+    origin: usize,
+}
+
+impl HeadTape {
+    /// Creates a tape with the head on the cell `head`, padded on the left like the tape of a `TuringMachine`
+    fn new(cells: Vec<bool>, head: usize) -> Self {
+        let mut tape = Self {
+            cells: Tape::from(cells),
+            position: head,
+            origin: 0,
+        };
+        tape.pad();
+
+        tape
+    }
+
+    /// Writes `value` under the head and moves it
+    fn apply(&mut self, value: bool, movement: Movement) {
+        self.cells.set(self.position, value);
+
+        match movement {
+            Movement::LEFT => {
+                if self.position == 0 {
+                    self.cells.push_front(1, false);
+                    self.origin += 1;
+                } else {
+                    self.position -= 1;
+                }
+            }
+            Movement::RIGHT => self.position += 1,
+            Movement::HALT | Movement::STAY => {}
+        }
+
+        self.pad();
+    }
+
+    /// Keeps at least 3 blank cells at each side of the head, like `TuringMachine::pad`
+    fn pad(&mut self) {
+        let grew_left = 3usize.saturating_sub(self.position);
+
+        if grew_left > 0 {
+            self.cells.push_front(grew_left, false);
+            self.position += grew_left;
+            self.origin += grew_left;
+        }
+
+        let len = self.cells.len().max(self.position + 4);
+        self.cells.resize(len, false);
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A Turing machine with several tapes, each one with its own head.
+///
+/// Its code declares the number of tapes with `tapes = k;` and gives the tapes in order,
+/// blank tapes being left out or written as `{}`. Every instruction reads and writes one value
+/// and moves the head of each tape, e.g. `(q0, [1, 0], [1, 1], [R, R], q0);`.
+/// The tapes given in the code are padded and trimmed like the tape of a `TuringMachine`
+pub struct MultiTapeMachine {
+    /// The instructions of the machine, by state and values read from the tapes
+    pub instructions: HashMap<(String, Vec<bool>), MultiTapeInstruction>,
+
+    /// The final states of the machine
+    pub final_states: Vec<String>,
+
+    /// The current state of the machine
+    pub current_state: String,
+
+    /// The previous state of the machine
+    pub previous_state: Option<String>,
+
+    /// The number of steps executed since the machine was created or reset
+    pub steps: usize,
+
+    /// The description of the machine. Found in the `///` comments at the top of the file
+    pub description: Option<String>,
+
+    /// The actual code of the machine
+    pub code: String,
+
+    /// The tapes of the machine
+    tapes: Vec<HeadTape>,
+
+    /// The tapes right after compilation. Used for resetting the machine without parsing the code again
+    initial_tapes: Vec<HeadTape>,
+
+    /// The state the machine starts in
+    initial_state: String,
+}
+
+impl MultiTapeMachine {
+    /// Create a new multi-tape Turing machine from a string of code.
+    /// The value of the `StateOverwrite` warnings is the one read from the first tape
+    pub fn new(code: &str) -> Result<(Self, Vec<CompilerWarning>), CompilerError> {
+        let mut warnings: Vec<CompilerWarning> = Vec::new();
+
+        let file = match TuringParser::parse(Rule::multi_file, code) {
+            Ok(mut f) => f.next().unwrap(),
+            Err(error) => {
+                return Err(CompilerError::FileRuleError {
+                    error: Box::new(error),
+                })
+            }
+        };
+
+        let mut tape_count: Option<usize> = None;
+        let mut tapes: Vec<(Vec<bool>, Option<usize>, ErrorPosition)> = Vec::new();
+        let mut initial_state: Option<String> = None;
+        let mut final_states: Vec<String> = Vec::new();
+        let mut description: Option<String> = None;
+        let mut parsed: Vec<(MultiTapeInstruction, ErrorPosition, String)> = Vec::new();
+
+        for record in file.into_inner() {
+            match record.as_rule() {
+                Rule::description => {
+                    description = parse_description(record.as_str());
+                    debug!("Found description: \"{:?}\"", description);
+                }
+                Rule::tapes => {
+                    if tape_count.is_some() {
+                        return Err(TuringMachine::repeated_definition(
+                            &record,
+                            "number of tapes",
+                        ));
+                    }
+
+                    let count = record.clone().into_inner().as_str();
+                    match count.parse::<usize>() {
+                        Ok(count) if count > 0 => tape_count = Some(count),
+                        _ => {
+                            error!("Invalid number of tapes: {}", count);
+
+                            return Err(CompilerError::SyntaxError {
+                                position: (&record).into(),
+                                message: String::from("A machine needs at least one tape"),
+                                code: String::from(record.as_str()),
+                                expected: Rule::tape_count,
+                                found: None,
+                            });
+                        }
+                    }
+                    debug!("The machine has {:?} tapes", tape_count);
+                }
+                Rule::tape => {
+                    let position: ErrorPosition = (&record).into();
+                    let (cells, head) = parse_tape(record, DEFAULT_MAX_TAPE_CELLS)?;
+                    debug!("Tape {}: {:?}", tapes.len(), cells);
+
+                    tapes.push((cells, head, position));
+                }
+                Rule::initial_state => {
+                    if initial_state.is_some() {
+                        return Err(TuringMachine::repeated_definition(&record, "initial state"));
+                    }
+
+                    initial_state = Some(String::from(record.into_inner().as_str()));
+                    debug!("The initial tape state is \"{:?}\"", initial_state);
+                }
+                Rule::final_state => {
+                    if !final_states.is_empty() {
+                        return Err(TuringMachine::repeated_definition(&record, "final states"));
+                    }
+
+                    final_states = list_items(record, &mut warnings)
+                        .into_iter()
+                        .map(|v| String::from(v.as_str()))
+                        .collect();
+                    debug!("The final tape state is {:?}", final_states);
+                }
+                Rule::multi_instruction => {
+                    let position: ErrorPosition = (&record).into();
+                    let code = String::from(record.as_str());
+                    let instruction = MultiTapeInstruction::from(record)?;
+                    debug!("Found instruction {}", instruction);
+
+                    parsed.push((instruction, position, code));
+                }
+                Rule::doc => debug!("Ignoring the doc comment {}", record.as_str()),
+                Rule::EOI => {
+                    debug!("End of file");
+                }
+                _ => {
+                    warn!("Unhandled: {}", record.into_inner().as_str());
+                }
+            }
+        }
+
+        let Some(tape_count) = tape_count else {
+            error!("No number of tapes given");
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: String::from("No number of tapes given"),
+                code: String::from(code),
+                expected: Rule::tapes,
+                found: None,
+            });
+        };
+
+        if tapes.is_empty() {
+            error!("No tape given");
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: String::from("No tape given"),
+                code: String::from(code),
+                expected: Rule::tape,
+                found: None,
+            });
+        }
+
+        if tapes.len() > tape_count {
+            let (_, _, position) = tapes.swap_remove(tape_count);
+            error!("More than {} tapes given", tape_count);
+
+            return Err(CompilerError::SyntaxError {
+                position,
+                message: format!("The machine only has {} tapes", tape_count),
+                code: String::from(code),
+                expected: Rule::multi_instruction,
+                found: Some(Rule::tape),
+            });
+        }
+
+        if final_states.is_empty() {
+            error!("No final state given");
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: String::from("No final state given"),
+                code: String::from(code),
+                expected: Rule::final_state,
+                found: None,
+            });
+        }
+
+        let Some(initial_state) = initial_state else {
+            error!("No initial state given");
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: String::from("No initial state given"),
+                code: String::from(code),
+                expected: Rule::initial_state,
+                found: None,
+            });
+        };
+
+        let mut instructions: HashMap<(String, Vec<bool>), MultiTapeInstruction> = HashMap::new();
+
+        for (instruction, position, code) in parsed {
+            if instruction.from_values.len() != tape_count
+                || instruction.to_values.len() != tape_count
+                || instruction.movements.len() != tape_count
+            {
+                error!("The instruction {} does not match the tapes", instruction);
+
+                return Err(CompilerError::SyntaxError {
+                    position,
+                    message: format!(
+                        "Expected a value and a movement for each of the {} tapes",
+                        tape_count
+                    ),
+                    code,
+                    expected: Rule::multi_instruction,
+                    found: None,
+                });
+            }
+
+            let key = (
+                instruction.from_state.clone(),
+                instruction.from_values.clone(),
+            );
+            if instructions.contains_key(&key) {
+                warn!("Instruction {} already exists, overwriting it", instruction);
+
+                warnings.push(CompilerWarning::StateOverwrite {
+                    position,
+                    state: instruction.from_state.clone(),
+                    value_from: instruction.from_values[0],
+                })
+            }
+            instructions.insert(key, instruction);
+        }
+
+        if !instructions
+            .keys()
+            .any(|(state, _)| *state == initial_state)
+            && !final_states.contains(&initial_state)
+        {
+            error!("The initial state {} is not known", initial_state);
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: format!(
+                    "The initial state {} has no instructions and is not a final state",
+                    initial_state
+                ),
+                code: String::from(code),
+                expected: Rule::initial_state,
+                found: None,
+            });
+        }
+
+        let mut head_tapes: Vec<HeadTape> = tapes
+            .into_iter()
+            .enumerate()
+            .map(|(i, (cells, head, _))| {
+                // Leading 0s are skipped like in a single tape, except the ones from the head onwards
+                let leading = cells.iter().take_while(|v| !**v).count();
+                let skipped = leading.min(head.unwrap_or(leading));
+                if skipped > 0 {
+                    info!("The tape {} started with {} 0s, skipping them", i, skipped);
+                }
+
+                let mut cells = cells[skipped..].to_vec();
+                if cells.is_empty() {
+                    cells.push(false);
+                }
+
+                HeadTape::new(cells, head.unwrap_or(skipped) - skipped)
+            })
+            .collect();
+        head_tapes.resize(tape_count, HeadTape::new(vec![false], 0));
+
+        Ok((
+            Self {
+                instructions,
+                final_states,
+                current_state: initial_state.clone(),
+                previous_state: None,
+                steps: 0,
+                description,
+                code: String::from(code),
+                initial_tapes: head_tapes.clone(),
+                tapes: head_tapes,
+                initial_state,
+            },
+            warnings,
+        ))
+    }
+
+    /// Restores the tapes, the heads, the current state and the step counter
+    /// to the values they had right after the machine was created
+    pub fn reset(&mut self) {
+        self.tapes = self.initial_tapes.clone();
+        self.current_state = self.initial_state.clone();
+        self.previous_state = None;
+        self.steps = 0;
+    }
+
+    /// Returns the number of tapes of the machine
+    pub fn tape_count(&self) -> usize {
+        self.tapes.len()
+    }
+
+    /// Returns the values under the heads, one for each tape
+    pub fn read(&self) -> Vec<bool> {
+        self.tapes.iter().map(|t| t.cells[t.position]).collect()
+    }
+
+    /// Returns true if the current state is a final state
+    pub fn finished(&self) -> bool {
+        self.final_states.contains(&self.current_state)
+    }
+
+    /// Calculates the next step of the machine, writing and moving the head of every tape
+    pub fn advance(&mut self) -> MultiTapeStep {
+        let values = self.read();
+        let key = (self.current_state.clone(), values);
+
+        let (to_values, movements, to_state) = match self.instructions.get(&key) {
+            Some(i) => (i.to_values.clone(), i.movements.clone(), i.to_state.clone()),
+            // The implicit halt instruction of a final state leaves the tapes untouched
+            None if self.finished() => (
+                key.1.clone(),
+                vec![Movement::HALT; self.tapes.len()],
+                self.current_state.clone(),
+            ),
+            None => {
+                error!(
+                    "No instruction given for state ({}, {:?})",
+                    self.current_state, key.1
+                );
+
+                return MultiTapeStep::Undefined {
+                    state: key.0,
+                    values: key.1,
+                };
+            }
+        };
+
+        for ((tape, value), movement) in self.tapes.iter_mut().zip(to_values).zip(movements) {
+            tape.apply(value, movement);
+        }
+
+        self.previous_state = Some(key.0);
+        self.current_state = to_state;
+        self.steps += 1;
+
+        if self.finished() {
+            MultiTapeStep::Halted
+        } else {
+            MultiTapeStep::Continued
+        }
+    }
+
+    /// Runs the machine until it halts, gets stuck or executes `max_steps` steps.
+    /// Like `TuringMachine::run`, the step executed from a final state is the halting one
+    pub fn run(&mut self, max_steps: usize) -> MultiTapeResult {
+        let mut steps = 0;
+
+        while steps < max_steps {
+            let halting = self.finished();
+
+            if let MultiTapeStep::Undefined { state, values } = self.advance() {
+                return MultiTapeResult::Stuck {
+                    state,
+                    values,
+                    steps,
+                };
+            }
+            steps += 1;
+
+            if halting {
+                return MultiTapeResult::Halted { steps };
+            }
+        }
+
+        MultiTapeResult::StepLimitReached { steps }
+    }
+
+    /// Returns the cells of the tape `i`, including the blank padding around its head,
+    /// or None if the machine has no such tape
+    pub fn tape(&self, i: usize) -> Option<&[bool]> {
+        self.tapes.get(i).map(|t| &*t.cells)
+    }
+
+    /// Returns the position of the head of the tape `i` relative to the first cell given in the code,
+    /// like `TuringMachine::head_offset`, or None if the machine has no such tape
+    pub fn head_offset(&self, i: usize) -> Option<isize> {
+        self.tapes
+            .get(i)
+            .map(|t| t.position as isize - t.origin as isize)
+    }
+
+    /// Returns the values of the tape `i` (i.e. the number of 1s between each 0), like `TuringMachine::values`.
+    /// Returns an empty list if the machine has no such tape
+    pub fn values(&self, i: usize) -> Vec<u32> {
+        let Some(tape) = self.tape(i) else {
+            return Vec::new();
+        };
+
+        tape.split(|v| !*v)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.len() as u32 - 1)
+            .collect()
+    }
+
+    /// Returns the tape `i` as text, like the `Display` of a `TuringMachine`,
+    /// or None if the machine has no such tape
+    pub fn tape_to_string(&self, i: usize) -> Option<String> {
+        let tape = self.tapes.get(i)?;
+
+        let mut cells = String::new();
+        let mut head = String::new();
+        for (j, v) in tape.cells.iter().enumerate() {
+            cells += if *v { "1 " } else { "0 " };
+            head += if j == tape.position { "^ " } else { "  " };
+        }
+
+        Some(format!("{}\n{}", cells, head))
+    }
+}
+
+impl Display for MultiTapeMachine {
+    /// Displays every tape with its head, separated by blank lines
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tapes: Vec<String> = (0..self.tapes.len())
+            .filter_map(|i| self.tape_to_string(i))
+            .collect();
+
+        write!(f, "{}", tapes.join("\n\n"))
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
+
+use log::{debug, info};
+
+use crate::{
+    CompilerError, CompilerWarning, InstructionKey, Movement, TuringInstruction, TuringMachine,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The result of exploring the computations of a machine with `explore`
+pub struct Exploration {
+    /// True if a branch reached a final state
+    pub accepted: bool,
+
+    /// The instructions of one of the shortest accepting computations, empty if no branch was accepted
+    pub path: Vec<TuringInstruction>,
+
+    /// The number of distinct configurations reached
+    pub configurations: usize,
+
+    /// True if some branch was cut at the maximum depth, so a rejection may not be final
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A configuration of one branch of the computation, with the blank cells around the other ones and the head trimmed
+struct Branch {
+    state: String,
+    cells: Vec<bool>,
+    head: usize,
+}
+
+impl Branch {
+    /// Executes an instruction on a copy of the configuration, growing the tape with `blank` cells
+    fn apply(&self, instruction: &TuringInstruction, blank: bool) -> Self {
+        let mut cells = self.cells.clone();
+        let mut head = self.head;
+
+        cells[head] = instruction.to_value;
+
+        match instruction.movement {
+            Movement::LEFT => {
+                if head == 0 {
+                    cells.insert(0, blank);
+                } else {
+                    head -= 1;
+                }
+            }
+            Movement::RIGHT => {
+                head += 1;
+                if head == cells.len() {
+                    cells.push(blank);
+                }
+            }
+            Movement::HALT | Movement::STAY => {}
+        }
+
+        Self::trimmed(instruction.to_state.clone(), cells, head, blank)
+    }
+
+    /// Builds a configuration keeping only the cells between the first and the last cell that is not `blank` and the head,
+    /// so configurations that only differ in blank cells are the same
+    fn trimmed(state: String, cells: Vec<bool>, head: usize, blank: bool) -> Self {
+        let first = cells
+            .iter()
+            .position(|v| *v != blank)
+            .unwrap_or(head)
+            .min(head);
+        let last = cells
+            .iter()
+            .rposition(|v| *v != blank)
+            .unwrap_or(head)
+            .max(head);
+
+        Self {
+            state,
+            cells: cells[first..=last].to_vec(),
+            head: head - first,
+        }
+    }
+}
+
+impl TuringMachine {
+    /// Create a new non-deterministic Turing machine from a string of code.
+    /// Instructions with the same state and value are all kept as choices for `explore`
+    /// instead of overwriting each other, so no `StateOverwrite`, `LibraryOverride` or `CompositionCollision`
+    /// warning is given.
+    /// No `DeadInstruction` warning is given either, since a state may only be reached by some of the choices.
+    /// The other methods run the machine deterministically, with the last instruction given for each choice
+    pub fn new_nondeterministic(code: &str) -> Result<(Self, Vec<CompilerWarning>), CompilerError> {
+        let parts = Self::parse_parts(code)?;
+
+        let mut choices: HashMap<InstructionKey, Vec<TuringInstruction>> = HashMap::new();
+        let composed: Vec<TuringInstruction> = parts
+            .composed
+            .iter()
+            .map(|(l, _)| l.get_instructions())
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flat_map(|i| i.into_values())
+            .collect();
+
+        for instruction in composed
+            .into_iter()
+            .chain(parts.instructions.iter().map(|(i, _)| i.clone()))
+        {
+            let list = choices.entry(instruction.key()).or_default();
+
+            if !list.contains(&instruction) {
+                list.push(instruction);
+            }
+        }
+
+        let (mut tm, warnings) = Self::from_machine_parts(parts)?;
+        Arc::make_mut(&mut tm.program).choices = Some(choices);
+
+        let warnings = warnings
+            .into_iter()
+            .filter(|w| {
+                !matches!(
+                    w,
+                    CompilerWarning::StateOverwrite { .. }
+                        | CompilerWarning::LibraryOverride { .. }
+                        | CompilerWarning::CompositionCollision { .. }
+                        | CompilerWarning::DeadInstruction { .. }
+                )
+            })
+            .collect();
+
+        Ok((tm, warnings))
+    }
+
+    /// Returns true if the machine was created with `new_nondeterministic`
+    pub fn is_nondeterministic(&self) -> bool {
+        self.program.choices.is_some()
+    }
+
+    /// Returns every instruction given for each state and value if the machine is non-deterministic
+    pub fn choices(&self) -> Option<&HashMap<InstructionKey, Vec<TuringInstruction>>> {
+        self.program.choices.as_ref()
+    }
+
+    /// Explores every computation from the current configuration breadth-first, up to `max_depth` steps,
+    /// until a branch reaches a final state. Configurations already reached by another branch are not explored again.
+    /// Deterministic machines only have one branch, which is explored without changing the machine
+    pub fn explore(&self, max_depth: usize) -> Exploration {
+        let start = Branch::trimmed(
+            String::from(self.current_state()),
+            self.tape.to_vec(),
+            self.tape_position,
+            self.blank,
+        );
+
+        // The instruction that led to each configuration, with the index of the previous one
+        let mut parents: Vec<Option<(usize, &TuringInstruction)>> = vec![None];
+        let mut visited: HashSet<Branch> = HashSet::from([start.clone()]);
+        let mut queue: VecDeque<(usize, Branch, usize)> = VecDeque::from([(0, start, 0)]);
+        let mut truncated = false;
+
+        while let Some((node, branch, depth)) = queue.pop_front() {
+            if self.final_states().contains(&branch.state) {
+                info!("A branch reached the final state {}", branch.state);
+
+                let mut path = Vec::new();
+                let mut current = node;
+                while let Some((parent, instruction)) = parents[current] {
+                    path.push(instruction.clone());
+                    current = parent;
+                }
+                path.reverse();
+
+                return Exploration {
+                    accepted: true,
+                    path,
+                    configurations: visited.len(),
+                    truncated,
+                };
+            }
+
+            let key = InstructionKey::new(&branch.state, branch.cells[branch.head]);
+            let instructions: Vec<&TuringInstruction> = match &self.program.choices {
+                Some(choices) => choices.get(&key).into_iter().flatten().collect(),
+                None => self.instructions().get(&key).into_iter().collect(),
+            };
+
+            if depth == max_depth {
+                truncated |= !instructions.is_empty();
+                continue;
+            }
+
+            for instruction in instructions {
+                let next = branch.apply(instruction, self.blank);
+
+                if visited.insert(next.clone()) {
+                    parents.push(Some((node, instruction)));
+                    queue.push_back((parents.len() - 1, next, depth + 1));
+                }
+            }
+        }
+
+        debug!("No branch reached a final state");
+
+        Exploration {
+            accepted: false,
+            path: Vec::new(),
+            configurations: visited.len(),
+            truncated,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{CompilerError, CompilerWarning, TuringMachine};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// How the compiler treats a problem in the code that is not an error by itself
+pub enum WarningLevel {
+    /// The problem is ignored
+    Allow,
+
+    /// The problem is given as a `CompilerWarning`
+    #[default]
+    Warn,
+
+    /// The problem is a `CompilerError`
+    Deny,
+}
+
+/// The maximum number of cells of a tape written with repetitions or numbers, unless `max_tape_cells` is changed
+pub const DEFAULT_MAX_TAPE_CELLS: usize = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// The options of the compiler for `new_with_options`. The default ones compile like `new`
+pub struct ParserOptions {
+    /// Accepts tapes without any 1, which are an error by default, in the code and in `set_input_tape`
+    pub allow_empty_tape: bool,
+
+    /// The number of blank cells an accepted tape without any 1 has at least, with the head on the first one
+    /// unless the code marks it. 1 by default, so `{};` is a single blank cell
+    #[serde(default = "default_empty_tape_cells")]
+    pub empty_tape_cells: usize,
+
+    /// Keeps the 0s at the start of the tape, which are dropped by default (except the ones from the head onwards),
+    /// so every cell of the tape written in the code is a cell of the initial tape and the head starts on the first one.
+    /// The tapes given to `set_input_tape` and `run_batch` are mapped onto the cells the same way.
+    ///
+    /// The blank cells that are always kept around the head are added before `origin`, so with this option
+    /// `read_cell(origin() + i)` is the cell `i` of the code and `head_offset` counts from the first cell written.
+    /// The default may keep the 0s in the next breaking release
+    pub preserve_leading_zeros: bool,
+
+    /// How an instruction given again for the same state and value is treated, a `StateOverwrite` warning by default
+    pub duplicate_instruction: WarningLevel,
+
+    /// Requires every state used by the machine to have an instruction or to be final,
+    /// so that the machine can only get stuck on a missing value
+    pub strict_states: bool,
+
+    /// The maximum number of cells a tape written with repetitions (`1^57`) or numbers (`{3, 5}`) expands to,
+    /// `DEFAULT_MAX_TAPE_CELLS` by default
+    #[serde(default = "default_max_tape_cells")]
+    pub max_tape_cells: usize,
+}
+
+fn default_empty_tape_cells() -> usize {
+    1
+}
+
+fn default_max_tape_cells() -> usize {
+    DEFAULT_MAX_TAPE_CELLS
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            allow_empty_tape: false,
+            empty_tape_cells: default_empty_tape_cells(),
+            preserve_leading_zeros: false,
+            duplicate_instruction: WarningLevel::default(),
+            strict_states: false,
+            max_tape_cells: DEFAULT_MAX_TAPE_CELLS,
+        }
+    }
+}
+
+impl TuringMachine {
+    /// Create a new Turing machine from a string of code, like `new`, compiling it with the given options
+    pub fn new_with_options(
+        code: &str,
+        options: &ParserOptions,
+    ) -> Result<(Self, Vec<CompilerWarning>), CompilerError> {
+        Self::from_machine_parts_with(Self::parse_parts_with(code, options)?, options)
+    }
+}
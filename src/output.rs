@@ -1,9 +1,38 @@
 use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TuringOutput {
+    /// The machine did not halt, with the number of steps executed
     Undefined(usize),
+
+    /// The machine halted, with the number of steps executed and the number of 1s on the tape
     Defined((usize, u32)),
+
+    /// The machine got stuck because there is no instruction for its state and the value it read
+    Stuck {
+        /// The number of steps executed before getting stuck
+        steps: usize,
+        /// The state the machine got stuck in
+        state: String,
+        /// The value read from the tape
+        value: bool,
+    },
+}
+
+impl TuringOutput {
+    /// Returns the number of steps executed
+    pub fn steps(&self) -> usize {
+        match self {
+            Self::Undefined(steps) | Self::Defined((steps, _)) | Self::Stuck { steps, .. } => {
+                *steps
+            }
+        }
+    }
+
+    /// Returns true if the machine halted
+    pub fn is_defined(&self) -> bool {
+        matches!(self, Self::Defined(_))
+    }
 }
 
 impl Default for TuringOutput {
@@ -17,6 +46,12 @@ impl Display for TuringOutput {
         match self {
             Self::Undefined(_) => write!(f, "Undefined"),
             Self::Defined((pos, val)) => write!(f, "Defined({}, {})", pos, val),
+            Self::Stuck { state, value, .. } => write!(
+                f,
+                "Undefined (stuck in {} reading {})",
+                state,
+                if *value { "1" } else { "0" }
+            ),
         }
     }
 }
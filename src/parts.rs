@@ -0,0 +1,590 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
+
+use log::{debug, error, warn};
+
+use crate::{
+    program::Program,
+    tape::{normalize_tape, Tape, TapeMode},
+    turing::DEFAULT_LOOP_THRESHOLD,
+    warnings::ErrorPosition,
+    CompilerError, CompilerWarning, InstructionKey, Library, ParserOptions, Rule,
+    TuringInstruction, TuringMachine, WarningLevel,
+};
+
+/// The parts a machine is built from, either parsed from the code or given programmatically.
+/// All the semantic checks are done when building the machine from them, so both ways behave the same
+pub(crate) struct MachineParts {
+    /// The instructions in the order they were given, with their position in the code
+    pub instructions: Vec<(TuringInstruction, ErrorPosition)>,
+
+    /// The initial state
+    pub initial_state: Option<String>,
+
+    /// The final states
+    pub final_states: Vec<String>,
+
+    /// The final states that accept the input
+    pub accepting_states: Vec<String>,
+
+    /// The final states that reject the input
+    pub rejecting_states: Vec<String>,
+
+    /// The cells of the tape, leading 0s included, with the position of the tape in the code
+    pub tape: Option<(Vec<bool>, ErrorPosition)>,
+
+    /// The cell of the tape where the head starts
+    pub head: Option<usize>,
+
+    /// Whether the tape is left-bounded
+    pub tape_mode: TapeMode,
+
+    /// The value of the blank cells added when the tape grows
+    pub blank: bool,
+
+    /// The description of the machine
+    pub description: Option<String>,
+
+    /// The name of the machine, given with `name = "adder";`
+    pub name: Option<String>,
+
+    /// The files to import with `import "sum.tm";` that are not resolved yet, with their position in the code
+    pub imports: Vec<(String, ErrorPosition)>,
+
+    /// The composed libraries, whose instructions come before the ones of the machine, with their position in the code
+    pub composed: Vec<(Library, ErrorPosition)>,
+
+    /// The code of the machine
+    pub code: String,
+
+    /// The warnings found while parsing the code, given before the ones of the checks
+    pub warnings: Vec<CompilerWarning>,
+}
+
+impl MachineParts {
+    pub fn new(code: String) -> Self {
+        Self {
+            instructions: Vec::new(),
+            initial_state: None,
+            final_states: Vec::new(),
+            accepting_states: Vec::new(),
+            rejecting_states: Vec::new(),
+            tape: None,
+            head: None,
+            tape_mode: TapeMode::default(),
+            blank: false,
+            description: None,
+            name: None,
+            imports: Vec::new(),
+            composed: Vec::new(),
+            code,
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Writes the code of the tape, with the cell of the head between brackets if it is not the first one
+    pub fn tape_code(&self) -> Option<String> {
+        let (cells, _) = self.tape.as_ref()?;
+
+        let mut code = String::from("{");
+        for (i, v) in cells.iter().enumerate() {
+            let value = if *v { "1" } else { "0" };
+
+            if self.head == Some(i) {
+                code += &format!("[{}]", value);
+            } else {
+                code += value;
+            }
+        }
+        code += "};";
+
+        Some(code)
+    }
+
+    /// Writes the code of a machine with these parts, with its instructions sorted like `instructions_sorted`
+    pub fn write_code(&self) -> String {
+        let mut code = String::new();
+
+        // The empty lines of the description separate its paragraphs
+        for line in self.description.iter().flat_map(|d| d.lines()) {
+            if line.is_empty() {
+                code += "///\n";
+            } else {
+                code += &format!("/// {}\n", line);
+            }
+        }
+
+        if let Some(name) = &self.name {
+            code += &format!("name = \"{}\";\n", name);
+        }
+
+        if self.tape_mode == TapeMode::LeftBounded {
+            code += "tape = bounded;\n";
+        }
+
+        if self.blank {
+            code += "blank = 1;\n";
+        }
+
+        if let Some(tape) = self.tape_code() {
+            code += &format!("{}\n", tape);
+        }
+
+        if let Some(state) = &self.initial_state {
+            code += &format!("I = {{{}}};\n", state);
+        }
+        if !self.final_states.is_empty() {
+            code += &format!("F = {{{}}};\n", self.final_states.join(", "));
+        }
+        if !self.accepting_states.is_empty() {
+            code += &format!("A = {{{}}};\n", self.accepting_states.join(", "));
+        }
+        if !self.rejecting_states.is_empty() {
+            code += &format!("R = {{{}}};\n", self.rejecting_states.join(", "));
+        }
+        // A function composed with several aliases is only defined once
+        let mut defined: Vec<&str> = Vec::new();
+        for (library, _) in self.composed.iter().filter(|(l, _)| !l.is_builtin()) {
+            if defined.contains(&library.name.as_ref()) {
+                continue;
+            }
+            defined.push(library.name.as_ref());
+
+            for line in library.description.lines() {
+                code += &format!("/// {}\n", line);
+            }
+            code += &format!("fn {} {{\n", library.name);
+            code += &format!("    I = {{{}}};\n", library.initial_state);
+            code += &format!("    F = {{{}}};\n", library.final_state);
+            for line in library.code.lines() {
+                code += &format!("    {}\n", line);
+            }
+            code += "}\n";
+        }
+        if !self.composed.is_empty() {
+            let names: Vec<String> = self
+                .composed
+                .iter()
+                .map(|(l, _)| l.composed_name())
+                .collect();
+            code += &format!("compose = {{{}}};\n", names.join(", "));
+        }
+        code += "\n";
+
+        let mut sorted: Vec<&TuringInstruction> =
+            self.instructions.iter().map(|(i, _)| i).collect();
+        sorted.sort_by(|a, b| a.cmp_by_state(b));
+
+        for instruction in sorted {
+            for line in instruction.doc.iter().flat_map(|d| d.lines()) {
+                code += &format!("/// {}\n", line);
+            }
+            code += &format!("{};\n", instruction);
+        }
+
+        code
+    }
+}
+
+impl TuringMachine {
+    /// Create a new Turing machine from its parts instead of its code.
+    /// The parts are checked exactly like the parsed code, and `code` is filled in by writing them as code
+    pub fn from_parts(
+        instructions: Vec<TuringInstruction>,
+        initial_state: &str,
+        final_states: &[&str],
+        tape: &[bool],
+    ) -> Result<(Self, Vec<CompilerWarning>), CompilerError> {
+        let mut parts = MachineParts::new(String::new());
+
+        parts.instructions = instructions
+            .into_iter()
+            .map(|i| (i, ErrorPosition::new((0, 0), None)))
+            .collect();
+        parts.initial_state = Some(String::from(initial_state));
+        parts.final_states = final_states.iter().map(|s| String::from(*s)).collect();
+        parts.tape = Some((tape.to_vec(), ErrorPosition::new((0, 0), None)));
+        parts.code = parts.write_code();
+
+        Self::from_machine_parts(parts)
+    }
+
+    /// Writes the code of the machine as it was created, in a canonical form: the description, the tape,
+    /// the initial and final states, the composed libraries and the instructions given in the code,
+    /// sorted like `instructions_sorted`. Compiling it gives back a machine equal to this one
+    pub fn to_code(&self) -> String {
+        self.machine_parts().write_code()
+    }
+
+    /// Returns the parts of the machine as it was created, with its current instructions and states.
+    /// The composed libraries are kept if all of their instructions are still there, otherwise they are written out
+    pub(crate) fn machine_parts(&self) -> MachineParts {
+        let mut parts = MachineParts::new(String::new());
+
+        parts.description = self.program.description.clone();
+        parts.name = self.program.name.clone();
+        parts.tape_mode = self.tape_mode;
+        parts.blank = self.blank;
+        parts.initial_state = Some(String::from(self.initial_state()));
+        parts.final_states = self
+            .final_states()
+            .iter()
+            .filter(|s| {
+                !self.accepting_states().contains(s) && !self.rejecting_states().contains(s)
+            })
+            .cloned()
+            .collect();
+        parts.accepting_states = self.accepting_states().to_vec();
+        parts.rejecting_states = self.rejecting_states().to_vec();
+
+        // The tape given in the code, whose cells are all there since only its left side is padded when it is loaded
+        let cells = &self.initial_tape[self.initial_origin..];
+        let head = self.initial_tape_position - self.initial_origin;
+        parts.tape = Some((cells.to_vec(), ErrorPosition::new((0, 0), None)));
+        // The head is marked if the tape starts with a blank, since the blanks before an unmarked head are skipped
+        parts.head = (head > 0 || cells.first() == Some(&self.blank)).then_some(head);
+
+        let mut composed: HashMap<InstructionKey, TuringInstruction> = HashMap::new();
+        for library in self.composed_libs() {
+            composed.extend(library.get_instructions().unwrap_or_default());
+        }
+        let keep = composed
+            .iter()
+            .all(|(key, instruction)| self.instructions().get(key) == Some(instruction));
+
+        if keep {
+            parts.composed = self
+                .composed_libs()
+                .iter()
+                .map(|l| (l.clone(), ErrorPosition::new((0, 0), None)))
+                .collect();
+        }
+
+        parts.instructions = self
+            .instructions_sorted()
+            .into_iter()
+            .filter(|i| !keep || composed.get(&i.key()) != Some(*i))
+            .map(|i| (i.clone(), ErrorPosition::new((0, 0), None)))
+            .collect();
+
+        parts
+    }
+
+    /// Writes the code of the machine again from its parts, after changing them.
+    /// The composed libraries that were written out are not composed anymore
+    pub(crate) fn regenerate_code(&mut self) {
+        let parts = self.machine_parts();
+
+        let program = Arc::make_mut(&mut self.program);
+
+        program.composed_libs = parts.composed.iter().map(|(l, _)| l.clone()).collect();
+        program.code = parts.write_code();
+
+        // The instructions of the libraries written out are now given in the code
+        if program.composed_libs.is_empty() {
+            program.libraries.clear();
+        }
+    }
+
+    /// Checks the parts of a machine and builds it with the default options
+    pub(crate) fn from_machine_parts(
+        parts: MachineParts,
+    ) -> Result<(Self, Vec<CompilerWarning>), CompilerError> {
+        Self::from_machine_parts_with(parts, &ParserOptions::default())
+    }
+
+    /// Checks the parts of a machine with the given options and builds it
+    pub(crate) fn from_machine_parts_with(
+        parts: MachineParts,
+        options: &ParserOptions,
+    ) -> Result<(Self, Vec<CompilerWarning>), CompilerError> {
+        if let Some((path, position)) = parts.imports.first() {
+            error!("The file {} cannot be imported without a resolver", path);
+
+            return Err(CompilerError::ImportError {
+                path: path.clone(),
+                position: *position,
+                source: String::from(
+                    "No resolver was given to read the files, see new_with_resolver",
+                ),
+            });
+        }
+
+        let mut warnings: Vec<CompilerWarning> = parts.warnings;
+
+        let Some((cells, tape_position)) = parts.tape else {
+            error!("No tape given");
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: String::from("No tape given"),
+                code: parts.code,
+                expected: Rule::tape,
+                found: None,
+            });
+        };
+
+        let (tape, skipped) =
+            normalize_tape(&cells, parts.head, parts.blank, options).map_err(|message| {
+                CompilerError::SyntaxError {
+                    position: tape_position,
+                    message,
+                    code: cells.iter().map(|v| if *v { '1' } else { '0' }).collect(),
+                    expected: Rule::tape,
+                    found: None,
+                }
+            })?;
+
+        if let Some(state) = parts
+            .accepting_states
+            .iter()
+            .find(|s| parts.rejecting_states.contains(s))
+        {
+            error!("The state {} both accepts and rejects", state);
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: format!("The state {} cannot both accept and reject", state),
+                code: parts.code,
+                expected: Rule::rejecting_states,
+                found: None,
+            });
+        }
+
+        // The accepting and rejecting states are final states too
+        let mut final_states = parts.final_states;
+        for state in parts
+            .accepting_states
+            .iter()
+            .chain(parts.rejecting_states.iter())
+        {
+            if !final_states.contains(state) {
+                final_states.push(state.clone());
+            }
+        }
+
+        if final_states.is_empty() {
+            error!("No final state given");
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: String::from("No final state given"),
+                code: parts.code,
+                expected: Rule::final_state,
+                found: None,
+            });
+        }
+
+        let Some(initial_state) = parts.initial_state.filter(|s| !s.is_empty()) else {
+            error!("No initial state given");
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: String::from("No initial state given"),
+                code: parts.code,
+                expected: Rule::initial_state,
+                found: None,
+            });
+        };
+
+        let mut instructions: HashMap<InstructionKey, TuringInstruction> = HashMap::new();
+        // The library each instruction comes from, if any
+        let mut libraries: HashMap<InstructionKey, String> = HashMap::new();
+
+        for (library, position) in parts.composed.iter() {
+            let mut composed: Vec<_> = library.get_instructions()?.into_iter().collect();
+            composed.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            for (key, instruction) in composed {
+                if let Some(previous) = libraries.insert(key.clone(), library.composed_name()) {
+                    warn!(
+                        "The library {} overwrites the instruction {} of the library {}, compose them with an alias",
+                        library.composed_name(), instructions[&key], previous
+                    );
+
+                    warnings.push(CompilerWarning::CompositionCollision {
+                        position: *position,
+                        state: key.state.clone(),
+                        value_from: key.read,
+                        library: previous,
+                        composed: library.composed_name(),
+                    });
+                }
+                instructions.insert(key, instruction);
+            }
+        }
+
+        // The instructions given in the code, to find the dead ones once the machine is built
+        let mut given: Vec<(String, bool, ErrorPosition)> = Vec::new();
+
+        for (instruction, position) in parts.instructions {
+            let key = instruction.key();
+            given.push((key.state.clone(), key.read, position));
+
+            if let Some(library) = libraries.remove(&key) {
+                warn!(
+                    "Instruction {} overwrites the one of the library {}",
+                    instruction, library
+                );
+
+                warnings.push(CompilerWarning::LibraryOverride {
+                    position,
+                    state: instruction.from_state.clone(),
+                    value_from: instruction.from_value,
+                    library,
+                })
+            } else if instructions.contains_key(&key) {
+                match options.duplicate_instruction {
+                    WarningLevel::Allow => {}
+                    WarningLevel::Warn => {
+                        warn!("Instruction {} already exists, overwriting it", instruction);
+
+                        warnings.push(CompilerWarning::StateOverwrite {
+                            position,
+                            state: instruction.from_state.clone(),
+                            value_from: instruction.from_value,
+                        })
+                    }
+                    WarningLevel::Deny => {
+                        error!("Instruction {} already exists", instruction);
+
+                        return Err(CompilerError::SyntaxError {
+                            position,
+                            message: format!(
+                                "The instruction for the state {} and the value {} is given twice",
+                                instruction.from_state,
+                                u8::from(instruction.from_value)
+                            ),
+                            code: instruction.to_string(),
+                            expected: Rule::instruction,
+                            found: None,
+                        });
+                    }
+                }
+            }
+            instructions.insert(key, instruction);
+        }
+
+        if !instructions.keys().any(|key| key.state == initial_state)
+            && !final_states.contains(&initial_state)
+        {
+            error!("The initial state {} is not known", initial_state);
+
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: format!(
+                    "The initial state {} has no instructions and is not a final state",
+                    initial_state
+                ),
+                code: parts.code,
+                expected: Rule::initial_state,
+                found: None,
+            });
+        }
+
+        if options.strict_states {
+            let known = |state: &str| {
+                final_states.iter().any(|s| s == state)
+                    || instructions.keys().any(|key| key.state == *state)
+            };
+
+            let mut used: Vec<&TuringInstruction> = instructions.values().collect();
+            used.sort_by(|a, b| a.cmp_by_state(b));
+
+            if let Some(instruction) = used.into_iter().find(|i| !known(&i.to_state)) {
+                error!(
+                    "The state {} has no instructions and is not a final state",
+                    instruction.to_state
+                );
+
+                // The last instruction given for a state and value is the one kept
+                let position = given
+                    .iter()
+                    .rfind(|(s, v, _)| *s == instruction.from_state && *v == instruction.from_value)
+                    .map_or(ErrorPosition::new((0, 0), None), |(_, _, p)| *p);
+
+                return Err(CompilerError::SyntaxError {
+                    position,
+                    message: format!(
+                        "The state {} has no instructions and is not a final state",
+                        instruction.to_state
+                    ),
+                    code: instruction.to_string(),
+                    expected: Rule::state,
+                    found: None,
+                });
+            }
+        }
+
+        debug!("The instructions are {:?}", instructions);
+
+        let mut program = Program::new(instructions, final_states, &initial_state);
+        program.description = parts.description;
+        program.name = parts.name;
+        program.composed_libs = parts.composed.iter().map(|(l, _)| l.clone()).collect();
+        program.code = parts.code;
+        program.options = options.clone();
+        program.accepting_states = parts.accepting_states;
+        program.rejecting_states = parts.rejecting_states;
+        program.libraries = libraries;
+
+        let state_count = program.states.len();
+        let mut tm = Self {
+            current_state: program.initial_state,
+            program: Arc::new(program),
+            previous_state: None,
+            initial_tape_position: 0,
+            tape_position: 0,
+            initial_origin: 0,
+            origin: 0,
+            initial_tape: Tape::default(),
+            tape: Tape::default(),
+            tape_mode: parts.tape_mode,
+            blank: parts.blank,
+            steps: 0,
+            frequencies: vec![[0; 2]; state_count],
+            max_frequency: None,
+            loop_threshold: DEFAULT_LOOP_THRESHOLD,
+            history: VecDeque::new(),
+            history_capacity: 0,
+            breakpoints: HashSet::new(),
+            initial_breakpoint_hit: false,
+            cell_watches: HashSet::new(),
+            write_watches: HashSet::new(),
+            watch_hit: None,
+            trace: None,
+            trace_tape: Vec::new(),
+            stats: None,
+            coverage: None,
+            configurations: None,
+            cycle_detected: false,
+            fast_forward: false,
+            tape_limit: None,
+            checkpoints: None,
+        };
+
+        tm.load_tape(tape, parts.head.map_or(0, |head| head - skipped));
+
+        // The states reached through the instructions of the libraries are also reachable
+        let reachable = tm.reachable_states();
+        for (state, value, position) in given {
+            if !reachable.contains(&state) {
+                warn!(
+                    "The instruction of ({}, {}) can never be executed, since its state is not reachable from the initial state",
+                    state,
+                    u8::from(value)
+                );
+
+                warnings.push(CompilerWarning::DeadInstruction {
+                    position,
+                    state,
+                    value,
+                });
+            }
+        }
+
+        Ok((tm, warnings))
+    }
+}
@@ -0,0 +1,485 @@
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    natural_cmp, InstructionKey, Library, Movement, ParserOptions, TuringInstruction, TuringMachine,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+/// The id of an interned state name. Ids are given in the order the names are first found
+pub(crate) struct StateId(pub u32);
+
+impl StateId {
+    /// The index of the state in the list of names
+    pub(crate) fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<String>", into = "Vec<String>")]
+/// The names of the states of a machine, so that the machine can refer to them by id while running
+pub(crate) struct States {
+    names: Vec<String>,
+    ids: HashMap<String, StateId>,
+}
+
+impl States {
+    /// Returns the id of the state with the given name, giving it a new id if it had none
+    pub(crate) fn intern(&mut self, name: &str) -> StateId {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+
+        let id = StateId(self.names.len() as u32);
+        self.names.push(String::from(name));
+        self.ids.insert(String::from(name), id);
+
+        id
+    }
+
+    /// Gives a new name to a state, keeping its id
+    pub(crate) fn rename(&mut self, old: &str, new: &str) {
+        if let Some(id) = self.ids.remove(old) {
+            self.names[id.index()] = String::from(new);
+            self.ids.insert(String::from(new), id);
+        }
+    }
+
+    /// Returns the id of the state with the given name, or None if it has none
+    pub(crate) fn id(&self, name: &str) -> Option<StateId> {
+        self.ids.get(name).copied()
+    }
+
+    /// Returns the name of the state with the given id
+    pub(crate) fn name(&self, id: StateId) -> &str {
+        &self.names[id.index()]
+    }
+
+    /// Returns the number of interned states
+    pub(crate) fn len(&self) -> usize {
+        self.names.len()
+    }
+}
+
+impl From<Vec<String>> for States {
+    fn from(names: Vec<String>) -> Self {
+        let mut states = Self::default();
+        for name in names {
+            states.intern(&name);
+        }
+
+        states
+    }
+}
+
+impl From<States> for Vec<String> {
+    fn from(states: States) -> Self {
+        states.names
+    }
+}
+
+/// A (state, value) pair that can be looked up in the maps keyed by `(String, bool)` or `InstructionKey` without allocating a `String`
+pub(crate) trait StateKey {
+    fn key(&self) -> (&str, bool);
+}
+
+impl StateKey for (String, bool) {
+    fn key(&self) -> (&str, bool) {
+        (self.0.as_str(), self.1)
+    }
+}
+
+impl StateKey for InstructionKey {
+    fn key(&self) -> (&str, bool) {
+        (self.state.as_str(), self.read)
+    }
+}
+
+impl StateKey for (&str, bool) {
+    fn key(&self) -> (&str, bool) {
+        *self
+    }
+}
+
+impl<'a> Borrow<dyn StateKey + 'a> for (String, bool) {
+    fn borrow(&self) -> &(dyn StateKey + 'a) {
+        self
+    }
+}
+
+impl<'a> Borrow<dyn StateKey + 'a> for InstructionKey {
+    fn borrow(&self) -> &(dyn StateKey + 'a) {
+        self
+    }
+}
+
+// Hashes like `(String, bool)` and `InstructionKey`, since a `String` hashes like its `str`
+impl Hash for dyn StateKey + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
+impl PartialEq for dyn StateKey + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for dyn StateKey + '_ {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An instruction of the transition table, where the next state is already interned
+pub(crate) struct CompiledInstruction {
+    /// The value to write on the tape
+    pub to_value: bool,
+
+    /// The movement of the head
+    pub movement: Movement,
+
+    /// The id of the next state
+    pub to_state: StateId,
+}
+
+#[derive(Debug, Clone, Default)]
+/// The transition table of a machine, indexed by state id and then by the read value
+pub(crate) struct CompiledProgram {
+    /// The instructions of each state, with the read value as index
+    pub transitions: Vec<[Option<CompiledInstruction>; 2]>,
+
+    /// The instructions each compiled instruction comes from, with the same indices as `transitions`
+    pub sources: Vec<[Option<TuringInstruction>; 2]>,
+
+    /// Whether each state is a final state
+    pub finals: Vec<bool>,
+}
+
+/// Every instruction given for each state and value of a non-deterministic machine
+pub(crate) type Choices = HashMap<InstructionKey, Vec<TuringInstruction>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "ProgramData", into = "ProgramData")]
+/// The instructions and final states of a machine, along with the transition table that is used while running.
+/// The table is built again when deserializing
+pub(crate) struct Program {
+    /// The dictionary of instructions, indexed by state name and value
+    pub instructions: HashMap<InstructionKey, TuringInstruction>,
+
+    /// The final states of the machine
+    pub final_states: Vec<String>,
+
+    /// The final states that accept the input
+    pub accepting_states: Vec<String>,
+
+    /// The final states that reject the input
+    pub rejecting_states: Vec<String>,
+
+    /// Every instruction given for each state and value, if the machine is non-deterministic.
+    /// Only used by `explore`, the transition table is built from `instructions`
+    pub choices: Option<Choices>,
+
+    /// The composed library each instruction comes from, as it is written in `compose` (e.g. `sum as s`), by state and value.
+    /// The instructions given in the code are not in the map
+    pub libraries: HashMap<InstructionKey, String>,
+
+    /// The names of the states of the machine
+    pub states: States,
+
+    /// The state the machine starts in
+    pub initial_state: StateId,
+
+    /// The description of the machine, found in the `///` comments at the top of the code
+    pub description: Option<String>,
+
+    /// The name of the machine, given with `name = "adder";` in the code
+    pub name: Option<String>,
+
+    /// The composed libraries the machine uses, only as information since their instructions are already in `instructions`
+    pub composed_libs: Vec<Library>,
+
+    /// The code the machine was compiled from, or written from its parts
+    pub code: String,
+
+    /// The options the machine was compiled with, which `set_input_tape` checks the new tapes with too
+    pub options: ParserOptions,
+
+    /// The transition table, indexed by state id and value
+    pub compiled: CompiledProgram,
+}
+
+impl Program {
+    /// Creates a program from its instructions and final states, interning the initial state first
+    pub fn new(
+        instructions: HashMap<InstructionKey, TuringInstruction>,
+        final_states: Vec<String>,
+        initial_state: &str,
+    ) -> Self {
+        let mut states = States::default();
+        let initial_state = states.intern(initial_state);
+
+        let mut program = Self {
+            instructions,
+            final_states,
+            accepting_states: Vec::new(),
+            rejecting_states: Vec::new(),
+            choices: None,
+            libraries: HashMap::new(),
+            states,
+            initial_state,
+            description: None,
+            name: None,
+            composed_libs: Vec::new(),
+            code: String::new(),
+            options: ParserOptions::default(),
+            compiled: CompiledProgram::default(),
+        };
+        program.compile();
+
+        program
+    }
+
+    /// Builds the transition table. The state names are interned first, so ids given before are kept
+    pub fn compile(&mut self) {
+        for instruction in self.instructions.values() {
+            self.states.intern(&instruction.from_state);
+            self.states.intern(&instruction.to_state);
+        }
+
+        for state in self.final_states.iter() {
+            self.states.intern(state);
+        }
+
+        let mut compiled = CompiledProgram {
+            transitions: vec![[None; 2]; self.states.len()],
+            sources: vec![[None, None]; self.states.len()],
+            finals: vec![false; self.states.len()],
+        };
+
+        for (key, instruction) in self.instructions.iter() {
+            let id = self.states.intern(&key.state);
+
+            compiled.transitions[id.index()][key.read as usize] = Some(CompiledInstruction {
+                to_value: instruction.to_value,
+                movement: instruction.movement,
+                to_state: self.states.intern(&instruction.to_state),
+            });
+            compiled.sources[id.index()][key.read as usize] = Some(instruction.clone());
+        }
+
+        for state in self.final_states.iter() {
+            compiled.finals[self.states.intern(state).index()] = true;
+        }
+
+        self.compiled = compiled;
+    }
+
+    /// Returns the compiled instruction for the given state and value
+    pub fn transition(&self, state: StateId, value: bool) -> Option<CompiledInstruction> {
+        self.compiled.transitions.get(state.index())?[value as usize]
+    }
+
+    /// Returns the instruction for the given state and value
+    pub fn get(&self, state: StateId, value: bool) -> Option<&TuringInstruction> {
+        self.compiled.sources.get(state.index())?[value as usize].as_ref()
+    }
+
+    /// Returns true if the given state is a final state
+    pub fn is_final(&self, state: StateId) -> bool {
+        self.compiled
+            .finals
+            .get(state.index())
+            .copied()
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+/// The serialized form of a program, which leaves out the table indexed by state id
+struct ProgramData {
+    #[serde(with = "crate::serialization::instruction_map")]
+    instructions: HashMap<InstructionKey, TuringInstruction>,
+    final_states: Vec<String>,
+    #[serde(default, skip_serializing_if = "crate::serialization::skip_empty")]
+    accepting_states: Vec<String>,
+    #[serde(default, skip_serializing_if = "crate::serialization::skip_empty")]
+    rejecting_states: Vec<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serialization::skip_none",
+        with = "crate::serialization::choice_map"
+    )]
+    choices: Option<Choices>,
+    #[serde(
+        default,
+        skip_serializing_if = "crate::serialization::skip_empty_map",
+        with = "crate::serialization::library_map"
+    )]
+    libraries: HashMap<InstructionKey, String>,
+    states: States,
+    initial_state: StateId,
+    description: Option<String>,
+    #[serde(default)]
+    name: Option<String>,
+    composed_libs: Vec<Library>,
+    code: String,
+    #[serde(default)]
+    options: ParserOptions,
+}
+
+impl From<ProgramData> for Program {
+    fn from(data: ProgramData) -> Self {
+        let mut program = Self {
+            instructions: data.instructions,
+            final_states: data.final_states,
+            accepting_states: data.accepting_states,
+            rejecting_states: data.rejecting_states,
+            choices: data.choices,
+            libraries: data.libraries,
+            states: data.states,
+            initial_state: data.initial_state,
+            description: data.description,
+            name: data.name,
+            composed_libs: data.composed_libs,
+            code: data.code,
+            options: data.options,
+            compiled: CompiledProgram::default(),
+        };
+        program.compile();
+
+        program
+    }
+}
+
+impl From<Program> for ProgramData {
+    fn from(program: Program) -> Self {
+        Self {
+            instructions: program.instructions,
+            final_states: program.final_states,
+            accepting_states: program.accepting_states,
+            rejecting_states: program.rejecting_states,
+            choices: program.choices,
+            libraries: program.libraries,
+            states: program.states,
+            initial_state: program.initial_state,
+            description: program.description,
+            name: program.name,
+            composed_libs: program.composed_libs,
+            code: program.code,
+            options: program.options,
+        }
+    }
+}
+
+impl TuringMachine {
+    /// Returns the dictionary of instructions of the machine, indexed by their `InstructionKey`
+    pub fn instructions(&self) -> &HashMap<InstructionKey, TuringInstruction> {
+        &self.program.instructions
+    }
+
+    /// Returns the final states of the machine. If the machine reaches one of these states, it will stop
+    pub fn final_states(&self) -> &[String] {
+        &self.program.final_states
+    }
+
+    /// Returns the description of the machine, found in the `///` comments at the top of the code
+    pub fn description(&self) -> Option<&str> {
+        self.program.description.as_deref()
+    }
+
+    /// Returns the name of the machine, given with `name = "adder";` in the code
+    pub fn name(&self) -> Option<&str> {
+        self.program.name.as_deref()
+    }
+
+    /// Returns the composed libraries that the machine uses.
+    /// Only as information, since their instructions are already compiled into the machine
+    pub fn composed_libs(&self) -> &[Library] {
+        &self.program.composed_libs
+    }
+
+    /// Returns the code of the machine, either the one it was compiled from or the one written from its parts
+    pub fn code(&self) -> &str {
+        &self.program.code
+    }
+
+    /// Returns the name of the current state
+    pub fn current_state(&self) -> &str {
+        self.program.states.name(self.current_state)
+    }
+
+    /// Returns the name of the state the machine was in before the last step, or None if no step was executed
+    pub fn previous_state(&self) -> Option<&str> {
+        self.previous_state.map(|id| self.program.states.name(id))
+    }
+
+    /// Returns the name of the state the machine starts in
+    pub fn initial_state(&self) -> &str {
+        self.program.states.name(self.program.initial_state)
+    }
+
+    /// Returns true if the given state is one of the final states
+    pub fn is_final(&self, state: &str) -> bool {
+        self.program.final_states.iter().any(|s| s == state)
+    }
+
+    /// Returns the names of every state of the machine, sorted in natural order like `transitions` (see `natural_cmp`):
+    /// the states of the instructions, the initial state and the final states
+    pub fn states(&self) -> Vec<&str> {
+        let mut states: Vec<&str> = self
+            .program
+            .instructions
+            .values()
+            .flat_map(|i| [i.from_state.as_str(), i.to_state.as_str()])
+            .chain(self.program.final_states.iter().map(String::as_str))
+            .chain([self.initial_state()])
+            .collect();
+        states.sort_unstable_by(|a, b| natural_cmp(a, b));
+        states.dedup();
+
+        states
+    }
+
+    /// Returns the values read or written by the instructions, sorted
+    pub fn alphabet(&self) -> Vec<bool> {
+        [false, true]
+            .into_iter()
+            .filter(|v| {
+                self.program
+                    .instructions
+                    .values()
+                    .any(|i| i.from_value == *v || i.to_value == *v)
+            })
+            .collect()
+    }
+
+    /// Returns the instructions of the machine sorted by state, in natural order (`q2` before `q10`, see `natural_cmp`),
+    /// and value, so they are always given in the same order unlike the ones of `instructions()`
+    pub fn instructions_sorted(&self) -> Vec<&TuringInstruction> {
+        let mut instructions: Vec<&TuringInstruction> =
+            self.program.instructions.values().collect();
+        instructions.sort_by(|a, b| a.cmp_by_state(b));
+
+        instructions
+    }
+
+    /// Returns the instructions of the machine sorted like `instructions_sorted`
+    pub fn transitions(&self) -> Vec<&TuringInstruction> {
+        self.instructions_sorted()
+    }
+
+    /// Returns the id of the state with the given name, giving it a new one if it had none.
+    /// The program is only copied if it is shared with other machines and the name is new
+    pub(crate) fn intern_state(&mut self, name: &str) -> StateId {
+        match self.program.states.id(name) {
+            Some(id) => id,
+            None => Arc::make_mut(&mut self.program).states.intern(name),
+        }
+    }
+}
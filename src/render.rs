@@ -0,0 +1,102 @@
+use crate::TuringMachine;
+
+impl TuringMachine {
+    /// Renders only the `radius` cells at each side of the head, with the head cell between brackets.
+    /// Hidden cells are replaced by their count and an ellipsis, e.g. `…(120) 0 1 1 [1] 0 0 (85)…`
+    pub fn render_window(&self, radius: usize) -> String {
+        let start = self.tape_position.saturating_sub(radius);
+        let end = (self.tape_position + radius + 1).min(self.tape.len());
+
+        let mut parts: Vec<String> = Vec::new();
+
+        if start > 0 {
+            parts.push(format!("…({start})"));
+        }
+
+        for (i, v) in self.tape[start..end].iter().enumerate() {
+            let value = if *v { "1" } else { "0" };
+
+            if start + i == self.tape_position {
+                parts.push(format!("[{value}]"));
+            } else {
+                parts.push(String::from(value));
+            }
+        }
+
+        if end < self.tape.len() {
+            parts.push(format!("({})…", self.tape.len() - end));
+        }
+
+        parts.join(" ")
+    }
+}
+
+/// A run-length compressed rendering of the tape of a Turing machine, e.g. `0{3} 1{57} 0 1{12} 0{4} @cell 61 (run 3)`.
+///
+/// Runs of a single cell are rendered without a count,
+/// and the annotation gives the index of the head cell and of the run (starting at 0) that contains it
+pub struct Compressed<'a>(pub &'a TuringMachine);
+
+impl Compressed<'_> {
+    /// Returns the runs of the tape as (value, length) pairs
+    pub fn runs(&self) -> Vec<(bool, usize)> {
+        let mut runs: Vec<(bool, usize)> = Vec::new();
+
+        for v in self.0.tape.iter() {
+            match runs.last_mut() {
+                Some((value, length)) if value == v => *length += 1,
+                _ => runs.push((*v, 1)),
+            }
+        }
+
+        runs
+    }
+
+    /// Returns the index of the run that contains the head
+    pub fn head_run(&self) -> usize {
+        let mut start = 0;
+
+        for (i, (_, length)) in self.runs().iter().enumerate() {
+            start += length;
+
+            if self.0.tape_position < start {
+                return i;
+            }
+        }
+
+        0
+    }
+}
+
+impl std::fmt::Display for Compressed<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (value, length) in self.runs() {
+            let value = if value { "1" } else { "0" };
+
+            if length == 1 {
+                write!(f, "{value} ")?;
+            } else {
+                write!(f, "{value}{{{length}}} ")?;
+            }
+        }
+
+        write!(
+            f,
+            "@cell {} (run {})",
+            self.0.tape_position,
+            self.head_run()
+        )
+    }
+}
+
+impl TuringMachine {
+    /// Returns a run-length compressed view of the tape that can be used with `format!`
+    pub fn compressed(&self) -> Compressed<'_> {
+        Compressed(self)
+    }
+
+    /// Renders the tape with runs of equal values collapsed, e.g. `0{3} 1{57} 0 1{12} 0{4} @cell 61 (run 3)`
+    pub fn render_compressed(&self) -> String {
+        self.compressed().to_string()
+    }
+}
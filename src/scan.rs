@@ -0,0 +1,101 @@
+use crate::{Movement, TapeMode, TuringMachine};
+
+impl TuringMachine {
+    /// Makes `run` execute the scanning loops at once: an instruction `(q, v, v, R, q)` or `(q, v, v, L, q)`
+    /// moves the head straight to the next cell with a different value, counting every cell skipped as a step.
+    /// The step counter, the frequencies, the coverage and the tape end up exactly like stepping cell by cell.
+    ///
+    /// The loops are still executed step by step while the trace, the history, the statistics,
+    /// the watchpoints, cycle detection or the checkpoints are enabled, since they need to see every step
+    pub fn enable_fast_forward(&mut self) {
+        self.fast_forward = true;
+    }
+
+    /// Makes `run` execute every step one by one again
+    pub fn disable_fast_forward(&mut self) {
+        self.fast_forward = false;
+    }
+
+    /// Executes the next steps at once if the machine is in a scanning loop, up to `max_steps` steps.
+    /// Only the cells already on the tape are skipped, the blank cells after them are added step by step.
+    /// Returns the number of steps executed, which is 0 if the next instruction is not a scanning loop
+    pub(crate) fn skip_scan(&mut self, max_steps: usize) -> usize {
+        if self.trace.is_some()
+            || self.history_capacity > 0
+            || self.stats.is_some()
+            || self.configurations.is_some()
+            || self.checkpoints.is_some()
+            || !self.cell_watches.is_empty()
+            || !self.write_watches.is_empty()
+        {
+            return 0;
+        }
+
+        let state = self.current_state;
+        let value = self.tape[self.tape_position];
+
+        // The step executed from a final state is the halting one
+        if self.program.is_final(state) {
+            return 0;
+        }
+        let Some(transition) = self.program.transition(state, value) else {
+            return 0;
+        };
+        if transition.to_state != state || transition.to_value != value {
+            return 0;
+        }
+
+        let skipped = match transition.movement {
+            Movement::RIGHT => {
+                let cells = self.tape[self.tape_position..]
+                    .iter()
+                    .take_while(|v| **v == value)
+                    .count();
+                let limit = self.head_bounds().1.saturating_sub(self.head_offset()) as usize;
+                let skipped = cells.min(limit).min(max_steps);
+
+                self.tape_position += skipped;
+                skipped
+            }
+            Movement::LEFT => {
+                let cells = self.tape[..=self.tape_position]
+                    .iter()
+                    .rev()
+                    .take_while(|v| **v == value)
+                    .count();
+
+                // The head stops on the first cell, where moving left either grows the tape or is an error
+                let first = match self.tape_mode {
+                    TapeMode::Unbounded => self.tape_position,
+                    TapeMode::LeftBounded => self.head_offset().max(0) as usize,
+                };
+                let limit = self.head_offset().saturating_sub(self.head_bounds().0) as usize;
+                let skipped = cells.min(first).min(limit).min(max_steps);
+
+                self.tape_position -= skipped;
+                skipped
+            }
+            Movement::HALT | Movement::STAY => 0,
+        };
+
+        if skipped == 0 {
+            return 0;
+        }
+
+        // Growing the tape once is the same as growing it on every step, since it only depends on the head
+        self.pad();
+
+        self.watch_hit = None;
+        self.previous_state = Some(state);
+        self.steps += skipped;
+
+        if let Some(coverage) = &mut self.coverage {
+            let name = self.program.states.name(state);
+            *coverage.entry((String::from(name), value)).or_default() += skipped;
+        }
+
+        self.count_frequency(state, value, skipped);
+
+        skipped
+    }
+}
@@ -0,0 +1,152 @@
+//! Serde representations for the maps of a Turing machine whose keys are not representable in formats like JSON,
+//! and the fields that are only written when they are not empty
+
+use std::{cell::Cell, collections::HashMap};
+
+thread_local! {
+    /// Whether the fields skipped when empty are written anyway, like formats without field names need
+    static EVERY_FIELD: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` writing every field of the machines, even the empty ones, for formats that read the fields by their order
+#[cfg(any(test, feature = "binary"))]
+pub(crate) fn with_every_field<T>(f: impl FnOnce() -> T) -> T {
+    let previous = EVERY_FIELD.replace(true);
+    let result = f();
+    EVERY_FIELD.set(previous);
+
+    result
+}
+
+/// Skips an optional field that is None, unless every field is written
+pub(crate) fn skip_none<T>(value: &Option<T>) -> bool {
+    value.is_none() && !EVERY_FIELD.get()
+}
+
+/// Skips a list that is empty, unless every field is written
+pub(crate) fn skip_empty<T>(list: &[T]) -> bool {
+    list.is_empty() && !EVERY_FIELD.get()
+}
+
+/// Skips a map that is empty, unless every field is written
+pub(crate) fn skip_empty_map<K, V>(map: &HashMap<K, V>) -> bool {
+    map.is_empty() && !EVERY_FIELD.get()
+}
+
+/// Serializes the instructions as a list sorted like `instructions_sorted`, and rebuilds the map when deserializing
+pub(crate) mod instruction_map {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::{InstructionKey, TuringInstruction};
+
+    pub fn serialize<S>(
+        instructions: &HashMap<InstructionKey, TuringInstruction>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut list: Vec<&TuringInstruction> = instructions.values().collect();
+        list.sort_by(|a, b| a.cmp_by_state(b));
+
+        list.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(
+        deserializer: D,
+    ) -> Result<HashMap<InstructionKey, TuringInstruction>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let list: Vec<TuringInstruction> = Vec::deserialize(deserializer)?;
+
+        Ok(list.into_iter().map(|i| (i.key(), i)).collect())
+    }
+}
+
+/// Serializes the choices of a non-deterministic machine as a single list sorted by state and value,
+/// keeping the order of the instructions of each key, and rebuilds the map when deserializing.
+/// Formats that are not human readable write an optional list, since deterministic machines have no choices
+pub(crate) mod choice_map {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::{program::Choices, InstructionKey, TuringInstruction};
+
+    pub fn serialize<S>(choices: &Option<Choices>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut list: Vec<(&InstructionKey, &Vec<TuringInstruction>)> =
+            choices.iter().flatten().collect();
+        list.sort_by(|a, b| a.0.cmp(b.0));
+
+        let list: Vec<&TuringInstruction> = list.into_iter().flat_map(|(_, i)| i).collect();
+        if serializer.is_human_readable() {
+            list.serialize(serializer)
+        } else {
+            choices.as_ref().map(|_| list).serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Choices>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let list: Vec<TuringInstruction> = if deserializer.is_human_readable() {
+            Vec::deserialize(deserializer)?
+        } else {
+            match Option::deserialize(deserializer)? {
+                Some(list) => list,
+                None => return Ok(None),
+            }
+        };
+
+        let mut choices: Choices = HashMap::new();
+        for i in list {
+            choices.entry(i.key()).or_default().push(i);
+        }
+
+        Ok(Some(choices))
+    }
+}
+
+/// Serializes the libraries of the instructions as a list of `(state, value, library)` sorted by state and value
+pub(crate) mod library_map {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::InstructionKey;
+
+    pub fn serialize<S>(
+        libraries: &HashMap<InstructionKey, String>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut list: Vec<(&str, bool, &str)> = libraries
+            .iter()
+            .map(|(key, library)| (key.state.as_str(), key.read, library.as_str()))
+            .collect();
+        list.sort();
+
+        list.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<HashMap<InstructionKey, String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let list: Vec<(String, bool, String)> = Vec::deserialize(deserializer)?;
+
+        Ok(list
+            .into_iter()
+            .map(|(state, read, library)| (InstructionKey { state, read }, library))
+            .collect())
+    }
+}
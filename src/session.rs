@@ -0,0 +1,127 @@
+use crate::{ExecutionResult, MachineSnapshot, TuringMachine};
+
+/// The default number of steps between the checkpoints of a `DebugSession`
+pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 100;
+
+#[derive(Debug, Clone)]
+/// A Turing machine that can jump to any step of its execution, like the timeline of a debugger.
+///
+/// The session enables the checkpoints of the machine every `interval` steps (see `TuringMachine::enable_checkpoints`),
+/// so going to a step only needs to restore the nearest checkpoint before it and execute the remaining steps again,
+/// instead of running from the start, and going past the last step reached runs the machine further.
+/// The breakpoints and watchpoints of the machine are used by `run_to_breakpoint`, and the trace (if enabled)
+/// is cut back when going to a previous step. The statistics and the coverage count the steps executed again
+pub struct DebugSession {
+    tm: TuringMachine,
+
+    /// The step after which the machine cannot continue (it halted or got stuck), once it has been reached
+    end: Option<usize>,
+}
+
+impl DebugSession {
+    /// Starts a session at the current step of the machine, taking a checkpoint every `interval` steps
+    /// (at least 1, see `DEFAULT_CHECKPOINT_INTERVAL`)
+    pub fn new(mut tm: TuringMachine, interval: usize) -> Self {
+        tm.enable_checkpoints(interval);
+
+        Self { tm, end: None }
+    }
+
+    /// Returns the machine at the current step
+    pub fn machine(&self) -> &TuringMachine {
+        &self.tm
+    }
+
+    /// Returns the machine at the current step, to change its breakpoints, watchpoints or trace.
+    /// The checkpoints are not updated if its tape or its state are changed, and the session cannot move anymore
+    /// if they are disabled
+    pub fn machine_mut(&mut self) -> &mut TuringMachine {
+        &mut self.tm
+    }
+
+    /// Ends the session, returning the machine at the current step
+    pub fn into_machine(self) -> TuringMachine {
+        self.tm
+    }
+
+    /// Returns a snapshot of the machine at the current step
+    pub fn current(&self) -> MachineSnapshot {
+        self.tm.snapshot()
+    }
+
+    /// Returns the current step
+    pub fn steps(&self) -> usize {
+        self.tm.steps
+    }
+
+    /// Returns the number of checkpoints taken so far
+    pub fn checkpoints(&self) -> usize {
+        self.tm.checkpoint_count()
+    }
+
+    /// Goes to the given step, restoring the nearest checkpoint before it if needed.
+    /// The machine stops early if it halts or gets stuck, or if it cannot execute a step again since it was changed
+    /// (see `TuringMachine::goto_step`), and it cannot go before the step the session started at.
+    /// Returns the step reached
+    pub fn goto_step(&mut self, step: usize) -> usize {
+        let Some(checkpoints) = &self.tm.checkpoints else {
+            return self.tm.steps;
+        };
+
+        let start = checkpoints.start();
+        let recorded = checkpoints.recorded();
+        let step = match self.end {
+            Some(end) => step.clamp(start, end),
+            None => step.max(start),
+        };
+
+        if self.tm.goto_step(step.min(recorded)).is_err() {
+            return self.tm.steps;
+        }
+
+        if step > self.tm.steps {
+            self.run_recording(step - self.tm.steps, |_, _| None);
+        }
+
+        self.tm.steps
+    }
+
+    /// Executes the next step, unless the machine already halted or got stuck. Returns the step reached
+    pub fn step_forward(&mut self) -> usize {
+        self.goto_step(self.tm.steps + 1)
+    }
+
+    /// Goes back to the previous step. Returns the step reached
+    pub fn step_back(&mut self) -> usize {
+        self.goto_step(self.tm.steps.saturating_sub(1))
+    }
+
+    /// Runs the machine like `TuringMachine::run_to_breakpoint`, taking checkpoints along the way
+    pub fn run_to_breakpoint(&mut self, max_steps: usize) -> ExecutionResult {
+        if let Some(result) = self.tm.initial_breakpoint() {
+            return result;
+        }
+
+        self.run_recording(max_steps, TuringMachine::breakpoint_hit)
+    }
+
+    /// Runs the machine like `run_checked`, which takes the checkpoints, remembering where it cannot continue
+    fn run_recording<F>(&mut self, max_steps: usize, check: F) -> ExecutionResult
+    where
+        F: FnMut(&TuringMachine, usize) -> Option<ExecutionResult>,
+    {
+        let result = self.tm.run_checked(max_steps, |_, _, _| {}, check);
+
+        if matches!(
+            result,
+            ExecutionResult::Halted { .. }
+                | ExecutionResult::Stuck { .. }
+                | ExecutionResult::OutOfTape { .. }
+                | ExecutionResult::TapeLimitExceeded { .. }
+        ) {
+            self.end = Some(self.tm.steps);
+        }
+
+        result
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{tape::Tape, TuringMachine};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// The runtime state of a Turing machine, which can be restored later on a machine compiled from the same code
+pub struct MachineSnapshot {
+    /// The tape of the machine
+    pub tape: Vec<bool>,
+
+    /// The position of the head on the tape
+    pub tape_position: usize,
+
+    /// The index in the tape of the first cell of the tape given in the code
+    pub origin: usize,
+
+    /// The current state of the machine
+    pub current_state: String,
+
+    /// The previous state of the machine
+    pub previous_state: Option<String>,
+
+    /// The number of times each (state, value) pair has been executed
+    pub frequencies: Vec<(String, bool, usize)>,
+
+    /// The number of steps executed
+    pub steps: usize,
+
+    /// A fingerprint of the instructions and final states of the machine
+    pub program: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An error when restoring a snapshot
+pub enum SnapshotError {
+    /// The snapshot was taken from a machine with different instructions or final states
+    DifferentProgram,
+
+    /// The head position or the origin of the snapshot are outside of its tape
+    InvalidTape,
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DifferentProgram => write!(f, "The snapshot was taken from a different program"),
+            Self::InvalidTape => write!(f, "The head of the snapshot is outside of its tape"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Checks that the head and the origin are cells of a tape with `len` cells, with the 3 cells
+/// `advance` keeps left of the head. The right side grows as the head moves
+pub(crate) fn check_tape(
+    len: usize,
+    tape_position: usize,
+    origin: usize,
+) -> Result<(), SnapshotError> {
+    if tape_position < 3 || tape_position >= len || origin >= len {
+        return Err(SnapshotError::InvalidTape);
+    }
+
+    Ok(())
+}
+
+impl TuringMachine {
+    /// Takes a snapshot of the runtime state of the machine
+    pub fn snapshot(&self) -> MachineSnapshot {
+        let mut frequencies: Vec<(String, bool, usize)> = self
+            .state_frequencies()
+            .map(|(state, value, f)| (String::from(state), value, f))
+            .collect();
+        frequencies.sort();
+
+        MachineSnapshot {
+            tape: self.tape.to_vec(),
+            tape_position: self.tape_position,
+            origin: self.origin,
+            current_state: String::from(self.current_state()),
+            previous_state: self.previous_state().map(String::from),
+            frequencies,
+            steps: self.steps,
+            program: self.program_fingerprint(),
+        }
+    }
+
+    /// Restores a snapshot taken with `snapshot`, so that the machine continues exactly as the one it was taken from.
+    /// The history of steps is forgotten, since it does not belong to the restored state.
+    ///
+    /// Returns an error (leaving the machine untouched) if the snapshot was taken from a machine with a different program
+    pub fn restore(&mut self, snapshot: MachineSnapshot) -> Result<(), SnapshotError> {
+        if snapshot.program != self.program_fingerprint() {
+            return Err(SnapshotError::DifferentProgram);
+        }
+
+        check_tape(snapshot.tape.len(), snapshot.tape_position, snapshot.origin)?;
+
+        self.tape = Tape::from(snapshot.tape);
+        self.tape_position = snapshot.tape_position;
+        self.origin = snapshot.origin;
+        self.current_state = self.intern_state(&snapshot.current_state);
+        self.previous_state = snapshot
+            .previous_state
+            .map(|state| self.intern_state(&state));
+        self.frequencies.fill([0; 2]);
+        for (state, value, f) in snapshot.frequencies {
+            let id = self.intern_state(&state);
+            self.count_frequency(id, value, f);
+        }
+        self.update_max_frequency();
+        self.steps = snapshot.steps;
+        self.history.clear();
+
+        if self.configurations.is_some() {
+            self.detect_cycles(true);
+        }
+
+        Ok(())
+    }
+
+    /// A fingerprint of the instructions and the final states, stable across processes.
+    /// It is the FNV-1a hash of the sorted instructions and final states
+    pub(crate) fn program_fingerprint(&self) -> u64 {
+        let mut lines: Vec<String> = self
+            .instructions()
+            .values()
+            .map(|i| i.to_string())
+            .collect();
+        lines.sort();
+
+        let mut final_states = self.final_states().to_vec();
+        final_states.sort();
+        lines.extend(final_states);
+
+        lines
+            .iter()
+            .flat_map(|l| l.bytes().chain(std::iter::once(b'\n')))
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+}
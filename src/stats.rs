@@ -0,0 +1,86 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Movement, TuringMachine};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Statistics collected while executing a Turing machine.
+/// Cells are identified by their position relative to the first cell of the tape given in the code
+pub struct Stats {
+    /// The number of steps executed
+    pub steps: usize,
+
+    /// The number of times the head moved to the left
+    pub moves_left: usize,
+
+    /// The number of times the head moved to the right
+    pub moves_right: usize,
+
+    /// The cells the head has been on
+    pub visited: HashSet<isize>,
+
+    /// The leftmost cell the head has been on
+    pub leftmost: isize,
+
+    /// The rightmost cell the head has been on
+    pub rightmost: isize,
+
+    /// The number of times each cell was written
+    pub writes: HashMap<isize, usize>,
+}
+
+impl Stats {
+    /// Creates empty statistics for a head starting on the given cell
+    fn new(head: isize) -> Self {
+        Self {
+            visited: HashSet::from([head]),
+            leftmost: head,
+            rightmost: head,
+            ..Default::default()
+        }
+    }
+
+    /// The number of distinct cells the head has been on
+    pub fn cells_visited(&self) -> usize {
+        self.visited.len()
+    }
+
+    /// The total number of moves of the head
+    pub fn head_travel(&self) -> usize {
+        self.moves_left + self.moves_right
+    }
+
+    /// Records a step that wrote on the cell `written` and left the head on the cell `head`
+    pub(crate) fn record(&mut self, written: isize, movement: Movement, head: isize) {
+        self.steps += 1;
+        *self.writes.entry(written).or_insert(0) += 1;
+
+        match movement {
+            Movement::LEFT => self.moves_left += 1,
+            Movement::RIGHT => self.moves_right += 1,
+            Movement::HALT | Movement::STAY => {}
+        }
+
+        self.visited.insert(head);
+        self.leftmost = self.leftmost.min(head);
+        self.rightmost = self.rightmost.max(head);
+    }
+}
+
+impl TuringMachine {
+    /// Starts collecting statistics of the execution, forgetting any previously collected ones
+    pub fn enable_stats(&mut self) {
+        self.stats = Some(Stats::new(self.head_offset()));
+    }
+
+    /// Stops collecting statistics and forgets them
+    pub fn disable_stats(&mut self) {
+        self.stats = None;
+    }
+
+    /// Returns the statistics collected since they were enabled, or None if they are not enabled
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+}
@@ -0,0 +1,440 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    ops::{Bound, Deref, RangeBounds},
+};
+
+use log::{error, info};
+
+use crate::{warnings::ErrorPosition, CompilerError, ParserOptions, Rule, TuringMachine};
+
+/// The minimum number of spare cells reserved at the front of the tape when it has to grow leftwards
+const MIN_FRONT_GAP: usize = 16;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<bool>", into = "Vec<bool>")]
+/// The cells of a tape. Spare cells are kept at the front of the buffer so that inserting cells at the front
+/// is amortized O(1), like pushing them at the back. It derefs to the slice of the cells in use.
+///
+/// The cells are only written through `set`, so the number of 1s is kept up to date and counting them is O(1)
+pub(crate) struct Tape {
+    cells: Vec<bool>,
+    start: usize,
+    ones: usize,
+}
+
+impl Tape {
+    /// Writes a cell of the tape
+    pub(crate) fn set(&mut self, i: usize, value: bool) {
+        let cell = &mut self.cells[self.start + i];
+
+        match (*cell, value) {
+            (false, true) => self.ones += 1,
+            (true, false) => self.ones -= 1,
+            _ => {}
+        }
+        *cell = value;
+    }
+
+    /// Returns the number of 1s on the tape
+    pub(crate) fn ones(&self) -> usize {
+        debug_assert_eq!(self.ones, self.iter().filter(|v| **v).count());
+
+        self.ones
+    }
+
+    /// Inserts `n` blank cells at the front of the tape
+    pub(crate) fn push_front(&mut self, n: usize, blank: bool) {
+        if n > self.start {
+            let gap = (self.len() + n).max(MIN_FRONT_GAP);
+            let mut cells = vec![false; gap];
+            cells.extend_from_slice(&self.cells[self.start..]);
+
+            self.cells = cells;
+            self.start = gap;
+        }
+
+        self.start -= n;
+        self.cells[self.start..self.start + n].fill(blank);
+        self.ones += n * blank as usize;
+    }
+
+    /// Removes `n` cells from the front of the tape
+    pub(crate) fn pop_front(&mut self, n: usize) {
+        let end = (self.start + n).min(self.cells.len());
+
+        self.ones -= count_ones(&self.cells[self.start..end]);
+        self.start = end;
+    }
+
+    /// Pushes a cell at the back of the tape
+    pub(crate) fn push(&mut self, value: bool) {
+        self.cells.push(value);
+        self.ones += value as usize;
+    }
+
+    /// Resizes the tape to `len` cells, removing or pushing blank cells at the back
+    pub(crate) fn resize(&mut self, len: usize, blank: bool) {
+        if let Some(removed) = self.cells.get(self.start + len..) {
+            self.ones -= count_ones(removed);
+        }
+
+        self.ones += len.saturating_sub(self.len()) * blank as usize;
+        self.cells.resize(self.start + len, blank);
+    }
+}
+
+impl Deref for Tape {
+    type Target = [bool];
+
+    fn deref(&self) -> &[bool] {
+        &self.cells[self.start..]
+    }
+}
+
+impl PartialEq for Tape {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Tape {}
+
+impl From<Vec<bool>> for Tape {
+    fn from(cells: Vec<bool>) -> Self {
+        Self {
+            ones: count_ones(&cells),
+            cells,
+            start: 0,
+        }
+    }
+}
+
+/// Counts the 1s of some cells
+fn count_ones(cells: &[bool]) -> usize {
+    cells.iter().filter(|v| **v).count()
+}
+
+impl From<Tape> for Vec<bool> {
+    fn from(tape: Tape) -> Self {
+        tape.to_vec()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+/// Whether the tape can grow to the left of the first cell given in the code
+pub enum TapeMode {
+    /// The tape grows in both directions
+    #[default]
+    Unbounded,
+
+    /// The first cell given in the code is the leftmost one, and moving left from it is an error
+    LeftBounded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An error when modifying the tape from outside of the machine
+pub enum TapeError {
+    /// The index is past the end of the tape. The tape can only be extended one cell at a time
+    OutOfBounds { index: usize, len: usize },
+
+    /// The head cannot be placed out of the tape given in the code
+    HeadOutOfRange { offset: isize, len: usize },
+}
+
+impl Display for TapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutOfBounds { index, len } => write!(
+                f,
+                "Cannot write the cell {index} of a tape with {len} cells"
+            ),
+            Self::HeadOutOfRange { offset, len } => write!(
+                f,
+                "Cannot place the head at the cell {offset} of an input with {len} cells"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TapeError {}
+
+/// Builds the initial cells of a tape given in the code or to `set_input_tape`, with the head on the cell `head`
+/// (the first one if it is not marked). Returns the cells and the number of cells skipped at the front.
+///
+/// Leading blanks are skipped, except the ones from the head onwards, unless `preserve_leading_zeros` is set.
+/// A tape with only blanks is kept as it was given, with at least the cell of the head, and it is an error
+/// (with its message) unless `allow_empty_tape` is set, which pads it to `empty_tape_cells`
+pub(crate) fn normalize_tape(
+    cells: &[bool],
+    head: Option<usize>,
+    blank: bool,
+    options: &ParserOptions,
+) -> Result<(Vec<bool>, usize), String> {
+    let leading = cells.iter().take_while(|v| **v == blank).count();
+    let skipped = match head {
+        _ if options.preserve_leading_zeros || leading == cells.len() => 0,
+        Some(head) => leading.min(head),
+        None => leading,
+    };
+    if skipped > 0 {
+        info!("The tape started with {} blanks, skipping them", skipped);
+    }
+
+    let mut tape = cells[skipped..].to_vec();
+    if tape.is_empty() {
+        tape.push(blank);
+    }
+
+    // The tape must have a cell that is not blank, a 1 unless the blank is 1
+    if !tape.contains(&!blank) {
+        if !options.allow_empty_tape {
+            let mark = u8::from(!blank);
+            error!("The tape did not contain at least a {}", mark);
+
+            return Err(format!("Expected at least a {} in the tape", mark));
+        }
+
+        tape.resize(tape.len().max(options.empty_tape_cells), blank);
+    }
+
+    Ok((tape, skipped))
+}
+
+impl TuringMachine {
+    /// Returns the value of the tape at the given index, or None if the index is out of bounds
+    pub fn read_cell(&self, i: usize) -> Option<bool> {
+        self.tape.get(i).copied()
+    }
+
+    /// Writes a value on the tape at the given index. Writing at `tape_len()` extends the tape by one cell.
+    /// The blank cells needed around the head are added back after writing, so the machine can keep stepping
+    pub fn write_cell(&mut self, i: usize, value: bool) -> Result<(), TapeError> {
+        if i > self.tape.len() {
+            return Err(TapeError::OutOfBounds {
+                index: i,
+                len: self.tape.len(),
+            });
+        }
+
+        if i == self.tape.len() {
+            self.tape.push(value);
+        } else {
+            self.tape.set(i, value);
+        }
+
+        self.pad();
+
+        Ok(())
+    }
+
+    /// Returns the position of the head relative to the first cell of the tape given in the code.
+    /// The offset is negative when the head is left of that cell, and stays stable when the tape grows at the front
+    pub fn head_offset(&self) -> isize {
+        self.tape_position as isize - self.origin as isize
+    }
+
+    /// Replaces the tape given in the code by `cells`, with the head on the cell `head`,
+    /// and makes it the tape the machine goes back to on `reset`
+    pub(crate) fn load_tape(&mut self, cells: Vec<bool>, head: usize) {
+        self.tape = Tape::from(cells);
+        self.tape_position = 0;
+        self.origin = 0;
+
+        // Only the left side is padded, the right side grows as the head moves
+        self.pad_left();
+        self.tape_position += head;
+
+        self.initial_tape = self.tape.clone();
+        self.initial_tape_position = self.tape_position;
+        self.initial_origin = self.origin;
+    }
+
+    /// Resets the machine with a new input tape instead of the one given in the code, without parsing the code again.
+    /// The tape is checked like a tape of the code with the options the machine was compiled with: the leading blanks
+    /// are skipped unless `preserve_leading_zeros` is set, and the tape must contain at least a cell that is not blank
+    /// (a 1 unless the blank is 1) unless `allow_empty_tape` is set. The head starts on the first cell kept.
+    /// The `code` is left untouched
+    pub fn set_input_tape(&mut self, input: &[bool]) -> Result<(), CompilerError> {
+        let (cells, _) =
+            normalize_tape(input, None, self.blank, &self.program.options).map_err(|message| {
+                CompilerError::SyntaxError {
+                    position: ErrorPosition::new((0, 0), None),
+                    message,
+                    code: input.iter().map(|v| if *v { '1' } else { '0' }).collect(),
+                    expected: Rule::tape,
+                    found: None,
+                }
+            })?;
+
+        self.load_tape(cells, 0);
+        self.reset();
+
+        Ok(())
+    }
+
+    /// Resets the machine with a tape encoding the given numbers, like `set_input_tape`.
+    /// Each number `n` is written as `n + 1` 1s, and the numbers are separated by a single 0,
+    /// which is the convention used by the libraries
+    pub fn set_input_values(&mut self, values: &[u32]) -> Result<(), CompilerError> {
+        self.set_input_tape(&Self::encode_input(values)?)
+    }
+
+    /// Writes the given numbers as a tape, with each number `n` written as `n + 1` 1s and the numbers separated by a single 0
+    /// (e.g. 3 and 5 give `11110111111`, and 0 gives `1`). At least a number must be given
+    pub fn encode_input(values: &[u32]) -> Result<Vec<bool>, CompilerError> {
+        if values.is_empty() {
+            return Err(CompilerError::SyntaxError {
+                position: ErrorPosition::new((0, 0), None),
+                message: String::from("Expected at least a value"),
+                code: String::new(),
+                expected: Rule::tape,
+                found: None,
+            });
+        }
+
+        let mut input = Vec::new();
+
+        for (i, n) in values.iter().enumerate() {
+            if i > 0 {
+                input.push(false);
+            }
+            input.extend(std::iter::repeat_n(true, *n as usize + 1));
+        }
+
+        Ok(input)
+    }
+
+    /// Reads the numbers written on a tape like `encode_input` does, ignoring the blank cells around them.
+    /// Each run of 1s is a number, even if the runs are separated by several 0s
+    pub fn decode_output(tape: &[bool]) -> Vec<u32> {
+        tape.split(|v| !*v)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.len() as u32 - 1)
+            .collect()
+    }
+
+    /// Places the head on the given cell of the tape given in the code (0 being its first cell).
+    /// Meant to be called before the first step: the head also goes back to this cell on `reset`
+    pub fn set_head_position(&mut self, offset: isize) -> Result<(), TapeError> {
+        let len = self.initial_tape.len() - self.initial_origin;
+
+        if offset < 0 || offset as usize >= len {
+            return Err(TapeError::HeadOutOfRange { offset, len });
+        }
+
+        self.initial_tape_position = self.initial_origin + offset as usize;
+        self.tape_position = (self.origin as isize + offset) as usize;
+        self.pad();
+
+        Ok(())
+    }
+
+    /// Limits the number of cells the tape can use to `cells`, so a step that moves the head to a new cell past the limit
+    /// stops the execution with `TapeLimitExceeded`. The tape is unlimited by default.
+    ///
+    /// The cells used are the ones given in the code and the ones reached by the head,
+    /// not the blank cells kept around the head
+    pub fn set_tape_limit(&mut self, cells: usize) {
+        self.tape_limit = Some(cells);
+    }
+
+    /// Lets the tape grow without limit again
+    pub fn remove_tape_limit(&mut self) {
+        self.tape_limit = None;
+    }
+
+    /// Returns the number of cells the tape can use, or None if it is unlimited
+    pub fn tape_limit(&self) -> Option<usize> {
+        self.tape_limit
+    }
+
+    /// Returns the number of cells used by the tape: the cells given in the code and the ones reached by the head
+    pub fn used_cells(&self) -> usize {
+        let (first, last) = self.used_offsets();
+
+        (last - first + 1) as usize
+    }
+
+    /// Returns the offsets of the first and the last cell used by the tape, leaving out the 3 blank cells
+    /// kept on each side of the head that it never reached
+    fn used_offsets(&self) -> (isize, isize) {
+        let given = (self.initial_tape.len() - self.initial_origin) as isize;
+
+        let first = -(self.origin.saturating_sub(3) as isize);
+        let last = (self.tape.len() as isize - 4 - self.origin as isize).max(given - 1);
+
+        (first, last)
+    }
+
+    /// Returns the offsets of the leftmost and the rightmost cell the head can move to without exceeding the tape limit
+    pub(crate) fn head_bounds(&self) -> (isize, isize) {
+        let Some(cells) = self.tape_limit else {
+            return (isize::MIN, isize::MAX);
+        };
+        let (first, last) = self.used_offsets();
+        let cells = cells.min(isize::MAX as usize) as isize;
+
+        (first.min(last - cells + 1), last.max(first + cells - 1))
+    }
+
+    /// Returns the index of the tape where the first cell given in the code is stored
+    pub fn origin(&self) -> usize {
+        self.origin
+    }
+
+    /// Returns the cells between the first and the last cell of the tape that are not blank (both included),
+    /// which are the 1s unless the blank is set to 1, along with the position of the head relative to the first of them.
+    /// The position is out of the bounds of the slice when the head is on the blank cells around it.
+    /// If the tape only has blanks, the slice is empty and the position is 0
+    pub fn tape_trimmed(&self) -> (&[bool], isize) {
+        let Some(first) = self.tape.iter().position(|v| *v != self.blank) else {
+            return (&[], 0);
+        };
+        let last = self
+            .tape
+            .iter()
+            .rposition(|v| *v != self.blank)
+            .unwrap_or(first);
+
+        (
+            &self.tape[first..=last],
+            self.tape_position as isize - first as isize,
+        )
+    }
+
+    /// Returns an iterator over the cells of the tape, including the blank padding around the head
+    pub fn tape(&self) -> impl Iterator<Item = bool> + '_ {
+        self.tape.iter().copied()
+    }
+
+    /// Returns a copy of the cells of the tape, including the blank padding around the head
+    pub fn tape_vec(&self) -> Vec<bool> {
+        self.tape.to_vec()
+    }
+
+    /// Returns the number of cells of the tape
+    pub fn tape_len(&self) -> usize {
+        self.tape.len()
+    }
+
+    /// Returns the cells of the tape in the given range. The range is clamped to the bounds of the tape
+    pub fn tape_slice<R: RangeBounds<usize>>(&self, range: R) -> &[bool] {
+        let len = self.tape.len();
+
+        let start = match range.start_bound() {
+            Bound::Included(s) => *s,
+            Bound::Excluded(s) => s + 1,
+            Bound::Unbounded => 0,
+        }
+        .min(len);
+        let end = match range.end_bound() {
+            Bound::Included(e) => e + 1,
+            Bound::Excluded(e) => *e,
+            Bound::Unbounded => len,
+        }
+        .clamp(start, len);
+
+        &self.tape[start..end]
+    }
+}
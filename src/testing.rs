@@ -0,0 +1,118 @@
+//! Generators of random machines, tapes and instructions for fuzzing, enabled with the `testing` feature.
+//! They do not depend on any random number crate, and always give the same result for the same seed
+
+use crate::{Movement, TuringInstruction};
+
+/// A small pseudo-random number generator (SplitMix64), good enough to generate test cases
+struct Generator(u64);
+
+impl Generator {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number between 0 and `n - 1`
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn value(&mut self) -> bool {
+        self.next() & 1 == 1
+    }
+
+    fn movement(&mut self) -> Movement {
+        match self.below(3) {
+            0 => Movement::LEFT,
+            1 => Movement::RIGHT,
+            _ => Movement::HALT,
+        }
+    }
+
+    /// Returns the name of one of the `n_states` states `q0, q1…` or the final state `qf`
+    fn state(&mut self, n_states: usize) -> String {
+        match self.below(n_states + 1) {
+            i if i == n_states => String::from("qf"),
+            i => format!("q{}", i),
+        }
+    }
+
+    fn cells(&mut self, len: usize) -> Vec<bool> {
+        let len = len.max(1);
+        let mut cells: Vec<bool> = (0..len).map(|_| self.value()).collect();
+
+        // The tape needs at least a 1 to compile
+        if !cells.contains(&true) {
+            let i = self.below(len);
+            cells[i] = true;
+        }
+
+        cells
+    }
+}
+
+/// Generates the code of a tape with `len` cells (at least 1), e.g. `{10110};`, with at least a 1 so that it compiles
+pub fn generate_tape(seed: u64, len: usize) -> String {
+    let cells: String = Generator::new(seed)
+        .cells(len)
+        .into_iter()
+        .map(|v| if v { '1' } else { '0' })
+        .collect();
+
+    format!("{{{}}};", cells)
+}
+
+/// Generates an instruction between the states `q0, q1…` of a machine with `n_states` states (at least 1)
+/// and its final state `qf`. The instruction never starts from `qf`
+pub fn generate_instruction(seed: u64, n_states: usize) -> TuringInstruction {
+    let n_states = n_states.max(1);
+    let mut generator = Generator::new(seed);
+
+    TuringInstruction {
+        from_state: format!("q{}", generator.below(n_states)),
+        from_value: generator.value(),
+        to_value: generator.value(),
+        movement: generator.movement(),
+        to_state: generator.state(n_states),
+        span: None,
+        doc: None,
+    }
+}
+
+/// Generates the code of a machine with `n_states` states (at least 1) named `q0, q1…`, starting from `q0`,
+/// and the final state `qf`. It has a random tape of up to 16 cells and an instruction for every state and value,
+/// so it always compiles but may never halt
+pub fn generate_machine(seed: u64, n_states: usize) -> String {
+    let n_states = n_states.max(1);
+    let mut generator = Generator::new(seed);
+
+    let len = 1 + generator.below(16);
+    let mut code = generate_tape(generator.next(), len);
+    code += "\nI = {q0};\nF = {qf};\n\n";
+
+    for state in 0..n_states {
+        for from_value in [false, true] {
+            let instruction = TuringInstruction {
+                from_state: format!("q{}", state),
+                from_value,
+                to_value: generator.value(),
+                movement: generator.movement(),
+                to_state: generator.state(n_states),
+                span: None,
+                doc: None,
+            };
+
+            code += &format!("{};\n", instruction);
+        }
+    }
+
+    code
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Movement, TuringInstruction, TuringMachine};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// The changes made by a single step of a Turing machine.
+/// Only the written cell and the growth of the tape are stored, instead of a full copy of the tape
+pub struct TraceEntry {
+    /// The number of the step, starting at 1 for the first step recorded
+    pub step: usize,
+
+    /// The state the machine was in before the step
+    pub from_state: String,
+
+    /// The value read from the tape
+    pub read: bool,
+
+    /// The instruction applied (which may be the implicit halt instruction of a final state)
+    pub instruction: TuringInstruction,
+
+    /// The value written to the tape
+    pub written: bool,
+
+    /// The movement of the head
+    pub movement: Movement,
+
+    /// The state the machine is in after the step
+    pub to_state: String,
+
+    /// The index in the tape of the written cell, before any cell was inserted at the front
+    pub position: usize,
+
+    /// The number of cells inserted at the front of the tape after writing
+    pub grew_left: usize,
+
+    /// The number of cells pushed at the back of the tape after writing
+    pub grew_right: usize,
+
+    /// The value of the cells added to the tape, the blank of the machine
+    #[serde(default)]
+    pub blank: bool,
+
+    /// What the step did, in English, like `explain_next_step` explained it before the step
+    #[serde(default)]
+    pub explanation: String,
+}
+
+/// Reconstructs the tape after applying the steps of `trace` to `initial_tape`,
+/// which must be the tape of the machine when the trace started being recorded.
+/// The cells added are the blank recorded in each entry. To get the tape at step `k`, replay `&trace[..k]`
+pub fn replay(trace: &[TraceEntry], initial_tape: &[bool]) -> Vec<bool> {
+    let mut tape = initial_tape.to_vec();
+
+    for entry in trace {
+        tape[entry.position] = entry.written;
+        tape.splice(0..0, std::iter::repeat_n(entry.blank, entry.grew_left));
+        tape.extend(std::iter::repeat_n(entry.blank, entry.grew_right));
+    }
+
+    tape
+}
+
+impl TuringMachine {
+    /// Starts recording a trace of the executed steps, forgetting any previously recorded trace
+    pub fn enable_trace(&mut self) {
+        self.trace = Some(Vec::new());
+        self.trace_tape = self.tape.to_vec();
+    }
+
+    /// Stops recording the trace and forgets it
+    pub fn disable_trace(&mut self) {
+        self.trace = None;
+        self.trace_tape.clear();
+    }
+
+    /// Returns the steps recorded since the trace was enabled (empty if it is not enabled)
+    pub fn trace(&self) -> &[TraceEntry] {
+        self.trace.as_deref().unwrap_or_default()
+    }
+
+    /// Returns the tape the machine had when the trace was enabled, to be used with `replay`
+    pub fn trace_initial_tape(&self) -> &[bool] {
+        &self.trace_tape
+    }
+}
@@ -1,14 +1,25 @@
-use log::{debug, error, info, warn};
-use pest::Parser;
+use log::{debug, error, warn};
+use pest::{iterators::Pair, Parser};
 use pest_derive::Parser;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
     fmt::{self, Display},
+    sync::Arc,
 };
 
 use crate::{
-    instruction::Movement, warnings::ErrorPosition, CompilerError, CompilerWarning, Library,
-    TuringInstruction,
+    breakpoints::Watchpoint,
+    checkpoints::Checkpoints,
+    history::HistoryEntry,
+    instruction::{InstructionKey, Movement, ResolvedInstruction, SourceSpan},
+    parts::MachineParts,
+    program::{CompiledInstruction, Program, StateId, StateKey},
+    snapshot::check_tape,
+    tape::{Tape, TapeMode},
+    warnings::ErrorPosition,
+    CompilerError, CompilerWarning, ExecutionResult, Language, Library, ParserOptions, Stats,
+    StepResult, Steps, TraceEntry, TuringInstruction,
 };
 
 use super::TuringOutput;
@@ -17,51 +28,257 @@ use super::TuringOutput;
 #[grammar = "../turing.pest"]
 pub struct TuringParser;
 
-#[derive(Debug, Clone)]
-/// A Turing machine
-pub struct TuringMachine {
-    /// The dictionary of instructions for the machine.
-    pub instructions: HashMap<(String, bool), TuringInstruction>,
+/// The default number of times a (state, value) pair can be executed before `final_result` considers that the machine is in an infinite loop
+pub const DEFAULT_LOOP_THRESHOLD: usize = 10_000;
 
-    /// The final states of the machine. If the machine reaches one of these states, it will stop.
-    pub final_states: Vec<String>,
+fn default_loop_threshold() -> usize {
+    DEFAULT_LOOP_THRESHOLD
+}
 
-    /// The current state of the machine.
-    pub current_state: String,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "MachineData")]
+/// A Turing machine.
+///
+/// It is serialized with its instructions as a list and its runtime state, but without the debugging state
+/// (history, breakpoints, watchpoints, trace, statistics and cycle detection).
+/// Deserializing a machine whose head, origin or states are out of range is an error
+pub struct TuringMachine {
+    /// The parts of the machine that do not change while it runs: the instructions, the final states,
+    /// the initial state, the description, the composed libraries and the code.
+    /// They are shared by the clones of the machine, so cloning it only copies its runtime state.
+    pub(crate) program: Arc<Program>,
 
-    /// The previous state of the machine.
-    pub previous_state: Option<String>,
+    /// The current state of the machine, accessed through `current_state()`.
+    pub(crate) current_state: StateId,
+
+    /// The previous state of the machine, accessed through `previous_state()`.
+    pub(crate) previous_state: Option<StateId>,
 
     /// The position of the head on the tape.
     pub tape_position: usize,
 
-    /// The binary tape of the machine.
-    pub tape: Vec<bool>,
+    /// The index in the tape of the first cell of the tape given in the code.
+    /// It moves whenever cells are inserted at the front of the tape.
+    pub(crate) origin: usize,
+
+    /// The binary tape of the machine, accessed through `tape()`, `read_cell()`, `write_cell()` and `tape_slice()`.
+    /// It is padded with blank cells around the head, so use `origin()` or `head_offset()` to locate the cells given in the code.
+    pub(crate) tape: Tape,
+
+    /// Whether the head can move left of the first cell given in the code. Set with `tape = bounded;` in the code.
+    #[serde(default)]
+    pub tape_mode: TapeMode,
+
+    /// The value of the blank cells added when the tape grows, 0 unless another one is set with `blank = 1;` in the code.
+    #[serde(default)]
+    pub blank: bool,
+
+    /// The number of steps executed since the machine was created or reset.
+    pub steps: usize,
+
+    /// The number of times each (state, value) pair has been executed, indexed by the id of the state and the value.
+    /// Used to detect infinite loops, accessed through `frequency()`, `state_frequencies()` and `frequencies()`.
+    /// It only grows when the machine gets new states, so the steps never allocate
+    #[serde(default)]
+    pub(crate) frequencies: Vec<[usize; 2]>,
+
+    /// The (state, value) pair executed the most times, with its frequency.
+    #[serde(default)]
+    pub(crate) max_frequency: Option<(StateId, bool, usize)>,
+
+    /// The number of times a (state, value) pair can be executed before `final_result` gives up, considering that the machine is in an infinite loop.
+    /// Defaults to `DEFAULT_LOOP_THRESHOLD`
+    #[serde(default = "default_loop_threshold")]
+    pub loop_threshold: usize,
+
+    /// The tape right after compilation. Used for resetting the machine without parsing the code again.
+    pub(crate) initial_tape: Tape,
+
+    /// The head position right after compilation.
+    pub(crate) initial_tape_position: usize,
+
+    /// The origin right after compilation.
+    pub(crate) initial_origin: usize,
+
+    /// The steps that can be undone with `step_back`. Only recorded if `history_capacity` is not 0.
+    #[serde(skip)]
+    pub(crate) history: VecDeque<HistoryEntry>,
+
+    /// The maximum number of steps kept in the history.
+    #[serde(skip)]
+    pub(crate) history_capacity: usize,
+
+    /// The states where `run_to_breakpoint` stops.
+    #[serde(skip)]
+    pub(crate) breakpoints: HashSet<String>,
+
+    /// True if `run_to_breakpoint` already stopped at the initial state before executing any step.
+    #[serde(skip)]
+    pub(crate) initial_breakpoint_hit: bool,
 
-    /// The frequencies of the states. Used to detect infinite loops.
-    pub frequencies: HashMap<String, usize>,
+    /// The cells (relative to the origin) whose writes stop `run_to_breakpoint`.
+    #[serde(skip)]
+    pub(crate) cell_watches: HashSet<isize>,
 
-    /// The description of the machine. Found in the `///` comments at the top of the file.
-    pub description: Option<String>,
+    /// The values whose writes over a different value stop `run_to_breakpoint`.
+    #[serde(skip)]
+    pub(crate) write_watches: HashSet<bool>,
 
-    /// The composed libraries that the machine uses.
-    /// Used only as information, since their instructions are already compiled into the machine.
-    pub composed_libs: Vec<Library>,
+    /// The watchpoint triggered by the last step, if any.
+    #[serde(skip)]
+    pub(crate) watch_hit: Option<Watchpoint>,
 
-    /// The actual code of the machine. Used for resetting the machine and debugging.
-    pub code: String,
+    /// The steps recorded since the trace was enabled, or None if it is not enabled.
+    #[serde(skip)]
+    pub(crate) trace: Option<Vec<TraceEntry>>,
+
+    /// The tape when the trace was enabled.
+    #[serde(skip)]
+    pub(crate) trace_tape: Vec<bool>,
+
+    /// The statistics of the execution, or None if they are not enabled.
+    #[serde(skip)]
+    pub(crate) stats: Option<Stats>,
+
+    /// The number of times each instruction was executed, or None if the coverage is not enabled.
+    #[serde(skip)]
+    pub(crate) coverage: Option<HashMap<(String, bool), usize>>,
+
+    /// The hashes of the configurations reached, or None if cycle detection is not enabled.
+    #[serde(skip)]
+    pub(crate) configurations: Option<HashSet<u64>>,
+
+    /// True if a configuration has repeated since cycle detection was enabled.
+    #[serde(skip)]
+    pub(crate) cycle_detected: bool,
+
+    /// True if `run` executes the scanning loops at once.
+    #[serde(skip)]
+    pub(crate) fast_forward: bool,
+
+    /// The maximum number of cells the tape can use, or None if it can grow without limit.
+    #[serde(skip)]
+    pub(crate) tape_limit: Option<usize>,
+
+    /// The snapshots taken every few steps, or None if they are not enabled.
+    #[serde(skip)]
+    pub(crate) checkpoints: Option<Checkpoints>,
+}
+
+#[derive(Deserialize)]
+/// The serialized fields of a `TuringMachine`, checked before building it so that a corrupted machine
+/// is rejected instead of panicking on its first step
+struct MachineData {
+    program: Arc<Program>,
+    current_state: StateId,
+    previous_state: Option<StateId>,
+    tape_position: usize,
+    origin: usize,
+    tape: Tape,
+    #[serde(default)]
+    tape_mode: TapeMode,
+    #[serde(default)]
+    blank: bool,
+    steps: usize,
+    #[serde(default)]
+    frequencies: Vec<[usize; 2]>,
+    #[serde(default)]
+    max_frequency: Option<(StateId, bool, usize)>,
+    #[serde(default = "default_loop_threshold")]
+    loop_threshold: usize,
+    initial_tape: Tape,
+    initial_tape_position: usize,
+    initial_origin: usize,
+}
+
+impl TryFrom<MachineData> for TuringMachine {
+    type Error = String;
+
+    fn try_from(data: MachineData) -> Result<Self, Self::Error> {
+        check_tape(data.tape.len(), data.tape_position, data.origin)
+            .map_err(|e| format!("{}", e))?;
+        check_tape(
+            data.initial_tape.len(),
+            data.initial_tape_position,
+            data.initial_origin,
+        )
+        .map_err(|e| format!("The initial tape is invalid: {}", e))?;
+
+        let states = data.program.states.len();
+        let state = std::iter::once(data.current_state)
+            .chain(data.previous_state)
+            .chain(Some(data.program.initial_state))
+            .chain(data.max_frequency.map(|(state, _, _)| state))
+            .find(|s| s.index() >= states);
+        if let Some(state) = state {
+            return Err(format!(
+                "The state {} does not exist, the machine only has {} states",
+                state.index(),
+                states
+            ));
+        }
+        if data.frequencies.len() > states {
+            return Err(format!(
+                "The frequencies of {} states were given, the machine only has {} states",
+                data.frequencies.len(),
+                states
+            ));
+        }
+
+        Ok(Self {
+            program: data.program,
+            current_state: data.current_state,
+            previous_state: data.previous_state,
+            tape_position: data.tape_position,
+            origin: data.origin,
+            tape: data.tape,
+            tape_mode: data.tape_mode,
+            blank: data.blank,
+            steps: data.steps,
+            frequencies: data.frequencies,
+            max_frequency: data.max_frequency,
+            loop_threshold: data.loop_threshold,
+            initial_tape: data.initial_tape,
+            initial_tape_position: data.initial_tape_position,
+            initial_origin: data.initial_origin,
+            history: VecDeque::new(),
+            history_capacity: 0,
+            breakpoints: HashSet::new(),
+            initial_breakpoint_hit: false,
+            cell_watches: HashSet::new(),
+            write_watches: HashSet::new(),
+            watch_hit: None,
+            trace: None,
+            trace_tape: Vec::new(),
+            stats: None,
+            coverage: None,
+            configurations: None,
+            cycle_detected: false,
+            fast_forward: false,
+            tape_limit: None,
+            checkpoints: None,
+        })
+    }
 }
 
 impl TuringMachine {
-    /// Create a new Turing machine from a string of code
+    /// Create a new Turing machine from a string of code.
+    /// Kept for compatibility, `compile` gives the same with the composed libraries in a `CompilationResult`
     pub fn new(code: &str) -> Result<(Self, Vec<CompilerWarning>), CompilerError> {
-        let mut instructions: HashMap<(String, bool), TuringInstruction> = HashMap::new();
-        let mut final_states: Vec<String> = Vec::new();
-        let mut current_state: String = String::new();
-        let mut tape: Vec<bool> = Vec::new();
-        let mut description: Option<String> = None;
-        let mut composed: Vec<Library> = Vec::new();
-        let mut warnings: Vec<CompilerWarning> = Vec::new();
+        Self::compile(code).map(|result| (result.machine, result.warnings))
+    }
+
+    /// Parses the code of a machine into its parts, leaving the semantic checks to `from_machine_parts`
+    pub(crate) fn parse_parts(code: &str) -> Result<MachineParts, CompilerError> {
+        Self::parse_parts_with(code, &ParserOptions::default())
+    }
+
+    /// Parses the code of a machine into its parts with the given options, like `parse_parts`
+    pub(crate) fn parse_parts_with(
+        code: &str,
+        options: &ParserOptions,
+    ) -> Result<MachineParts, CompilerError> {
+        let mut parts = MachineParts::new(String::from(code));
 
         let file = match TuringParser::parse(Rule::file, code) {
             Ok(mut f) => f.next().unwrap(),
@@ -72,98 +289,168 @@ impl TuringMachine {
             }
         };
 
+        // The `///` comments since the last definition or instruction, which document the next instruction
+        let mut docs: Vec<&str> = Vec::new();
+
+        // The symbols of the alphabet, if it was given. It is found first, since the tape can be given before it
+        let alphabet: Option<Vec<String>> = file
+            .clone()
+            .into_inner()
+            .find(|r| r.as_rule() == Rule::alphabet)
+            .map(|r| {
+                r.into_inner()
+                    .filter(|s| s.as_rule() == Rule::symbol)
+                    .map(|s| String::from(symbol_name(s.as_str())))
+                    .collect()
+            });
+        // The functions defined in the code, found first so they can be composed before their definition
+        let functions = Self::parse_functions(&file, &mut parts.warnings)?;
+        let mut alphabet_given = false;
+        let mut blank_given = false;
+        let mut name_given = false;
+
+        // The indices of the instructions that come from reading the wildcard `*`
+        let mut wildcards: HashSet<usize> = HashSet::new();
+
         for record in file.into_inner() {
             let record_span = &record.as_span();
 
+            if !matches!(record.as_rule(), Rule::doc | Rule::instruction) && !docs.is_empty() {
+                debug!("The comments {:?} do not document an instruction", docs);
+                docs.clear();
+            }
+
             match record.as_rule() {
                 Rule::description => {
-                    let s = record.as_str();
-                    if !s.is_empty() {
-                        description = Some(String::from(s.replace("///", "").trim()));
-                        debug!("Found description: \"{:?}\"", description);
-                    }
+                    parts.description = parse_description(record.as_str());
+                    debug!("Found description: \"{:?}\"", parts.description);
                 }
                 Rule::COMMENT => debug!("Found comment: \"{:?}\"", record.as_str()),
+                Rule::doc => docs.push(record.as_str()),
+                Rule::alphabet => {
+                    if alphabet_given {
+                        return Err(Self::repeated_definition(&record, "alphabet"));
+                    }
+                    alphabet_given = true;
+                    list_items(record, &mut parts.warnings);
+                    debug!("The alphabet is {:?}", alphabet);
+                }
                 Rule::tape => {
+                    check_alphabet(&record, alphabet.as_deref())?;
                     debug!(
                         "Entered tape rule: {}",
                         record.clone().into_inner().as_str()
                     );
 
+                    if parts.tape.is_some() {
+                        return Err(Self::repeated_definition(&record, "tape"));
+                    }
+
                     // Used to extract the position of the error (if any)
                     // A span contains the start and end position of the error, while a Pair only contains the start position
                     let span = record.line_col();
 
-                    let code = record.clone().into_inner().as_str();
+                    let (tape, head) = parse_tape(record, options.max_tape_cells)?;
+                    parts.head = head;
 
-                    for r in record.into_inner() {
-                        match r.as_rule() {
-                            Rule::value => {
-                                if tape.is_empty() && r.as_str() == "0" {
-                                    info!("The tape started with a 0, skipping it");
-                                } else {
-                                    tape.push(r.as_str() == "1");
-                                }
-                            }
-                            _ => warn!(
-                                "Unhandled: ({:?}, {})",
-                                r.as_rule(),
-                                r.into_inner().as_str()
-                            ),
-                        }
+                    debug!("Tape: {:?}", tape);
+
+                    parts.tape = Some((tape, span.into()));
+                }
+                Rule::initial_state => {
+                    if parts.initial_state.is_some() {
+                        return Err(Self::repeated_definition(&record, "initial state"));
                     }
 
-                    debug!("Initial state: {}", current_state);
-                    debug!("Tape: {:?}", tape);
+                    let state = String::from(record.into_inner().as_str());
+                    debug!("The initial tape state is \"{}\"", state);
+                    parts.initial_state = Some(state);
+                }
+                Rule::final_state => {
+                    if !parts.final_states.is_empty() {
+                        return Err(Self::repeated_definition(&record, "final states"));
+                    }
 
-                    if tape.is_empty() || !tape.contains(&true) {
-                        error!("The tape did not contain at least a 1");
+                    parts.final_states = Self::parse_states(record, &mut parts.warnings);
+                    debug!("The final tape state is {:?}", parts.final_states);
+                }
+                Rule::accepting_states | Rule::rejecting_states => {
+                    let accepting = record.as_rule() == Rule::accepting_states;
+                    let (states, name) = if accepting {
+                        (&mut parts.accepting_states, "accepting states")
+                    } else {
+                        (&mut parts.rejecting_states, "rejecting states")
+                    };
 
-                        return Err(CompilerError::SyntaxError {
-                            position: span.into(),
-                            message: String::from("Expected at least a 1 in the tape"),
-                            code: String::from(code),
-                            expected: Rule::tape,
-                            found: None,
-                        });
+                    if !states.is_empty() {
+                        return Err(Self::repeated_definition(&record, name));
                     }
+
+                    *states = Self::parse_states(record, &mut parts.warnings);
+                    debug!("The {} are {:?}", name, states);
                 }
-                Rule::initial_state => {
-                    current_state = String::from(record.into_inner().as_str());
-                    debug!("The initial tape state is \"{}\"", current_state);
+                Rule::blank => {
+                    if blank_given {
+                        return Err(Self::repeated_definition(&record, "blank"));
+                    }
+                    blank_given = true;
+
+                    check_alphabet(&record, alphabet.as_deref())?;
+                    parts.blank = binary_value(&record.into_inner().next().unwrap())?;
+                    debug!("The blank is {}", u8::from(parts.blank));
                 }
-                Rule::final_state => {
-                    final_states = record
-                        .into_inner()
-                        .map(|v| String::from(v.as_span().as_str()))
-                        .collect();
-                    debug!("The final tape state is {:?}", final_states);
+                Rule::name => {
+                    if name_given {
+                        return Err(Self::repeated_definition(&record, "name"));
+                    }
+                    name_given = true;
+
+                    parts.name = Some(String::from(record.into_inner().as_str().trim_matches('"')));
+                    debug!("The machine is named {:?}", parts.name);
+                }
+                Rule::function => debug!("Found the function {}", record.as_str()),
+                Rule::import => {
+                    let path = record.clone().into_inner().as_str().trim_matches('"');
+                    debug!("Found the import of {}", path);
+
+                    parts
+                        .imports
+                        .push((String::from(path), record.as_span().into()));
+                }
+                Rule::tape_mode => {
+                    parts.tape_mode = match record.into_inner().as_str() {
+                        "bounded" => TapeMode::LeftBounded,
+                        _ => TapeMode::Unbounded,
+                    };
+                    debug!("The tape mode is {:?}", parts.tape_mode);
                 }
                 Rule::composition => {
                     debug!("Entered composition rule");
-                    for r in record.into_inner() {
+                    let mut items = list_items(record, &mut parts.warnings)
+                        .into_iter()
+                        .peekable();
+                    while let Some(r) = items.next() {
                         match r.as_rule() {
                             Rule::function_name => {
                                 debug!("Found composition of: {}", r.as_str());
 
-                                let mut lib: Option<Library> = None;
+                                // The functions of the code replace the libraries with the same name
+                                let lib: Option<Library> =
+                                    functions.get(r.as_str()).cloned().or_else(|| {
+                                        super::LIBRARIES.into_iter().find(|l| l.name == r.as_str())
+                                    });
 
-                                for l in super::LIBRARIES {
-                                    if l.name == r.as_str() {
-                                        lib = Some(l);
-                                        break;
-                                    }
-                                }
+                                // The alias after the name prefixes the states of the library
+                                let alias = items.next_if(|a| a.as_rule() == Rule::alias);
+                                let lib = match alias {
+                                    Some(alias) => lib.map(|l| l.with_alias(alias.as_str())),
+                                    None => lib,
+                                };
 
                                 if let Some(library) = lib {
                                     debug!("Found the library, composing...");
 
-                                    instructions.extend(match library.get_instructions() {
-                                        Ok(i) => i,
-                                        Err(c_err) => return Err(c_err),
-                                    });
-
-                                    composed.push(library.clone());
+                                    parts.composed.push((library.clone(), (&r).into()));
                                 } else {
                                     error!("Could not find the library \"{}\"", r.as_str());
 
@@ -190,23 +477,23 @@ impl TuringMachine {
                     }
                 }
                 Rule::instruction => {
-                    let tmp = match TuringInstruction::from(record.into_inner()) {
-                        Ok(i) => i,
-                        Err(c_err) => return Err(c_err),
-                    };
-
-                    if instructions.contains_key(&(tmp.from_state.clone(), tmp.from_value)) {
-                        warn!("Instruction {} already exists, overwriting it", tmp.clone());
-
-                        warnings.push(CompilerWarning::StateOverwrite {
-                            position: record_span.into(),
-                            state: tmp.from_state.clone(),
-                            value_from: tmp.from_value,
-                        })
+                    check_alphabet(&record, alphabet.as_deref())?;
+                    let expanded = TuringInstruction::expand(record.into_inner())?;
+                    let doc = TuringInstruction::doc_from(&docs);
+                    docs.clear();
+
+                    if expanded.len() > 1 {
+                        wildcards.extend(
+                            parts.instructions.len()..parts.instructions.len() + expanded.len(),
+                        );
                     }
-                    instructions.insert((tmp.from_state.clone(), tmp.from_value), tmp.clone());
+                    for mut tmp in expanded {
+                        tmp.span = Some(SourceSpan::from(record_span));
+                        tmp.doc = doc.clone();
+                        debug!("Found instruction {}", tmp);
 
-                    debug!("Found instruction {}", tmp);
+                        parts.instructions.push((tmp, record_span.into()));
+                    }
                 }
                 Rule::EOI => {
                     debug!("End of file");
@@ -217,85 +504,155 @@ impl TuringMachine {
             }
         }
 
-        if final_states.is_empty() {
-            error!("No final state given");
+        // The instructions written with a value win over the ones of a wildcard, wherever they are written
+        let explicit: HashSet<InstructionKey> = parts
+            .instructions
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !wildcards.contains(i))
+            .map(|(_, (instruction, _))| instruction.key())
+            .collect();
+        let mut index = 0;
+        parts.instructions.retain(|(instruction, _)| {
+            let overridden = wildcards.contains(&index) && explicit.contains(&instruction.key());
+            if overridden {
+                debug!(
+                    "The instruction {} of a wildcard is given explicitly",
+                    instruction
+                );
+            }
+            index += 1;
 
-            return Err(CompilerError::SyntaxError {
-                position: ErrorPosition::new((0, 0), None),
-                message: String::from("No final state given"),
-                code: String::from(code),
-                expected: Rule::final_state,
-                found: None,
-            });
-        }
+            !overridden
+        });
 
-        if current_state.is_empty() {
-            error!("No initial state given");
+        Ok(parts)
+    }
 
-            return Err(CompilerError::SyntaxError {
-                position: ErrorPosition::new((0, 0), None),
-                message: String::from("No initial state given"),
-                code: String::from(code),
-                expected: Rule::initial_state,
-                found: None,
-            });
-        }
+    /// Parses the states of a list of final, accepting or rejecting states,
+    /// giving a `RepeatedFinalState` warning for each state listed again and a `StyleWarning` for a trailing comma
+    fn parse_states(record: Pair<Rule>, warnings: &mut Vec<CompilerWarning>) -> Vec<String> {
+        let mut states: Vec<String> = Vec::new();
 
-        let mut tape_position = 0;
-        while tape_position <= 2 {
-            tape.insert(0, false);
-            tape_position += 1;
+        for r in list_items(record, warnings) {
+            let state = String::from(r.as_str());
+
+            if states.contains(&state) {
+                warn!("The state {} is listed more than once", state);
+
+                warnings.push(CompilerWarning::RepeatedFinalState {
+                    position: r.as_span().into(),
+                    state,
+                });
+            } else {
+                states.push(state);
+            }
         }
 
-        debug!("The instructions are {:?}", instructions);
+        states
+    }
 
-        Ok((
-            Self {
-                instructions,
-                final_states,
-                current_state,
-                previous_state: None,
-                tape_position,
-                tape,
-                frequencies: HashMap::new(),
-                description,
-                composed_libs: composed,
-                code: String::from(code),
-            },
-            warnings,
-        ))
+    /// Builds the error for a definition that was already given, with the span of the repeated one
+    pub(crate) fn repeated_definition(record: &Pair<Rule>, name: &str) -> CompilerError {
+        error!("The {} was given more than once", name);
+
+        CompilerError::SyntaxError {
+            position: record.as_span().into(),
+            message: format!("The {} can only be given once", name),
+            code: String::from(record.as_str()),
+            expected: Rule::instruction,
+            found: Some(record.as_rule()),
+        }
     }
 
     /// Create a new empty Turing machine
     pub fn none() -> Self {
         let state = String::from("f");
-        let mut instructions: HashMap<(String, bool), TuringInstruction> = HashMap::new();
+        let mut instructions: HashMap<InstructionKey, TuringInstruction> = HashMap::new();
         instructions.insert(
-            (String::from("F"), false),
+            InstructionKey::new("F", false),
             TuringInstruction {
                 from_state: state.clone(),
                 from_value: false,
                 to_value: false,
                 movement: Movement::HALT,
                 to_state: state.clone(),
+                span: None,
+                doc: None,
             },
         );
         let final_states: Vec<String> = vec![state.clone()];
-        let current_state: String = state.clone();
-        let tape: Vec<bool> = vec![false, false, false, false, false];
-        let description: Option<String> = None;
+        let tape = Tape::from(vec![false, false, false, false, false]);
+        let program = Program::new(instructions, final_states, &state);
+        let state_count = program.states.len();
 
         Self {
-            instructions,
-            final_states,
-            current_state,
+            current_state: program.initial_state,
+            program: Arc::new(program),
             previous_state: None,
+            initial_tape_position: 2,
             tape_position: 2,
+            initial_origin: 2,
+            origin: 2,
+            initial_tape: tape.clone(),
             tape,
-            frequencies: HashMap::new(),
-            description,
-            composed_libs: Vec::new(),
-            code: String::new(),
+            tape_mode: TapeMode::default(),
+            blank: false,
+            steps: 0,
+            frequencies: vec![[0; 2]; state_count],
+            max_frequency: None,
+            loop_threshold: DEFAULT_LOOP_THRESHOLD,
+            history: VecDeque::new(),
+            history_capacity: 0,
+            breakpoints: HashSet::new(),
+            initial_breakpoint_hit: false,
+            cell_watches: HashSet::new(),
+            write_watches: HashSet::new(),
+            watch_hit: None,
+            trace: None,
+            trace_tape: Vec::new(),
+            stats: None,
+            coverage: None,
+            configurations: None,
+            cycle_detected: false,
+            fast_forward: false,
+            tape_limit: None,
+            checkpoints: None,
+        }
+    }
+
+    /// Restores the tape, the head position, the current state, the step counter and the frequencies
+    /// to the values they had right after the machine was created, clearing the statistics, the coverage and the checkpoints
+    pub fn reset(&mut self) {
+        self.tape = self.initial_tape.clone();
+        self.tape_position = self.initial_tape_position;
+        self.origin = self.initial_origin;
+        self.current_state = self.program.initial_state;
+        self.previous_state = None;
+        self.steps = 0;
+        self.reset_frequencies();
+        self.history.clear();
+        self.initial_breakpoint_hit = false;
+        self.watch_hit = None;
+
+        if self.trace.is_some() {
+            self.enable_trace();
+        }
+
+        if self.stats.is_some() {
+            self.enable_stats();
+        }
+
+        if self.coverage.is_some() {
+            self.enable_coverage();
+        }
+
+        if self.configurations.is_some() {
+            self.detect_cycles(true);
+        }
+
+        if let Some(every) = self.checkpoints.as_ref().map(|c| c.every) {
+            self.enable_checkpoints(every);
         }
     }
 
@@ -327,174 +684,464 @@ impl TuringMachine {
         std::io::stdin().read_line(&mut input).unwrap_or_default();
     }
 
-    /// Gets the current instruction, or a halt instruction if the current state is a final state
-    /// even if there is no instruction for the current state and value
-    fn get_instruction(&self) -> Option<TuringInstruction> {
-        let current_val: bool = self.tape[self.tape_position];
-        let index = (self.current_state.clone(), current_val);
+    /// Gets the instruction given for the current state and value.
+    /// Unlike `next_instruction`, it is None for the implicit halt of a final state
+    #[deprecated(
+        since = "2.2.0",
+        note = "use `next_instruction` instead, which also gives the implicit halt of final states"
+    )]
+    pub fn get_current_instruction(&self) -> Option<TuringInstruction> {
+        self.next_instruction()
+            .filter(|i| !i.is_implicit_halt())
+            .map(|i| i.instruction().clone())
+    }
 
-        match self.instructions.get(&index) {
-            Some(i) => Some(i.to_owned()),
-            None => {
-                if !self.final_states.contains(&self.current_state) {
-                    return None;
-                }
+    /// Returns the instruction the next step executes: the one given for the current state and value,
+    /// or the implicit halt instruction if the current state is final and has none.
+    /// Returns None if the machine is stuck
+    pub fn next_instruction(&self) -> Option<ResolvedInstruction> {
+        let current_val: bool = self.tape[self.tape_position];
 
-                Some(TuringInstruction::halt(index))
+        match self.program.get(self.current_state, current_val) {
+            Some(i) => Some(ResolvedInstruction::Explicit(i.clone())),
+            None if self.program.is_final(self.current_state) => {
+                Some(ResolvedInstruction::ImplicitHalt(TuringInstruction::halt(
+                    (String::from(self.current_state()), current_val),
+                )))
             }
+            None => None,
         }
     }
 
-    /// Gets the current instruction
-    pub fn get_current_instruction(&self) -> Option<TuringInstruction> {
-        let current_val: bool = self.tape[self.tape_position];
-        let index = (self.current_state.clone(), current_val);
-
-        self.instructions.get(&index).cloned()
+    /// Returns true if the next step halts the machine, which happens when it is executed from a final state,
+    /// whether the state has an instruction for the current value or not
+    pub fn would_halt(&self) -> bool {
+        self.finished()
     }
 
     /// Returns true if the current state is undefined
     /// (i.e. there is no instruction for the current state and value)
     /// except if the current state is a final state
     pub fn is_undefined(&self) -> bool {
-        self.get_instruction().is_none()
+        let current_val: bool = self.tape[self.tape_position];
+
+        self.program
+            .transition(self.current_state, current_val)
+            .is_none()
+            && !self.program.is_final(self.current_state)
     }
 
     /// Calculates the next step of the Turing machine and returns true if the current state is a final state
+    /// (or if there is no instruction for the current state and value)
+    #[deprecated(
+        since = "2.2.0",
+        note = "use `advance` instead, which tells halting and getting stuck apart"
+    )]
     pub fn step(&mut self) -> bool {
+        !matches!(self.advance(), StepResult::Continued)
+    }
+
+    /// Calculates the next step of the Turing machine
+    pub fn advance(&mut self) -> StepResult {
+        self.step_with(|_, _, _| {})
+    }
+
+    /// Calculates the next step of the Turing machine like `advance`, and then calls `observer`
+    /// with the executed instruction (which may be the implicit halt instruction of a final state),
+    /// the new position of the head and the new state.
+    /// The observer is not called if no step was executed
+    pub fn step_with<F>(&mut self, mut observer: F) -> StepResult
+    where
+        F: FnMut(&TuringInstruction, usize, &str),
+    {
         let current_val: bool = self.tape[self.tape_position];
+        let from_state = self.current_state;
+        self.watch_hit = None;
 
-        let Some(instruction) = self.get_instruction() else {
-            if self.final_states.contains(&self.current_state) {
-                return true;
+        let transition = match self.program.transition(from_state, current_val) {
+            Some(t) => t,
+            None if self.program.is_final(from_state) => CompiledInstruction {
+                to_value: current_val,
+                movement: Movement::HALT,
+                to_state: from_state,
+            },
+            None => {
+                error!(
+                    "No instruction given for state ({}, {})",
+                    self.current_state(),
+                    if current_val { "1" } else { "0" }
+                );
+
+                return StepResult::Undefined {
+                    state: String::from(self.current_state()),
+                    value: current_val,
+                };
             }
-
+        };
+        if self.tape_mode == TapeMode::LeftBounded
+            && transition.movement == Movement::LEFT
+            && self.head_offset() <= 0
+        {
             error!(
-                "No instruction given for state ({}, {})",
-                self.current_state.clone(),
+                "The instruction ({}, {}) moves the head out of the left-bounded tape",
+                self.current_state(),
                 if current_val { "1" } else { "0" }
             );
 
-            return true;
-        };
-        self.tape[self.tape_position] = instruction.to_value;
+            return StepResult::OutOfTape {
+                state: String::from(self.current_state()),
+                value: current_val,
+            };
+        }
+        if let Some(cells) = self.tape_limit {
+            let head = match transition.movement {
+                Movement::LEFT => self.head_offset() - 1,
+                Movement::RIGHT => self.head_offset() + 1,
+                Movement::HALT | Movement::STAY => self.head_offset(),
+            };
+            let (leftmost, rightmost) = self.head_bounds();
+
+            if head < leftmost || head > rightmost {
+                error!(
+                    "The instruction ({}, {}) makes the tape use more than {} cells",
+                    self.current_state(),
+                    if current_val { "1" } else { "0" },
+                    cells
+                );
+
+                return StepResult::TapeLimitExceeded { cells };
+            }
+        }
+
+        let previous_position = self.tape_position;
+        let written_cell = self.head_offset();
+        let mut grew_left = 0;
+        let mut grew_right = 0;
 
-        match instruction.movement {
+        self.tape.set(self.tape_position, transition.to_value);
+        self.check_watches(current_val, transition.to_value);
+
+        match transition.movement {
             Movement::LEFT => {
                 if self.tape_position == 0 {
-                    self.tape.insert(0, false);
+                    self.tape.push_front(1, self.blank);
+                    self.origin += 1;
+                    grew_left += 1;
                 } else {
                     self.tape_position -= 1;
                 }
             }
             Movement::RIGHT => {
                 if self.tape_position == self.tape.len() - 1 {
-                    self.tape.push(false);
+                    self.tape.push(self.blank);
+                    grew_right += 1;
                 }
 
                 self.tape_position += 1;
             }
-            Movement::HALT => {}
+            Movement::HALT | Movement::STAY => {}
         }
 
-        while self.tape_position <= 2 {
-            self.tape.insert(0, false);
-            self.tape_position += 1;
+        let (padded_left, padded_right) = self.pad();
+        grew_left += padded_left;
+        grew_right += padded_right;
+
+        let head_cell = self.head_offset();
+
+        if let Some(stats) = &mut self.stats {
+            stats.record(written_cell, transition.movement, head_cell);
         }
 
-        while self.tape_position >= self.tape.len() - 3 {
-            self.tape.push(false);
+        if self.history_capacity > 0 {
+            self.record_history(HistoryEntry {
+                state: from_state,
+                previous_state: self.previous_state,
+                tape_position: previous_position,
+                value: current_val,
+                grew_left,
+                grew_right,
+            });
         }
 
-        self.update_state(instruction.to_state.clone())
-    }
+        self.previous_state = Some(from_state);
+        self.current_state = transition.to_state;
+        self.steps += 1;
 
-    /// Updates the current state and returns true if the current state is a final state
-    fn update_state(&mut self, state: String) -> bool {
-        self.previous_state = Some(self.current_state.clone());
-        self.current_state = state.clone();
+        if self.configurations.is_some() {
+            self.record_configuration();
+        }
 
-        if self.frequencies.contains_key(&state) {
-            let Some(f) = self.frequencies.get_mut(&state) else {
-                return self.final_states.contains(&self.current_state);
-            };
-            *f += 1;
+        // Only the instructions given in the code are covered, not the implicit halt of final states
+        if let (Some(coverage), Some(instruction)) = (
+            &mut self.coverage,
+            self.program.get(from_state, current_val),
+        ) {
+            let key: &dyn StateKey = &(instruction.from_state.as_str(), current_val);
+            match coverage.get_mut(key) {
+                Some(c) => *c += 1,
+                None => {
+                    coverage.insert((instruction.from_state.clone(), current_val), 1);
+                }
+            }
+        }
+
+        self.count_frequency(from_state, current_val, 1);
+
+        if self.checkpoints.is_some() {
+            self.record_checkpoint();
+        }
+
+        // The implicit halt instruction of a final state is only built when it is executed
+        let halt;
+        let instruction = match self.program.get(from_state, current_val) {
+            Some(i) => i,
+            None => {
+                halt = TuringInstruction::halt((
+                    String::from(self.program.states.name(from_state)),
+                    current_val,
+                ));
+                &halt
+            }
+        };
+
+        let explanation = self.trace.is_some().then(|| {
+            let implicit_halt = self.program.get(from_state, current_val).is_none();
+            self.explain(instruction, implicit_halt, Language::English)
+        });
+
+        if let (Some(trace), Some(explanation)) = (&mut self.trace, explanation) {
+            trace.push(TraceEntry {
+                step: trace.len() + 1,
+                from_state: instruction.from_state.clone(),
+                read: current_val,
+                instruction: instruction.clone(),
+                written: transition.to_value,
+                movement: transition.movement,
+                to_state: instruction.to_state.clone(),
+                position: previous_position,
+                grew_left,
+                grew_right,
+                blank: self.blank,
+                explanation,
+            });
+        }
+
+        observer(
+            instruction,
+            self.tape_position,
+            self.program.states.name(self.current_state),
+        );
+
+        if self.program.is_final(self.current_state) {
+            StepResult::Halted
         } else {
-            self.frequencies.insert(state.clone(), 1);
+            StepResult::Continued
+        }
+    }
+
+    /// Keeps at least 3 blank cells at each side of the head, inserting them at the front or pushing them at the back.
+    /// Inserted cells move `origin`, so offsets relative to the parsed tape stay the same.
+    /// Returns the number of cells inserted at the front and pushed at the back
+    pub(crate) fn pad(&mut self) -> (usize, usize) {
+        let grew_left = self.pad_left();
+
+        let grew_right = (self.tape_position + 4).saturating_sub(self.tape.len());
+        self.tape.resize(self.tape.len() + grew_right, self.blank);
+
+        (grew_left, grew_right)
+    }
+
+    /// Inserts the blank cells needed to have at least 3 cells left of the head, all at once.
+    /// Returns the number of cells inserted
+    pub(crate) fn pad_left(&mut self) -> usize {
+        let grew_left = 3usize.saturating_sub(self.tape_position);
+
+        if grew_left > 0 {
+            self.tape.push_front(grew_left, self.blank);
+            self.tape_position += grew_left;
+            self.origin += grew_left;
         }
 
-        self.final_states.contains(&self.current_state)
+        grew_left
     }
 
-    /// Returns true if the current state has been reached more times than the given threshold
+    /// Returns true if a (state, value) pair has been executed more times than the given threshold
     pub fn is_infinite_loop(&self, threshold: usize) -> bool {
-        for (_, v) in self.frequencies.iter() {
-            if *v > threshold {
-                return true;
-            }
+        self.max_frequency
+            .is_some_and(|(_, _, frequency)| frequency > threshold)
+    }
+
+    /// Returns the state of the (state, value) pair executed the most times, with the number of times it was executed,
+    /// or None if no step was executed
+    pub fn most_frequent_state(&self) -> Option<(&str, usize)> {
+        self.max_frequency
+            .map(|(state, _, frequency)| (self.program.states.name(state), frequency))
+    }
+
+    /// Counts `n` more executions of a (state, value) pair, keeping the most frequent one up to date.
+    /// Returns the new frequency of the pair
+    pub(crate) fn count_frequency(&mut self, state: StateId, value: bool, n: usize) -> usize {
+        if self.frequencies.len() <= state.index() {
+            let len = self.program.states.len().max(state.index() + 1);
+            self.frequencies.resize(len, [0; 2]);
         }
 
-        false
+        let frequency = &mut self.frequencies[state.index()][value as usize];
+        *frequency += n;
+        let frequency = *frequency;
+
+        if self.max_frequency.is_none_or(|(_, _, max)| frequency > max) {
+            self.max_frequency = Some((state, value, frequency));
+        }
+
+        frequency
     }
 
-    /// Resets the frequencies of the states
+    /// Finds the (state, value) pair executed the most times again, after the frequencies went down.
+    /// Ties go to the first pair by state name and value
+    pub(crate) fn update_max_frequency(&mut self) {
+        self.max_frequency = self
+            .state_frequencies()
+            .max_by(|(a, v, f), (b, w, g)| f.cmp(g).then_with(|| (b, w).cmp(&(a, v))))
+            .and_then(|(state, value, f)| Some((self.program.states.id(state)?, value, f)));
+    }
+
+    /// Returns true if a (state, value) pair has been executed more times than `loop_threshold`,
+    /// which defaults to `DEFAULT_LOOP_THRESHOLD`
+    pub fn is_probably_looping(&self) -> bool {
+        self.is_infinite_loop(self.loop_threshold)
+    }
+
+    /// Returns the number of times each (state, value) pair has been executed, for the pairs executed at least once
+    pub fn state_frequencies(&self) -> impl Iterator<Item = (&str, bool, usize)> {
+        self.frequencies
+            .iter()
+            .enumerate()
+            .flat_map(|(id, counts)| {
+                let name = self.program.states.name(StateId(id as u32));
+                [(name, false, counts[0]), (name, true, counts[1])]
+            })
+            .filter(|(_, _, f)| *f > 0)
+    }
+
+    /// Returns the number of times a (state, value) pair has been executed
+    pub fn frequency(&self, state: &str, value: bool) -> usize {
+        self.program
+            .states
+            .id(state)
+            .and_then(|id| self.frequencies.get(id.index()))
+            .map_or(0, |counts| counts[value as usize])
+    }
+
+    /// Returns the number of steps executed from a state, whatever the value read
+    pub fn total_steps_in_state(&self, state: &str) -> usize {
+        self.frequency(state, false) + self.frequency(state, true)
+    }
+
+    /// Returns a copy of the frequencies of the (state, value) pairs executed at least once, keyed by their names
+    pub fn frequencies(&self) -> HashMap<(String, bool), usize> {
+        self.state_frequencies()
+            .map(|(state, value, f)| ((String::from(state), value), f))
+            .collect()
+    }
+
+    /// Resets the frequencies of the states, without giving back their memory
     pub fn reset_frequencies(&mut self) {
-        self.frequencies = HashMap::new();
+        self.frequencies.fill([0; 2]);
+        self.max_frequency = None;
     }
 
     /// Returns true if the current state is a final state and the motion is to Halt
     pub fn finished(&self) -> bool {
-        self.final_states.contains(&self.current_state)
+        self.program.is_final(self.current_state)
     }
 
-    /// Returns the values of the tape
-    /// (i.e. the number of 1s between each 0)
+    /// Returns the values encoded in the tape, where the number n is written as n + 1 consecutive 1s
+    /// and the numbers are separated by 0s (e.g. `0110110` holds 1 and 1, and `010` holds 0).
+    /// Every run of 1s is a value, so the result is each run length of `values_raw` minus one (see `decode_output`)
     pub fn values(&self) -> Vec<u32> {
-        let tmp: String = self
-            .tape
-            .iter()
-            .map(|v| if *v { "1" } else { "0" })
-            .collect();
+        Self::decode_output(self.tape_trimmed().0)
+    }
 
-        tmp.split('0')
-            .filter_map(|s| {
-                if !s.is_empty() {
-                    Some(s.len() as u32 - 1)
-                } else {
-                    None
-                }
-            })
+    /// Returns the length of each run of consecutive 1s of the tape, from left to right
+    /// (e.g. `0110110` gives 2 and 2). The blank cells around the tape never affect the result
+    pub fn values_raw(&self) -> Vec<u32> {
+        let (tape, _) = self.tape_trimmed();
+
+        tape.split(|v| !*v)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.len() as u32)
             .collect()
     }
 
+    /// Returns the number computed by a machine that finished, decoded like the libraries leave it:
+    /// the number of 1s on the tape, wherever they are (e.g. `sum` on `{111011}` leaves `11001`, which is 3).
+    /// Returns None if the machine is not in a final state
+    pub fn numeric_result(&self) -> Option<u32> {
+        if !self.finished() {
+            return None;
+        }
+
+        Some(self.tape.ones() as u32)
+    }
+
+    /// Returns the numbers computed by a machine that finished and writes several outputs
+    /// encoded like its inputs (see `values`), or an empty list if the machine is not in a final state
+    pub fn numeric_results(&self) -> Vec<u32> {
+        if !self.finished() {
+            return Vec::new();
+        }
+
+        self.values()
+    }
+
     /// Returns the current output of the Turing machine
-    /// (i.e. the number of steps and the number of 1s on the tape,
-    /// or undefined if the Turing machine is in an undefined state)
+    /// (i.e. the number of steps executed so far and the number of 1s on the tape,
+    /// or where the machine got stuck if it is in an undefined state)
     pub fn tape_value(&self) -> TuringOutput {
         if self.is_undefined() {
-            return TuringOutput::Undefined(0);
+            return TuringOutput::Stuck {
+                steps: self.steps,
+                state: String::from(self.current_state()),
+                value: self.tape[self.tape_position],
+            };
         }
 
-        TuringOutput::Defined((0, self.tape.iter().map(|v| if *v { 1 } else { 0 }).sum()))
+        TuringOutput::Defined((self.steps, self.tape.ones() as u32))
     }
 
     /// Returns the final output of the Turing machine directly
-    /// (i.e. keeps calculating the next step until the current state is a final state)
+    /// (i.e. keeps calculating the next step until the current state is a final state).
+    /// The output is `Stuck` if the machine gets stuck, and undefined if `is_probably_looping` becomes true
     pub fn final_result(&mut self) -> TuringOutput {
-        let mut steps = 0;
+        self.final_result_with(|_, _, _| {})
+    }
 
-        while !self.finished() {
-            self.step();
-            steps += 1;
-        }
+    /// Returns the final output of the Turing machine like `final_result`, calling `observer` after every step
+    /// like `step_with`. The observer is called exactly as many times as steps are reported in the output
+    pub fn final_result_with<F>(&mut self, observer: F) -> TuringOutput
+    where
+        F: FnMut(&TuringInstruction, usize, &str),
+    {
+        let result = self.run_checked(usize::MAX, observer, |tm, steps| {
+            if tm.is_probably_looping() {
+                warn!("The machine seems to be in an infinite loop, giving up after {steps} steps");
+                return Some(ExecutionResult::ProbableLoop { steps });
+            }
 
-        self.step();
-        steps += 1;
+            None
+        });
 
-        TuringOutput::Defined((
-            steps,
-            self.tape.iter().map(|v| if *v { 1 } else { 0 }).sum(),
-        ))
+        match result {
+            ExecutionResult::Halted { .. } | ExecutionResult::Stuck { .. } => self.tape_value(),
+            _ => TuringOutput::Undefined(self.steps),
+        }
+    }
+
+    /// Returns an iterator that executes the machine step by step,
+    /// yielding the configuration after each one
+    pub fn iter(&mut self) -> Steps<'_> {
+        Steps::new(self)
     }
 
     /// Returns the value of the tape at the given index, or None if the index is out of bounds
@@ -507,11 +1154,280 @@ impl TuringMachine {
     }
 }
 
+/// Builds the description of a machine from the `///` lines at the top of its code, joined with newlines.
+/// The `///` and the space after it are removed from each line, so the empty ones separate paragraphs.
+/// Returns None if there are no lines or they are all empty
+pub(crate) fn parse_description(record: &str) -> Option<String> {
+    let lines: Vec<&str> = record
+        .lines()
+        .map(|line| {
+            let line = line.trim_start().trim_start_matches("///");
+            line.strip_prefix(' ').unwrap_or(line).trim_end()
+        })
+        .collect();
+    let description = lines.join("\n");
+    let description = description.trim_matches('\n');
+
+    (!description.is_empty()).then(|| String::from(description))
+}
+
+/// Returns the elements of a list between braces, like the final states or the composed libraries,
+/// giving a `StyleWarning` if a comma follows the last one
+pub(crate) fn list_items<'a>(
+    record: Pair<'a, Rule>,
+    warnings: &mut Vec<CompilerWarning>,
+) -> Vec<Pair<'a, Rule>> {
+    let (commas, items): (Vec<_>, Vec<_>) = record
+        .into_inner()
+        .partition(|r| r.as_rule() == Rule::trailing_comma);
+
+    for comma in commas {
+        warn!("Found a comma after the last element of a list");
+
+        warnings.push(CompilerWarning::StyleWarning {
+            position: comma.as_span().into(),
+            message: String::from("The comma after the last element of the list is not needed"),
+        });
+    }
+
+    items
+}
+
+/// Returns the name of a symbol written in the code, without its quotes if it has them
+pub(crate) fn symbol_name(symbol: &str) -> &str {
+    symbol
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(symbol)
+}
+
+/// Returns the value of a `value` record on the binary tape: 1 is true, and 0 and the blanks `_` and `B` are false.
+/// Gives an error for any other symbol, which can only be run by a `SymbolMachine`
+pub(crate) fn binary_value(record: &Pair<Rule>) -> Result<bool, CompilerError> {
+    match symbol_name(record.as_str()) {
+        "1" => Ok(true),
+        "0" | "_" | "B" => Ok(false),
+        symbol => {
+            error!("The symbol {} is not binary", symbol);
+
+            Err(CompilerError::SyntaxError {
+                position: record.into(),
+                message: format!(
+                    "Expected 0, 1 or a blank, found the symbol {}, which needs a SymbolMachine",
+                    symbol
+                ),
+                code: String::from(record.as_str()),
+                expected: Rule::value,
+                found: None,
+            })
+        }
+    }
+}
+
+/// Checks that every value of a `tape` or `instruction` record is in the alphabet given in the code, if any.
+/// The error points at the whole record
+fn check_alphabet(record: &Pair<Rule>, alphabet: Option<&[String]>) -> Result<(), CompilerError> {
+    let Some(alphabet) = alphabet else {
+        return Ok(());
+    };
+
+    let values = record.clone().into_inner().flatten();
+    for value in values.filter(|r| r.as_rule() == Rule::value) {
+        let symbol = symbol_name(value.as_str());
+
+        if !alphabet.iter().any(|s| s == symbol) {
+            error!("The symbol {} is not in the alphabet", symbol);
+
+            return Err(CompilerError::SyntaxError {
+                position: record.into(),
+                message: format!("The symbol {} is not in the alphabet", symbol),
+                code: String::from(record.as_str()),
+                expected: Rule::alphabet,
+                found: Some(Rule::value),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Encodes the decimal numbers of a `numbers` record on the tape like `encode_input`,
+/// giving an error if the tape would have more than `max_cells` cells
+fn parse_numbers(record: &Pair<Rule>, max_cells: usize) -> Result<Vec<bool>, CompilerError> {
+    let values = record
+        .clone()
+        .into_inner()
+        .map(|n| {
+            n.as_str().parse::<u32>().map_err(|_| {
+                error!("The number {} is too large", n.as_str());
+
+                CompilerError::SyntaxError {
+                    position: (&n).into(),
+                    message: format!(
+                        "The number {} is too large, the largest one is {}",
+                        n.as_str(),
+                        u32::MAX
+                    ),
+                    code: String::from(n.as_str()),
+                    expected: Rule::number,
+                    found: None,
+                }
+            })
+        })
+        .collect::<Result<Vec<u32>, _>>()?;
+    debug!("The tape encodes the numbers {:?}", values);
+
+    let cells = values.iter().map(|n| *n as usize + 2).sum::<usize>() - 1;
+    if cells > max_cells {
+        error!("The numbers need {} cells", cells);
+
+        return Err(CompilerError::SyntaxError {
+            position: record.as_span().into(),
+            message: format!(
+                "The numbers need {} cells, more than the {} a tape can have",
+                cells, max_cells
+            ),
+            code: String::from(record.as_str()),
+            expected: Rule::numbers,
+            found: None,
+        });
+    }
+
+    TuringMachine::encode_input(&values)
+}
+
+/// Parses the cells of a `tape` record, with the index of the cell marked as the head (if any)
+pub(crate) fn parse_tape(
+    record: Pair<Rule>,
+    max_cells: usize,
+) -> Result<(Vec<bool>, Option<usize>), CompilerError> {
+    let mut tape: Vec<bool> = Vec::new();
+    let mut head: Option<usize> = None;
+
+    if let Some(mixed) = record
+        .clone()
+        .into_inner()
+        .find(|r| r.as_rule() == Rule::mixed)
+    {
+        error!("The tape mixes numbers and cells");
+
+        return Err(CompilerError::SyntaxError {
+            position: (&mixed).into(),
+            message: String::from(
+                "A tape is either some numbers separated by commas or its cells, not both",
+            ),
+            code: String::from(record.as_str()),
+            expected: Rule::tape,
+            found: Some(Rule::mixed),
+        });
+    }
+    if let Some(numbers) = record
+        .clone()
+        .into_inner()
+        .find(|r| r.as_rule() == Rule::numbers)
+    {
+        return Ok((parse_numbers(&numbers, max_cells)?, None));
+    }
+
+    for r in record.into_inner() {
+        match r.as_rule() {
+            Rule::value => tape.push(binary_value(&r)?),
+            Rule::repetition => {
+                let mut inner = r.clone().into_inner();
+                let value = binary_value(&inner.next().unwrap())?;
+                let count = inner.next().unwrap();
+
+                let n = match count.as_str().parse::<usize>() {
+                    Ok(n) if n > 0 && n <= max_cells.saturating_sub(tape.len()) => n,
+                    parsed => {
+                        error!("The count of {} is not valid", r.as_str());
+
+                        let message = match parsed {
+                            Ok(0) => String::from("A value cannot be repeated 0 times"),
+                            _ => format!(
+                                "The repetition {} makes the tape longer than {} cells",
+                                r.as_str(),
+                                max_cells
+                            ),
+                        };
+
+                        return Err(CompilerError::SyntaxError {
+                            position: r.as_span().into(),
+                            message,
+                            code: String::from(r.as_str()),
+                            expected: Rule::count,
+                            found: None,
+                        });
+                    }
+                };
+
+                tape.extend(std::iter::repeat_n(value, n));
+            }
+            Rule::head => {
+                if head.is_some() {
+                    error!("The head was marked more than once");
+
+                    return Err(CompilerError::SyntaxError {
+                        position: r.line_col().into(),
+                        message: String::from(
+                            "Only one cell of the tape can be marked as the head",
+                        ),
+                        code: String::from(r.as_str()),
+                        expected: Rule::value,
+                        found: Some(Rule::head),
+                    });
+                }
+
+                head = Some(tape.len());
+                tape.push(binary_value(&r.into_inner().next().unwrap())?);
+            }
+            _ => warn!(
+                "Unhandled: ({:?}, {})",
+                r.as_rule(),
+                r.into_inner().as_str()
+            ),
+        }
+    }
+
+    Ok((tape, head))
+}
+
+impl PartialEq for TuringMachine {
+    /// Two machines are equal if they have the same instructions and final states (in any order),
+    /// and they are in the same configuration (current state, head position and tape).
+    ///
+    /// The code, the description, the composed libraries, the step counter, the frequencies
+    /// and the debugging state are ignored, so machines compiled from differently formatted
+    /// but equivalent code are equal
+    fn eq(&self, other: &Self) -> bool {
+        let mut final_states: Vec<&String> = self.final_states().iter().collect();
+        let mut other_final_states: Vec<&String> = other.final_states().iter().collect();
+        final_states.sort();
+        final_states.dedup();
+        other_final_states.sort();
+        other_final_states.dedup();
+
+        self.instructions() == other.instructions()
+            && final_states == other_final_states
+            && self.current_state() == other.current_state()
+            && self.tape_position == other.tape_position
+            && self.tape == other.tape
+    }
+}
+
+impl Eq for TuringMachine {}
+
 impl Display for TuringMachine {
+    /// Displays the tape with the head under it. The alternate form (`{:#}`) writes the blank cells as `_`
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut tmp2 = String::new();
         for (i, v) in self.tape.iter().enumerate() {
-            write!(f, "{} ", if *v { "1" } else { "0" }).unwrap();
+            let value = match *v {
+                v if v == self.blank && f.alternate() => "_",
+                true => "1",
+                false => "0",
+            };
+            write!(f, "{} ", value).unwrap();
 
             if i == self.tape_position {
                 tmp2 += "^ ";
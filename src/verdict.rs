@@ -0,0 +1,50 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::TuringMachine;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+/// The answer of a machine that decides a problem, given by the kind of final state it halted in
+pub enum Verdict {
+    /// The machine halted in one of the states given with `A = {...};`
+    Accept,
+
+    /// The machine halted in one of the states given with `R = {...};`
+    Reject,
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Accept => write!(f, "Accept"),
+            Self::Reject => write!(f, "Reject"),
+        }
+    }
+}
+
+impl TuringMachine {
+    /// Returns the final states that accept the input, given with `A = {...};`
+    pub fn accepting_states(&self) -> &[String] {
+        &self.program.accepting_states
+    }
+
+    /// Returns the final states that reject the input, given with `R = {...};`
+    pub fn rejecting_states(&self) -> &[String] {
+        &self.program.rejecting_states
+    }
+
+    /// Returns whether the machine accepted or rejected its input, once it is in an accepting or rejecting state.
+    /// Returns None if it is not in a final state, or if it is in a final state given with `F = {...};`
+    pub fn verdict(&self) -> Option<Verdict> {
+        let state = self.current_state();
+
+        if self.accepting_states().iter().any(|s| s == state) {
+            Some(Verdict::Accept)
+        } else if self.rejecting_states().iter().any(|s| s == state) {
+            Some(Verdict::Reject)
+        } else {
+            None
+        }
+    }
+}
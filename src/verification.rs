@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ExecutionResult, TuringMachine};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// How the run of a machine on an input ended, in a `VerificationReport`
+pub enum Outcome {
+    /// The machine halted
+    Halted,
+
+    /// There is no instruction for the state and the value read, and the state is not final
+    Stuck { state: String, value: bool },
+
+    /// An instruction tried to move the head left of the first cell of a left-bounded tape
+    OutOfTape { state: String, value: bool },
+
+    /// The tape would have used more cells than its limit
+    TapeLimitExceeded { cells: usize },
+
+    /// A configuration repeated, so the machine is in an infinite loop (only if `detect_cycles` is enabled)
+    InfiniteLoop,
+
+    /// The machine executed the maximum number of steps without halting
+    StepLimitReached,
+
+    /// The input could not be written on the tape
+    InvalidInput { message: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// The run of a machine on one of the inputs of a `VerificationReport`
+pub struct InputVerification {
+    /// The numbers given as input
+    pub input: Vec<u32>,
+
+    /// How the run ended
+    pub outcome: Outcome,
+
+    /// The number of steps executed
+    pub steps: usize,
+
+    /// The number computed by the machine (see `numeric_result`), only if it halted
+    pub output: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// The result of running a machine on several inputs with `verify_halts`
+pub struct VerificationReport {
+    /// The maximum number of steps of each run
+    pub max_steps: usize,
+
+    /// The runs, in the order of the inputs
+    pub results: Vec<InputVerification>,
+}
+
+impl VerificationReport {
+    /// Returns true if the machine halted on every input
+    pub fn all_halted(&self) -> bool {
+        self.results.iter().all(|r| r.outcome == Outcome::Halted)
+    }
+
+    /// Returns the runs that did not halt
+    pub fn failures(&self) -> impl Iterator<Item = &InputVerification> {
+        self.results.iter().filter(|r| r.outcome != Outcome::Halted)
+    }
+}
+
+impl TuringMachine {
+    /// Runs a copy of the machine on each input, written with `set_input_values`, for up to `max_steps` steps,
+    /// and reports how each run ended along with its output. The machine itself is left untouched
+    pub fn verify_halts<I>(&self, inputs: I, max_steps: usize) -> VerificationReport
+    where
+        I: IntoIterator<Item = Vec<u32>>,
+    {
+        let results = inputs
+            .into_iter()
+            .map(|input| {
+                let mut tm = self.clone();
+
+                if let Err(error) = tm.set_input_values(&input) {
+                    return InputVerification {
+                        input,
+                        outcome: Outcome::InvalidInput {
+                            message: error.message(),
+                        },
+                        steps: 0,
+                        output: None,
+                    };
+                }
+
+                let result = tm.run(max_steps);
+                let outcome = match &result {
+                    ExecutionResult::Halted { .. } => Outcome::Halted,
+                    ExecutionResult::Stuck { state, value, .. } => Outcome::Stuck {
+                        state: state.clone(),
+                        value: *value,
+                    },
+                    ExecutionResult::OutOfTape { state, value, .. } => Outcome::OutOfTape {
+                        state: state.clone(),
+                        value: *value,
+                    },
+                    ExecutionResult::TapeLimitExceeded { cells, .. } => {
+                        Outcome::TapeLimitExceeded { cells: *cells }
+                    }
+                    ExecutionResult::InfiniteLoop { .. } => Outcome::InfiniteLoop,
+                    // `run` does not stop for breakpoints, watchpoints or predicates
+                    _ => Outcome::StepLimitReached,
+                };
+
+                InputVerification {
+                    input,
+                    output: (outcome == Outcome::Halted)
+                        .then(|| tm.numeric_result())
+                        .flatten(),
+                    outcome,
+                    steps: result.steps(),
+                }
+            })
+            .collect();
+
+        VerificationReport { max_steps, results }
+    }
+}
@@ -14,6 +14,58 @@ pub enum CompilerWarning {
         state: String,
         value_from: bool,
     },
+
+    /// Warning for when an instruction given in the code overwrites one of a composed library
+    LibraryOverride {
+        position: ErrorPosition,
+        /// The state that is being overwritten
+        state: String,
+        value_from: bool,
+        /// The name of the library whose instruction is overwritten
+        library: String,
+    },
+
+    /// Warning for when an instruction can never be executed, since its state is not reachable from the initial state
+    DeadInstruction {
+        position: ErrorPosition,
+        /// The state of the instruction
+        state: String,
+        value: bool,
+    },
+
+    /// Warning for when a state is listed more than once in the final, accepting or rejecting states
+    RepeatedFinalState {
+        position: ErrorPosition,
+        /// The state listed again
+        state: String,
+    },
+
+    /// Warning for when a function defined in the code has the name of a built-in library, which it replaces
+    LibraryShadowed {
+        position: ErrorPosition,
+        /// The name of the library
+        library: String,
+    },
+
+    /// Warning for when a composed library overwrites an instruction of a library composed before it,
+    /// since both use the same state. Composing them with an alias, like `compose = {sum as s};`, avoids it
+    CompositionCollision {
+        position: ErrorPosition,
+        /// The state that is being overwritten
+        state: String,
+        value_from: bool,
+        /// The library whose instruction is overwritten, as it is written in `compose`
+        library: String,
+        /// The library composed later, as it is written in `compose`
+        composed: String,
+    },
+
+    /// Warning for code that is accepted but written in a way the formatter changes, like a comma after the last state
+    StyleWarning {
+        position: ErrorPosition,
+        /// What is wrong with the code
+        message: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -33,11 +85,22 @@ pub enum CompilerError {
     FileRuleError {
         error: Box<pest::error::Error<Rule>>,
     },
+
+    /// An error when importing a file with `import "sum.tm";`: the file could not be read or compiled,
+    /// it imports itself through other files, or no `Resolver` was given to read it
+    ImportError {
+        /// The path of the file, as written in the code
+        path: String,
+        position: ErrorPosition,
+        /// Why the file could not be imported
+        source: String,
+    },
 }
 
 impl CompilerError {
     /// Log the error to the console
-    /// with the format `Syntax error At position {position}: {message} - Expected {expected:?}, got {found:?}` or `Syntax error: {error}` if the error is a `FileRuleError`
+    /// with the format `Syntax error At position {position}: {message} - Expected {expected}, got {found}` or `Syntax error: {error}` if the error is a `FileRuleError`.
+    /// The rules are named with `rule_name`
     pub fn log_error(&self) {
         match self {
             CompilerError::SyntaxError {
@@ -48,23 +111,33 @@ impl CompilerError {
                 ..
             } => {
                 error!(
-                    "Syntax error At position {position}: {message} - Expected {expected:?}, got {:?}",
-                    found.unwrap_or(Rule::EOI)
+                    "Syntax error At position {position}: {message} - Expected {}, got {}",
+                    rule_name(*expected),
+                    rule_name(found.unwrap_or(Rule::EOI))
                 );
             }
             CompilerError::FileRuleError { error, .. } => {
-                error!("Syntax error: {}", error);
+                error!("Syntax error: {}", renamed(error));
+            }
+            CompilerError::ImportError { position, .. } => {
+                error!("Import error At position {position}: {}", self.message());
             }
         }
     }
 
-    /// Get the expected message. If the error is a `FileRuleError`, the message will be extracted from `pest::error::Error`, otherwise it will be `Expected {expected:?}, got {found:?}`
+    /// Get the expected message. If the error is a `FileRuleError`, the message will be extracted from `pest::error::Error`, otherwise it will be `Expected {expected}, found {found}`.
+    /// The rules are named with `rule_name`
     pub fn get_message_expected(&self) -> String {
         match &self {
             CompilerError::SyntaxError {
                 expected, found, ..
-            } => format!("Expected {:?}, found {:?}", expected, found),
-            CompilerError::FileRuleError { error } => String::from(error.variant.message()),
+            } => format!(
+                "Expected {}, found {}",
+                rule_name(*expected),
+                found.map_or("nothing", rule_name)
+            ),
+            CompilerError::FileRuleError { error } => renamed(error).variant.message().to_string(),
+            CompilerError::ImportError { .. } => self.message(),
         }
     }
 
@@ -73,6 +146,7 @@ impl CompilerError {
         match self {
             CompilerError::SyntaxError { code, .. } => code.clone(),
             CompilerError::FileRuleError { error, .. } => String::from(error.line()),
+            CompilerError::ImportError { path, .. } => format!("import \"{}\";", path),
         }
     }
 
@@ -80,7 +154,12 @@ impl CompilerError {
     pub fn message(&self) -> String {
         match self {
             CompilerError::SyntaxError { message, .. } => String::from(message),
-            CompilerError::FileRuleError { error, .. } => error.variant.message().to_string(),
+            CompilerError::FileRuleError { error, .. } => {
+                renamed(error).variant.message().to_string()
+            }
+            CompilerError::ImportError { path, source, .. } => {
+                format!("Could not import the file {}: {}", path, source)
+            }
         }
     }
 
@@ -89,6 +168,7 @@ impl CompilerError {
         match self {
             CompilerError::SyntaxError { position, .. } => position.start.0,
             CompilerError::FileRuleError { .. } => 0,
+            CompilerError::ImportError { position, .. } => position.start.0,
         }
     }
 
@@ -106,6 +186,7 @@ impl CompilerError {
                     end: Some((line2 - 1, col2)),
                 },
             },
+            CompilerError::ImportError { position, .. } => *position,
         }
     }
 
@@ -119,6 +200,7 @@ impl CompilerError {
                 }
                 _ => Rule::EOI,
             },
+            CompilerError::ImportError { .. } => Rule::import,
         }
     }
 
@@ -132,10 +214,83 @@ impl CompilerError {
                 }
                 _ => None,
             },
+            CompilerError::ImportError { .. } => None,
         }
     }
 }
 
+/// Returns a phrase naming a rule of the grammar for the messages of the errors, like "the tape section".
+/// The rules that are never shown to the users keep their name in the grammar
+pub fn rule_name(rule: Rule) -> &'static str {
+    match rule {
+        Rule::EOI => "the end of the code",
+        Rule::state => "a state like q0",
+        Rule::value => "a value, 0, 1 or the blank _",
+        Rule::wildcard => "the wildcard *",
+        Rule::number => "a decimal number",
+        Rule::numbers => "some decimal numbers separated by commas, like 3, 5",
+        Rule::mixed => "numbers mixed with cells",
+        Rule::count => "the number of times the value is repeated",
+        Rule::repetition => "a repeated value like 1^5",
+        Rule::quoted => "a symbol between quotes, like \"ab\"",
+        Rule::movement => "a movement like R, L or H",
+        Rule::description_line => "a line of the description, like /// a + b",
+        Rule::description => "a description like /// a + b",
+        Rule::doc => "a doc comment like /// carry the borrow left",
+        Rule::head => "the head of the tape, like [1]",
+        Rule::tape => "the tape section",
+        Rule::final_state => "a final states declaration",
+        Rule::initial_state => "an initial state declaration",
+        Rule::accepting_states => "an accepting states declaration",
+        Rule::rejecting_states => "a rejecting states declaration",
+        Rule::trailing_comma => "a comma after the last element of a list",
+        Rule::function_name => "a library name like sum",
+        Rule::alias => "an alias like s",
+        Rule::composed_library => "a composed library like sum as s",
+        Rule::function => "a function like fn increment { ... }",
+        Rule::composition => "a composition like compose = {sum};",
+        Rule::tape_mode => "a tape mode declaration like tape = bounded;",
+        Rule::blank => "a blank declaration like blank = 0;",
+        Rule::import => "an import like import \"sum.tm\";",
+        Rule::name => "a name declaration like name = \"adder\";",
+        Rule::mode => "a tape mode, bounded or unbounded",
+        Rule::instruction => "an instruction like (q0, 1, 0, R, q1)",
+        Rule::instructions => "a list of instructions",
+        Rule::instruction_lines => "lines with only instructions and comments",
+        Rule::machine_name => "a machine name like double",
+        Rule::machine_body => "the code of a machine",
+        Rule::machine => "a machine block like machine double { ... }",
+        Rule::tapes => "a number of tapes like tapes = 2;",
+        Rule::tape_values => "a value for each tape, like [1, 0]",
+        Rule::movements => "a movement for each tape, like [R, L]",
+        Rule::multi_instruction => "an instruction like (q0, [1, 0], [0, 1], [R, L], q1)",
+        Rule::symbol => "a symbol like a or #",
+        Rule::alphabet => "an alphabet declaration like alphabet = {a, b};",
+        Rule::mark => "a blank symbol declaration like mark = _;",
+        Rule::symbol_head => "the head of the tape, like [a]",
+        Rule::symbol_tape => "the tape section",
+        Rule::symbol_instruction => "an instruction like (q0, a, b, R, q1)",
+        Rule::file => "file",
+        Rule::machines_start => "machines_start",
+        Rule::machines_file => "machines_file",
+        Rule::tape_count => "tape_count",
+        Rule::multi_file => "multi_file",
+        Rule::symbol_file => "symbol_file",
+        Rule::definition => "definition",
+        Rule::multi_definition => "multi_definition",
+        Rule::symbol_definition => "symbol_definition",
+        Rule::WHITESPACE => "WHITESPACE",
+        Rule::COMMENT => "COMMENT",
+    }
+}
+
+/// Returns a copy of a pest error with its rules named with `rule_name`
+fn renamed(error: &pest::error::Error<Rule>) -> pest::error::Error<Rule> {
+    error
+        .clone()
+        .renamed_rules(|rule| String::from(rule_name(*rule)))
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// A struct to store the position of an error
 pub struct ErrorPosition {